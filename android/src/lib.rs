@@ -6,7 +6,9 @@ use rsaber_lib::Main;
 use rsaber_lib::asset::EmbedAssetManager;
 use rsaber_lib::openxr;
 use rsaber_lib::output::XROutput;
-use rsaber_lib::util::Stats;
+use rsaber_lib::util::{Settings, Stats};
+
+const REPLAY_DIR: &str = "replays";
 
 #[unsafe(no_mangle)]
 fn android_main(app: AndroidApp) {
@@ -20,7 +22,11 @@ fn android_main(app: AndroidApp) {
     
     let output = XROutput::new(xr_entry);
     let stats = Stats::new("");
-    let main = Main::new(asset_mgr, output.get_info(), stats);
+    let settings = match app.internal_data_path() {
+        Some(path) => Settings::new().with_replay_dir(path.join(REPLAY_DIR)),
+        None => Settings::new(),
+    };
+    let main = Main::new(asset_mgr, output.get_info(), stats, settings);
 
     let mut terminate = false;
 
@@ -0,0 +1,53 @@
+// Ribbon shader
+
+// Input
+
+#UNI#
+
+struct VertexIn {
+    #VIEW_INDEX_DEF#
+    // Per-vertex
+    @location(0) pos: vec3<f32>, // x: curve parameter (0..1), y: side (-0.5..0.5)
+    // Per-instance
+    @location(9) color: vec3<f32>,
+    @location(10) alpha: f32,
+    @location(11) width: f32,
+    @location(12) p0: vec3<f32>,
+    @location(13) p1: vec3<f32>,
+    @location(14) p2: vec3<f32>,
+    @location(15) p3: vec3<f32>,
+}
+
+// Implementation
+
+struct VertexOut {
+    @builtin(position) pos: vec4<f32>,
+    @location(0) color: vec3<f32>,
+    @location(1) alpha: f32,
+}
+
+@vertex fn vs_main(in: VertexIn) -> VertexOut {
+    // Evaluate cubic bezier curve and its tangent.
+
+    let t = in.pos.x;
+    let u = 1 - t;
+
+    let curve_pos = u * u * u * in.p0 + 3 * u * u * t * in.p1 + 3 * u * t * t * in.p2 + t * t * t * in.p3;
+    let tangent = 3 * u * u * (in.p1 - in.p0) + 6 * u * t * (in.p2 - in.p1) + 3 * t * t * (in.p3 - in.p2);
+
+    // The ribbon is always facing the camera.
+
+    let side_dir = normalize(cross(tangent, uni.cam_pos - curve_pos));
+    let pos = curve_pos + side_dir * in.pos.y * in.width;
+
+    var out: VertexOut;
+    out.pos = uni.view_m[#VIEW_INDEX_VAL#] * vec4(pos, 1);
+    out.color = in.color;
+    out.alpha = in.alpha;
+
+    return out;
+}
+
+@fragment fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    return vec4(in.color, in.alpha);
+}
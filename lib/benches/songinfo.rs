@@ -0,0 +1,79 @@
+// Loading of the maps in testmap, run with: cargo bench -p rsaber_lib --features bench
+// The files are read into memory beforehand, so only the parsing is measured.
+
+use std::collections::HashMap;
+use std::fs;
+use std::hint::black_box;
+use std::io::{Cursor, Read};
+use std::sync::Arc;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+
+use rsaber_lib::asset::{AssetError, AssetFileBox, AssetFileTrait, AssetManagerRc, AssetManagerTrait, AssetResult};
+use rsaber_lib::bench::SongInfo;
+
+const PREFIX: &str = "testmap";
+
+struct MemAssetManager {
+    files: HashMap<String, String>,
+}
+
+impl MemAssetManager {
+    fn new(dir: &str) -> Self {
+        let files = fs::read_dir(format!("{}/{}", PREFIX, dir)).expect("Unable to read dir").map(|entry| {
+            let entry = entry.expect("Unable to read dir entry");
+            let content = fs::read_to_string(entry.path()).expect("Unable to read file");
+
+            (format!("/{}", entry.file_name().to_string_lossy()), content)
+        }).collect();
+
+        Self {
+            files,
+        }
+    }
+}
+
+impl AssetManagerTrait for MemAssetManager {
+    fn open(&self, name: &str) -> AssetResult<AssetFileBox> {
+        let content = self.files.get(name).ok_or(AssetError::NotFound)?;
+        Ok(Box::new(MemAssetFile(content.clone())))
+    }
+}
+
+struct MemAssetFile(String);
+
+impl AssetFileTrait for MemAssetFile {
+    fn read(&self) -> AssetResult<Box<dyn Read + Send + Sync>> {
+        Ok(Box::new(Cursor::new(self.0.clone().into_bytes())))
+    }
+
+    fn read_str(&self) -> AssetResult<String> {
+        Ok(self.0.clone())
+    }
+}
+
+fn bench_load(c: &mut Criterion) {
+    let mut dirs: Vec<_> = fs::read_dir(PREFIX).expect("Unable to read dir").map(|entry| {
+        entry.expect("Unable to read dir entry").file_name().to_string_lossy().into_owned()
+    }).collect();
+    dirs.sort();
+
+    let mut group = c.benchmark_group("load");
+
+    for dir in dirs {
+        let asset_mgr: AssetManagerRc = Arc::new(MemAssetManager::new(&dir));
+
+        group.bench_function(&dir, |b| b.iter(|| {
+            let song_info = SongInfo::load(Arc::clone(&asset_mgr)).expect("Unable to load info");
+
+            for beatmap_info in song_info.get_beatmap_infos() {
+                black_box(beatmap_info.load().expect("Unable to load beatmap"));
+            }
+        }));
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_load);
+criterion_main!(benches);
@@ -9,8 +9,9 @@ use bytemuck::NoUninit;
 use rubato::{Fft, FixedSync, Resampler};
 use rubato::audioadapter_buffers::direct::InterleavedSlice;
 use symphonia::core::errors::Error as symphonia_Error;
-use symphonia::core::formats::TrackType;
+use symphonia::core::formats::{SeekMode, SeekTo, TrackType};
 use symphonia::core::io::{MediaSourceStream, ReadOnlySource};
+use symphonia::core::units::Time;
 
 use crate::asset::AssetFileBox;
 use crate::audio::{AudioInput, AudioSource, AudioSourceState};
@@ -18,14 +19,26 @@ use crate::circbuf::{self, Receiver};
 
 const BUF_LEN: u16 = 3; // [s]
 const RATE_CONV_CHUNK: usize = 1024;
+const SPEED_RATE_STEP: f32 = 100.0; // If the speed is changed, then the sample rate is rounded to keep the rate converter small [Hz]
 
 pub struct AudioFile {
     asset_file: AssetFileBox,
+    start: f32,
+    speed: f32,
     inner: InnerRc,
 }
 
 impl AudioFile {
     pub fn new(asset_file: AssetFileBox) -> (Self, AudioFileHandle) {
+        Self::new_at(asset_file, 0.0, 1.0)
+    }
+
+    // Playback is started from start [s] of the file. If speed is not 1, then the pitch is changed as well
+    // (the samples are only resampled).
+
+    pub fn new_at(asset_file: AssetFileBox, start: f32, speed: f32) -> (Self, AudioFileHandle) {
+        assert!(start >= 0.0 && speed > 0.0);
+
         let inner = Inner {
             state: Atomic::new(State::Paused),
             at_eof: AtomicBool::new(false),
@@ -34,6 +47,8 @@ impl AudioFile {
 
         let input = Self {
             asset_file,
+            start,
+            speed,
             inner: Arc::clone(&inner_rc),
         };
 
@@ -43,11 +58,11 @@ impl AudioFile {
     }
 
     fn build(self, channels: u16, sample_rate: u32) -> AudioFileSource {
-        let rx = Self::build_impl(self.asset_file, channels, sample_rate);
+        let rx = Self::build_impl(self.asset_file, self.start, self.speed, channels, sample_rate);
         AudioFileSource::new(self.inner, rx)
     }
 
-    fn build_impl(asset_file: AssetFileBox, channels: u16, sample_rate: u32) -> Receiver<f32> {
+    fn build_impl(asset_file: AssetFileBox, start: f32, speed: f32, channels: u16, sample_rate: u32) -> Receiver<f32> {
         let channels = channels as usize;
 
         // Setup circular buffer.
@@ -87,22 +102,52 @@ impl AudioFile {
             Err(_) => return rx,
         };
 
-        // Determine, if we need rate conversion.
+        // Determine, if we need rate conversion. Changing the speed is done by pretending a different
+        // sample rate of the decoded data.
 
         let decoder_sample_rate = codec_params.sample_rate.unwrap();
 
-        let rate_conv_opt = if decoder_sample_rate != sample_rate {
-            let rate_conv = Fft::<f32>::new(decoder_sample_rate as usize, sample_rate as usize, RATE_CONV_CHUNK, 1, channels, FixedSync::Both).expect("Unable to create sample rate converter");
+        let source_sample_rate = if speed != 1.0 {
+            ((decoder_sample_rate as f32 * speed / SPEED_RATE_STEP).round() * SPEED_RATE_STEP) as u32
+        } else {
+            decoder_sample_rate
+        };
+
+        let rate_conv_opt = if source_sample_rate != sample_rate {
+            let rate_conv = Fft::<f32>::new(source_sample_rate as usize, sample_rate as usize, RATE_CONV_CHUNK, 1, channels, FixedSync::Both).expect("Unable to create sample rate converter");
             Some(rate_conv)
         } else {
             None
         };
 
+        let track_id = track.id;
+        let time_base_opt = track.time_base;
+
+        // Seek to start. The seek is ending up before start, so the frames until start are dropped after decoding.
+        // If seeking is not supported, then everything is decoded from the beginning.
+
+        let mut skip_ts = 0.0;
+
+        if start > 0.0 {
+            let seek_to = SeekTo::Time {
+                time: Time::try_from_secs_f64(start.into()).expect("Invalid start"),
+                track_id: Some(track_id),
+            };
+
+            skip_ts = match format.seek(SeekMode::Accurate, seek_to) {
+                Ok(seeked_to) => {
+                    decoder.reset();
+                    time_base_opt.map_or(0.0, |time_base| (seeked_to.required_ts.get() - seeked_to.actual_ts.get()) as f64 * f64::from(time_base))
+                },
+                Err(_) => start.into(),
+            };
+        }
+
+        let mut skip_len = (skip_ts * decoder_sample_rate as f64).round() as usize; // [frame]
+
         // Start decoder thread. At the end, the decoded data should be converted
         // to interleaved samples, since this is the format expected by the audio engine.
 
-        let track_id = track.id;
-
         thread::spawn(move || {
             let mut do_decode = || {
                 loop {
@@ -134,14 +179,27 @@ impl AudioFile {
                         Err(_) => break None,
                     };
 
-                    let decoded_len = decoded.frames();
+                    let mut decoded_len = decoded.frames();
                     if decoded_len == 0 {
                         continue;
                     }
 
+                    // Drop frames before start.
+
+                    if skip_len >= decoded_len {
+                        skip_len -= decoded_len;
+                        continue;
+                    }
+
                     let mut buf = vec![0.0; decoded.samples_interleaved()];
                     decoded.copy_to_slice_interleaved(&mut buf);
 
+                    if skip_len > 0 {
+                        buf.drain(..(skip_len * channels));
+                        decoded_len -= skip_len;
+                        skip_len = 0;
+                    }
+
                     break Some((buf, decoded_len));
                 }
             };
@@ -354,7 +412,6 @@ impl AudioFileHandle {
         self.inner.state.store(State::Playing, Ordering::Relaxed);
     }
 
-    #[allow(dead_code)] // TODO: remove dead_code once it is used
     pub fn pause(&self) {
         self.inner.state.store(State::Paused, Ordering::Relaxed);
     }
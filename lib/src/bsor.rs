@@ -0,0 +1,639 @@
+// BS Open Replay format, see https://github.com/BeatLeader/BS-Open-Replay .
+// Notes regarding the format:
+// - All values are little-endian, strings are prefixed by their length in bytes (UTF-8).
+// - Positions and rotations are in the coordinate system of Unity (y is up, z is forward, left-handed).
+
+use std::io::{Error, ErrorKind, Read, Result, Write};
+
+use cgmath::{Deg, Quaternion, Rotation3, Vector3};
+
+const MAGIC: i32 = 0x442d3d69;
+const VERSION: u8 = 1;
+
+const SECTION_INFO: u8 = 0;
+const SECTION_FRAMES: u8 = 1;
+const SECTION_NOTES: u8 = 2;
+const SECTION_WALLS: u8 = 3;
+const SECTION_HEIGHTS: u8 = 4;
+const SECTION_PAUSES: u8 = 5;
+
+// Scoring types of the note ids.
+const SCORING_NO_SCORE: i32 = 0; // Bombs
+const SCORING_NORMAL: i32 = 1;
+const SCORING_ARC_HEAD: i32 = 2;
+const SCORING_ARC_TAIL: i32 = 3;
+const SCORING_CHAIN_HEAD: i32 = 4;
+const SCORING_CHAIN_LINK: i32 = 5;
+const SCORING_CHAIN_HEAD_ARC_TAIL: i32 = 7;
+const SCORING_CHAIN_LINK_ARC_HEAD: i32 = 8;
+
+const COLOR_NONE: i32 = -1; // Bombs
+const CUT_DIR_ANY: i32 = 8;
+const CUT_DIR_NONE: i32 = 9; // Bombs
+
+const STRING_LEN_MAX: usize = 1 << 20; // Longer strings are treated as corrupted data [bytes]
+
+pub struct Replay {
+    pub info: ReplayInfo,
+    pub frames: Vec<ReplayFrame>,
+    pub notes: Vec<ReplayNote>,
+    pub walls: Vec<ReplayWall>,
+    pub heights: Vec<ReplayHeight>,
+    pub pauses: Vec<ReplayPause>,
+}
+
+#[derive(Default)]
+pub struct ReplayInfo {
+    pub version: String, // Of the recording application
+    pub game_version: String,
+    pub timestamp: String, // Unix time of the recording [s]
+    pub player_id: String,
+    pub player_name: String,
+    pub platform: String,
+    pub tracking_system: String,
+    pub hmd: String,
+    pub controller: String,
+    pub hash: String, // Of the song, see SongInfo::calc_hash
+    pub song_name: String,
+    pub mapper: String,
+    pub difficulty: String, // E.g. ExpertPlus
+    pub score: i32,
+    pub mode: String, // Characteristic, e.g. Standard
+    pub environment: String,
+    pub modifiers: String, // Comma separated, e.g. NF for No-Fail
+    pub jump_distance: f32, // [m]
+    pub left_handed: bool,
+    pub height: f32, // Of the player [m]
+    pub start_time: f32, // [s]
+    pub fail_time: f32, // 0, if the song is not failed [s]
+    pub speed: f32, // 0, if the song is played at normal speed
+}
+
+#[derive(Clone, Copy)]
+pub struct ReplayFrame {
+    pub time: f32, // Song timestamp [s]
+    pub fps: i32,
+    pub head: ReplayPose,
+    pub left: ReplayPose,
+    pub right: ReplayPose,
+}
+
+#[derive(Clone, Copy)]
+pub struct ReplayPose {
+    pub pos: Vector3<f32>,
+    pub rot: Quaternion<f32>,
+}
+
+#[derive(Clone, Copy)]
+pub struct ReplayNote {
+    pub note_id: i32, // See calc_note_id
+    pub event_time: f32, // [s]
+    pub spawn_time: f32, // [s]
+    pub event_type: ReplayNoteEvent,
+    pub cut_info_opt: Option<ReplayCutInfo>, // Set for good and bad cuts
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ReplayNoteEvent {
+    Good,
+    Bad,
+    Miss,
+    Bomb,
+}
+
+#[derive(Clone, Copy)]
+pub struct ReplayCutInfo {
+    pub speed_ok: bool,
+    pub direction_ok: bool,
+    pub saber_type_ok: bool,
+    pub was_cut_too_soon: bool,
+    pub saber_speed: f32, // [m/s]
+    pub saber_dir: Vector3<f32>,
+    pub saber_type: i32, // 0: left, 1: right
+    pub time_deviation: f32, // [s]
+    pub cut_dir_deviation: f32, // [deg]
+    pub cut_point: Vector3<f32>,
+    pub cut_normal: Vector3<f32>,
+    pub cut_distance_to_center: f32, // [m]
+    pub cut_angle: f32, // [deg]
+    pub before_cut_rating: f32, // 1, if the pre-swing is full
+    pub after_cut_rating: f32, // 1, if the post-swing is full
+}
+
+#[derive(Clone, Copy)]
+pub struct ReplayWall {
+    pub wall_id: i32, // See calc_wall_id
+    pub energy: f32, // After the hit
+    pub time: f32, // [s]
+    pub spawn_time: f32, // [s]
+}
+
+#[derive(Clone, Copy)]
+pub struct ReplayHeight {
+    pub height: f32, // [m]
+    pub time: f32, // [s]
+}
+
+#[derive(Clone, Copy)]
+pub struct ReplayPause {
+    pub duration: i64, // [s]
+    pub time: f32, // [s]
+}
+
+// Type of the notes, which are identified by note ids.
+#[derive(Clone, Copy)]
+pub enum ReplayNoteKind {
+    Normal,
+    ArcHead,
+    ArcTail,
+    ChainHead,
+    ChainLink,
+    Bomb,
+}
+
+// Note id is used to pair the events with the notes of the beatmap:
+// (scoring type + 2) * 10000 + line index * 1000 + line layer * 100 + color * 10 + cut direction.
+pub fn calc_note_id(kind: ReplayNoteKind, x: i32, y: i32, color: i32, cut_dir: i32) -> i32 {
+    let (scoring_type, color, cut_dir) = match kind {
+        ReplayNoteKind::Normal => (SCORING_NORMAL, color, cut_dir),
+        ReplayNoteKind::ArcHead => (SCORING_ARC_HEAD, color, cut_dir),
+        ReplayNoteKind::ArcTail => (SCORING_ARC_TAIL, color, cut_dir),
+        ReplayNoteKind::ChainHead => (SCORING_CHAIN_HEAD, color, cut_dir),
+        ReplayNoteKind::ChainLink => (SCORING_CHAIN_LINK, color, CUT_DIR_ANY),
+        ReplayNoteKind::Bomb => (SCORING_NO_SCORE, COLOR_NONE, CUT_DIR_NONE),
+    };
+
+    (scoring_type + 2) * 10000 + x * 1000 + y * 100 + color * 10 + cut_dir
+}
+
+// The color of the bombs is negative, so the id of a bomb at line index 0 and line layer 0
+// is just below the multiple of 10000 of its scoring type.
+pub fn calc_note_kind(note_id: i32) -> ReplayNoteKind {
+    match (note_id - COLOR_NONE * 10) / 10000 - 2 {
+        SCORING_NO_SCORE => ReplayNoteKind::Bomb,
+        SCORING_ARC_HEAD => ReplayNoteKind::ArcHead,
+        SCORING_ARC_TAIL => ReplayNoteKind::ArcTail,
+        SCORING_CHAIN_HEAD | SCORING_CHAIN_HEAD_ARC_TAIL => ReplayNoteKind::ChainHead,
+        SCORING_CHAIN_LINK | SCORING_CHAIN_LINK_ARC_HEAD => ReplayNoteKind::ChainLink,
+        _ => ReplayNoteKind::Normal,
+    }
+}
+
+// Wall id: line index * 100 + type * 10 + width, type is 0 for full height and 1 for crouch walls.
+pub fn calc_wall_id(x: i32, y: i32, width: i32) -> i32 {
+    let wall_type = if y <= 0 { 0 } else { 1 };
+    x * 100 + wall_type * 10 + width
+}
+
+impl ReplayPose {
+    // Head poses have no saber, neutral rotation looks forward.
+    // The neutral head looks forward (+y), which is forward in Unity as well.
+    pub fn from_head(pos: &Vector3<f32>, rot: &Quaternion<f32>) -> Self {
+        Self {
+            pos: to_unity_vec(pos),
+            rot: to_unity_rot(rot),
+        }
+    }
+
+    // The neutral saber points up (see SABER_DIR), but forward in Unity.
+    pub fn from_saber(pos: &Vector3<f32>, rot: &Quaternion<f32>) -> Self {
+        Self {
+            pos: to_unity_vec(pos),
+            rot: to_unity_rot(rot) * Quaternion::from_angle_x(Deg(-90.0)),
+        }
+    }
+
+    pub fn empty() -> Self {
+        Self::from_head(&Vector3::new(0.0, 0.0, 0.0), &Quaternion::new(1.0, 0.0, 0.0, 0.0))
+    }
+
+    // Inverse of from_head.
+    pub fn get_head_pos_rot(&self) -> (Vector3<f32>, Quaternion<f32>) {
+        (to_unity_vec(&self.pos), to_unity_rot(&self.rot))
+    }
+
+    // Inverse of from_saber.
+    pub fn get_saber_pos_rot(&self) -> (Vector3<f32>, Quaternion<f32>) {
+        (to_unity_vec(&self.pos), to_unity_rot(&(self.rot * Quaternion::from_angle_x(Deg(90.0)))))
+    }
+}
+
+// Start of the filename of the replays, which are recorded for a beatmap.
+pub fn calc_filename_prefix(hash: &str, mode: &str, difficulty: &str) -> String {
+    format!("{}-{}-{}-", hash, mode, difficulty)
+}
+
+impl ReplayInfo {
+    pub fn calc_filename(&self) -> String {
+        format!("{}{}.bsor", calc_filename_prefix(&self.hash, &self.mode, &self.difficulty), self.timestamp)
+    }
+}
+
+// Converts between our coordinate system (z is up, y is forward, right-handed) and Unity,
+// the conversions are their own inverses.
+pub fn to_unity_vec(v: &Vector3<f32>) -> Vector3<f32> {
+    Vector3::new(v.x, v.z, v.y)
+}
+
+fn to_unity_rot(rot: &Quaternion<f32>) -> Quaternion<f32> {
+    // Mirroring the axis (by swapping y and z) reverses the direction of the rotation.
+
+    Quaternion::new(rot.s, -rot.v.x, -rot.v.z, -rot.v.y)
+}
+
+impl Replay {
+    pub fn read<R: Read>(r: &mut R) -> Result<Self> {
+        if read_i32(r)? != MAGIC {
+            return Err(invalid_data("Not a BSOR file"));
+        }
+
+        let version = read_u8(r)?;
+        if version != VERSION {
+            return Err(invalid_data(&format!("Unsupported version: {}", version)));
+        }
+
+        // Info section.
+
+        read_section(r, SECTION_INFO)?;
+
+        let info = ReplayInfo {
+            version: read_string(r)?,
+            game_version: read_string(r)?,
+            timestamp: read_string(r)?,
+            player_id: read_string(r)?,
+            player_name: read_string(r)?,
+            platform: read_string(r)?,
+            tracking_system: read_string(r)?,
+            hmd: read_string(r)?,
+            controller: read_string(r)?,
+            hash: read_string(r)?,
+            song_name: read_string(r)?,
+            mapper: read_string(r)?,
+            difficulty: read_string(r)?,
+            score: read_i32(r)?,
+            mode: read_string(r)?,
+            environment: read_string(r)?,
+            modifiers: read_string(r)?,
+            jump_distance: read_f32(r)?,
+            left_handed: read_bool(r)?,
+            height: read_f32(r)?,
+            start_time: read_f32(r)?,
+            fail_time: read_f32(r)?,
+            speed: read_f32(r)?,
+        };
+
+        // Frames section.
+
+        read_section(r, SECTION_FRAMES)?;
+
+        let mut frames = Vec::new();
+
+        for _ in 0..read_len(r)? {
+            frames.push(ReplayFrame {
+                time: read_f32(r)?,
+                fps: read_i32(r)?,
+                head: read_pose(r)?,
+                left: read_pose(r)?,
+                right: read_pose(r)?,
+            });
+        }
+
+        // Notes section.
+
+        read_section(r, SECTION_NOTES)?;
+
+        let mut notes = Vec::new();
+
+        for _ in 0..read_len(r)? {
+            let note_id = read_i32(r)?;
+            let event_time = read_f32(r)?;
+            let spawn_time = read_f32(r)?;
+
+            let event_type = match read_i32(r)? {
+                0 => ReplayNoteEvent::Good,
+                1 => ReplayNoteEvent::Bad,
+                2 => ReplayNoteEvent::Miss,
+                3 => ReplayNoteEvent::Bomb,
+                event_type => return Err(invalid_data(&format!("Invalid note event: {}", event_type))),
+            };
+
+            // Cut info is present only for good and bad cuts.
+
+            let cut_info_opt = if matches!(event_type, ReplayNoteEvent::Good | ReplayNoteEvent::Bad) {
+                Some(ReplayCutInfo {
+                    speed_ok: read_bool(r)?,
+                    direction_ok: read_bool(r)?,
+                    saber_type_ok: read_bool(r)?,
+                    was_cut_too_soon: read_bool(r)?,
+                    saber_speed: read_f32(r)?,
+                    saber_dir: read_vec3(r)?,
+                    saber_type: read_i32(r)?,
+                    time_deviation: read_f32(r)?,
+                    cut_dir_deviation: read_f32(r)?,
+                    cut_point: read_vec3(r)?,
+                    cut_normal: read_vec3(r)?,
+                    cut_distance_to_center: read_f32(r)?,
+                    cut_angle: read_f32(r)?,
+                    before_cut_rating: read_f32(r)?,
+                    after_cut_rating: read_f32(r)?,
+                })
+            } else {
+                None
+            };
+
+            notes.push(ReplayNote {
+                note_id,
+                event_time,
+                spawn_time,
+                event_type,
+                cut_info_opt,
+            });
+        }
+
+        // Walls section.
+
+        read_section(r, SECTION_WALLS)?;
+
+        let mut walls = Vec::new();
+
+        for _ in 0..read_len(r)? {
+            walls.push(ReplayWall {
+                wall_id: read_i32(r)?,
+                energy: read_f32(r)?,
+                time: read_f32(r)?,
+                spawn_time: read_f32(r)?,
+            });
+        }
+
+        // Heights section.
+
+        read_section(r, SECTION_HEIGHTS)?;
+
+        let mut heights = Vec::new();
+
+        for _ in 0..read_len(r)? {
+            heights.push(ReplayHeight {
+                height: read_f32(r)?,
+                time: read_f32(r)?,
+            });
+        }
+
+        // Pauses section.
+
+        read_section(r, SECTION_PAUSES)?;
+
+        let mut pauses = Vec::new();
+
+        for _ in 0..read_len(r)? {
+            pauses.push(ReplayPause {
+                duration: read_i64(r)?,
+                time: read_f32(r)?,
+            });
+        }
+
+        Ok(Self {
+            info,
+            frames,
+            notes,
+            walls,
+            heights,
+            pauses,
+        })
+    }
+
+    pub fn write<W: Write>(&self, w: &mut W) -> Result<()> {
+        write_i32(w, MAGIC)?;
+        write_u8(w, VERSION)?;
+
+        // Info section.
+
+        let info = &self.info;
+
+        write_u8(w, SECTION_INFO)?;
+
+        for s in [&info.version, &info.game_version, &info.timestamp, &info.player_id, &info.player_name, &info.platform, &info.tracking_system, &info.hmd, &info.controller, &info.hash, &info.song_name, &info.mapper, &info.difficulty] {
+            write_string(w, s)?;
+        }
+
+        write_i32(w, info.score)?;
+
+        for s in [&info.mode, &info.environment, &info.modifiers] {
+            write_string(w, s)?;
+        }
+
+        write_f32(w, info.jump_distance)?;
+        write_bool(w, info.left_handed)?;
+        write_f32(w, info.height)?;
+        write_f32(w, info.start_time)?;
+        write_f32(w, info.fail_time)?;
+        write_f32(w, info.speed)?;
+
+        // Frames section.
+
+        write_u8(w, SECTION_FRAMES)?;
+        write_len(w, self.frames.len())?;
+
+        for frame in &self.frames {
+            write_f32(w, frame.time)?;
+            write_i32(w, frame.fps)?;
+
+            for pose in [&frame.head, &frame.left, &frame.right] {
+                write_vec3(w, &pose.pos)?;
+                write_quat(w, &pose.rot)?;
+            }
+        }
+
+        // Notes section.
+
+        write_u8(w, SECTION_NOTES)?;
+        write_len(w, self.notes.len())?;
+
+        for note in &self.notes {
+            let event_type = match note.event_type {
+                ReplayNoteEvent::Good => 0,
+                ReplayNoteEvent::Bad => 1,
+                ReplayNoteEvent::Miss => 2,
+                ReplayNoteEvent::Bomb => 3,
+            };
+
+            write_i32(w, note.note_id)?;
+            write_f32(w, note.event_time)?;
+            write_f32(w, note.spawn_time)?;
+            write_i32(w, event_type)?;
+
+            // Cut info is present only for good and bad cuts.
+
+            if matches!(note.event_type, ReplayNoteEvent::Good | ReplayNoteEvent::Bad) {
+                let cut_info = note.cut_info_opt.as_ref().expect("Cut info is missing");
+
+                write_bool(w, cut_info.speed_ok)?;
+                write_bool(w, cut_info.direction_ok)?;
+                write_bool(w, cut_info.saber_type_ok)?;
+                write_bool(w, cut_info.was_cut_too_soon)?;
+                write_f32(w, cut_info.saber_speed)?;
+                write_vec3(w, &cut_info.saber_dir)?;
+                write_i32(w, cut_info.saber_type)?;
+                write_f32(w, cut_info.time_deviation)?;
+                write_f32(w, cut_info.cut_dir_deviation)?;
+                write_vec3(w, &cut_info.cut_point)?;
+                write_vec3(w, &cut_info.cut_normal)?;
+                write_f32(w, cut_info.cut_distance_to_center)?;
+                write_f32(w, cut_info.cut_angle)?;
+                write_f32(w, cut_info.before_cut_rating)?;
+                write_f32(w, cut_info.after_cut_rating)?;
+            }
+        }
+
+        // Walls section.
+
+        write_u8(w, SECTION_WALLS)?;
+        write_len(w, self.walls.len())?;
+
+        for wall in &self.walls {
+            write_i32(w, wall.wall_id)?;
+            write_f32(w, wall.energy)?;
+            write_f32(w, wall.time)?;
+            write_f32(w, wall.spawn_time)?;
+        }
+
+        // Heights section.
+
+        write_u8(w, SECTION_HEIGHTS)?;
+        write_len(w, self.heights.len())?;
+
+        for height in &self.heights {
+            write_f32(w, height.height)?;
+            write_f32(w, height.time)?;
+        }
+
+        // Pauses section.
+
+        write_u8(w, SECTION_PAUSES)?;
+        write_len(w, self.pauses.len())?;
+
+        for pause in &self.pauses {
+            write_i64(w, pause.duration)?;
+            write_f32(w, pause.time)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_u8<W: Write>(w: &mut W, value: u8) -> Result<()> {
+    w.write_all(&[value])
+}
+
+fn write_bool<W: Write>(w: &mut W, value: bool) -> Result<()> {
+    write_u8(w, value as u8)
+}
+
+fn write_i32<W: Write>(w: &mut W, value: i32) -> Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_i64<W: Write>(w: &mut W, value: i64) -> Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_f32<W: Write>(w: &mut W, value: f32) -> Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_len<W: Write>(w: &mut W, len: usize) -> Result<()> {
+    let len = len.try_into().map_err(|_| Error::new(ErrorKind::InvalidInput, "Too many entries"))?;
+    write_i32(w, len)
+}
+
+fn write_string<W: Write>(w: &mut W, value: &str) -> Result<()> {
+    write_len(w, value.len())?;
+    w.write_all(value.as_bytes())
+}
+
+fn write_vec3<W: Write>(w: &mut W, v: &Vector3<f32>) -> Result<()> {
+    write_f32(w, v.x)?;
+    write_f32(w, v.y)?;
+    write_f32(w, v.z)
+}
+
+fn write_quat<W: Write>(w: &mut W, q: &Quaternion<f32>) -> Result<()> {
+    write_vec3(w, &q.v)?;
+    write_f32(w, q.s)
+}
+
+fn invalid_data(msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn read_section<R: Read>(r: &mut R, section: u8) -> Result<()> {
+    let id = read_u8(r)?;
+
+    if id != section {
+        return Err(invalid_data(&format!("Expected section {}, found {}", section, id)));
+    }
+
+    Ok(())
+}
+
+fn read_u8<R: Read>(r: &mut R) -> Result<u8> {
+    let mut buf = [0; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_bool<R: Read>(r: &mut R) -> Result<bool> {
+    Ok(read_u8(r)? != 0)
+}
+
+fn read_i32<R: Read>(r: &mut R) -> Result<i32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(i32::from_le_bytes(buf))
+}
+
+fn read_i64<R: Read>(r: &mut R) -> Result<i64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(i64::from_le_bytes(buf))
+}
+
+fn read_f32<R: Read>(r: &mut R) -> Result<f32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(f32::from_le_bytes(buf))
+}
+
+fn read_len<R: Read>(r: &mut R) -> Result<usize> {
+    let len = read_i32(r)?;
+    len.try_into().map_err(|_| invalid_data(&format!("Invalid length: {}", len)))
+}
+
+fn read_string<R: Read>(r: &mut R) -> Result<String> {
+    let len = read_len(r)?;
+
+    if len > STRING_LEN_MAX {
+        return Err(invalid_data(&format!("String is too long: {}", len)));
+    }
+
+    let mut buf = vec![0; len];
+    r.read_exact(&mut buf)?;
+
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+fn read_vec3<R: Read>(r: &mut R) -> Result<Vector3<f32>> {
+    Ok(Vector3::new(read_f32(r)?, read_f32(r)?, read_f32(r)?))
+}
+
+fn read_quat<R: Read>(r: &mut R) -> Result<Quaternion<f32>> {
+    let v = read_vec3(r)?;
+    let s = read_f32(r)?;
+    Ok(Quaternion::from_sv(s, v))
+}
+
+fn read_pose<R: Read>(r: &mut R) -> Result<ReplayPose> {
+    Ok(ReplayPose {
+        pos: read_vec3(r)?,
+        rot: read_quat(r)?,
+    })
+}
@@ -16,6 +16,8 @@ use asset::AssetManagerTrait;
 mod audio;
 use audio::{AudioEngine, AudioEngineRc};
 
+mod bsor;
+
 mod circbuf;
 
 mod mailbox;
@@ -33,6 +35,8 @@ use render::Render;
 pub mod scene;
 use scene::SceneInput;
 
+mod score;
+
 mod simd;
 
 mod songdef;
@@ -42,11 +46,19 @@ mod songinfo;
 mod ui;
 
 pub mod util;
-use util::Stats;
+use util::{Settings, Stats};
 
 #[cfg(test)]
 mod tests;
 
+// Entry points of the benchmarks, not part of the API.
+
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub mod bench {
+    pub use crate::songinfo::SongInfo;
+}
+
 pub const APP_NAME: &str = env!("CARGO_PKG_DESCRIPTION");
 
 const APP_VERSION_MAJOR: &str = env!("CARGO_PKG_VERSION_MAJOR");
@@ -61,9 +73,9 @@ pub struct Main {
 }
 
 impl Main {
-    pub fn new<A: AssetManagerTrait + Send + Sync + 'static>(asset_mgr: A, output_info: OutputInfo, stats: Stats) -> Self {
+    pub fn new<A: AssetManagerTrait + Send + Sync + 'static>(asset_mgr: A, output_info: OutputInfo, stats: Stats, settings: Settings) -> Self {
         let audio_engine = Rc::new(AudioEngine::new());
-        let render = Render::new(Arc::new(asset_mgr), Rc::new(output_info), Arc::new(stats), Rc::clone(&audio_engine));
+        let render = Render::new(Arc::new(asset_mgr), Rc::new(output_info), Arc::new(stats), Arc::new(settings), Rc::clone(&audio_engine));
 
         Self {
             audio_engine,
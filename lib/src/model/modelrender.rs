@@ -5,10 +5,10 @@ use std::iter;
 use std::rc::Rc;
 use std::sync::Arc;
 
-use wgpu::{BindGroup, BindGroupLayout, BlendState, Buffer, BufferDescriptor, BufferSize, BufferUsages, ColorTargetState, ColorWrites, CompareFunction, DepthStencilState, Device, FragmentState, IndexFormat, MultisampleState, PipelineLayoutDescriptor, RenderPass, RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource, VertexState};
+use wgpu::{BindGroup, BindGroupLayout, Buffer, BufferDescriptor, BufferSize, BufferUsages, ColorTargetState, ColorWrites, CompareFunction, DepthStencilState, Device, FragmentState, IndexFormat, MultisampleState, PipelineLayoutDescriptor, RenderPass, RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderSource, VertexState};
 
 use crate::asset::AssetManagerRc;
-use crate::model::{InstGridBuf, InstPhongColorBuf, InstShaderImplType, InstShaderSize, InstShaderType, InstSimpleColorBuf, InstWindowBuf, Mesh};
+use crate::model::{InstGridBuf, InstPhongColorBuf, InstShaderImplType, InstShaderSize, InstShaderType, InstSimpleColorBuf, InstRibbonBuf, InstTransColorBuf, InstVignetteBuf, InstWindowBuf, Mesh};
 use crate::output::OutputInfoRc;
 use crate::ui::UIManagerRc;
 use crate::util::StatsRc;
@@ -48,6 +48,18 @@ pub trait Model {
         panic!("Method is not implemented");
     }
 
+    fn fill_trans_color(&self, _inst_index: u32) -> InstTransColorBuf {
+        panic!("Method is not implemented");
+    }
+
+    fn fill_vignette(&self, _inst_index: u32) -> InstVignetteBuf {
+        panic!("Method is not implemented");
+    }
+
+    fn fill_ribbon(&self, _inst_index: u32) -> InstRibbonBuf {
+        panic!("Method is not implemented");
+    }
+
     fn fill_window(&self, _inst_index: u32) -> InstWindowBuf {
         panic!("Method is not implemented");
     }
@@ -176,7 +188,7 @@ impl ModelRenderer {
         let view_len = output_info.get_view_len();
         let uni = UNI_TMPL.replace("#VIEW_LEN#", &format!("{view_len}"));
 
-        let render_infos = model_infos.into_values().map(|model_info| {
+        let mut render_infos: Vec<_> = model_infos.into_values().map(|model_info| {
             let mesh = model_info.mesh;
             let vertex_sh_type = mesh.get_vertex_sh_type();
 
@@ -270,14 +282,14 @@ impl ModelRenderer {
                             compilation_options: Default::default(),
                             targets: &[Some(ColorTargetState { // See fragment shader->@location().
                                 format: output_info.get_color_format(),
-                                blend: Some(BlendState::REPLACE),
+                                blend: Some(inst_sh_type.get_blend_state()),
                                 write_mask: ColorWrites::ALL,
                             })],
                         }),
                         primitive: primitive_st_type.get_primitive(),
                         depth_stencil: Some(DepthStencilState {
                             format: output_info.get_depth_format(),
-                            depth_write_enabled: Some(true),
+                            depth_write_enabled: Some(inst_sh_type.get_depth_write()),
                             depth_compare: Some(CompareFunction::Less),
                             stencil: Default::default(),
                             bias: Default::default(),
@@ -308,10 +320,14 @@ impl ModelRenderer {
             }
         }).collect();
 
+        // Make sure that translucent models are rendered after the opaque ones.
+
+        render_infos.sort_by_key(|render_info| render_info.mesh.get_submeshes().iter().map(|submesh| submesh.get_inst_sh_type().get_render_order()).max());
+
         Self {
             output_info,
             stats,
-            render_infos,
+            render_infos: render_infos.into_boxed_slice(),
         }
     }
 
@@ -363,6 +379,9 @@ impl ModelRenderer {
                         (SimpleColor, fill_simple_color),
                         (PhongColor, fill_phong_color),
                         (Grid, fill_grid),
+                        (TransColor, fill_trans_color),
+                        (Vignette, fill_vignette),
+                        (Ribbon, fill_ribbon),
                         (Window, fill_window)
                     );
 
@@ -0,0 +1,152 @@
+use std::cell::RefCell;
+use std::f32::consts::PI;
+
+use cgmath::{Matrix4, Vector3};
+use wgpu::{BufferUsages, Device};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+use crate::asset::AssetManagerRc;
+use crate::model::{Color, InstPhongColorBuf, InstShaderImplType, InstShaderType, Mesh, Model, ModelFactory, ModelHandle, PhongParam, PrimitiveStateType, Submesh, VertexPosNormal, VertexShaderType};
+use crate::ui::UIManagerRc;
+
+const RADIUS: f32 = 0.4;
+const RINGS: u16 = 8;
+const SEGMENTS: u16 = 12;
+
+pub struct BombParam {
+    color: Color,
+    phong_param: PhongParam,
+}
+
+impl BombParam {
+    pub fn new(color: &Color, phong_param: &PhongParam) -> Self {
+        Self {
+            color: *color,
+            phong_param: *phong_param,
+        }
+    }
+}
+
+impl ModelFactory for BombParam {
+    type Model = Bomb;
+
+    fn get_name() -> &'static str {
+        "bomb"
+    }
+
+    fn get_mesh(_asset_mgr: AssetManagerRc, device: &Device) -> Mesh {
+        // We don't have .obj file for bomb, calculate mesh (uv sphere).
+
+        let mut vertexes = Vec::new();
+
+        for ring in 0..=RINGS {
+            let theta = PI * ring as f32 / RINGS as f32;
+
+            for segment in 0..=SEGMENTS {
+                let phi = 2.0 * PI * segment as f32 / SEGMENTS as f32;
+                let normal = [theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()];
+
+                vertexes.push(VertexPosNormal {
+                    pos: normal.map(|v| v * RADIUS),
+                    normal,
+                });
+            }
+        }
+
+        let mut indexes: Vec<u16> = Vec::new();
+        let index = |ring: u16, segment: u16| ring * (SEGMENTS + 1) + segment;
+
+        for ring in 0..RINGS {
+            for segment in 0..SEGMENTS {
+                let a = index(ring, segment);
+                let b = index(ring + 1, segment);
+                let c = index(ring + 1, segment + 1);
+                let d = index(ring, segment + 1);
+
+                // Skip degenerate triangles at the poles.
+
+                if ring != RINGS - 1 {
+                    indexes.extend([a, b, c]);
+                }
+
+                if ring != 0 {
+                    indexes.extend([a, c, d]);
+                }
+            }
+        }
+
+        let submesh = Submesh::new(0, indexes.len() as u32, 0, PrimitiveStateType::TriangleList, InstShaderType::PhongColor); // 0
+
+        // Create buffers.
+
+        let vertex_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertexes),
+            usage: BufferUsages::VERTEX,
+        });
+
+        let index_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&indexes),
+            usage: BufferUsages::INDEX,
+        });
+
+        let submeshes = Box::from([submesh]);
+
+        Mesh::new(vertex_buf, index_buf, VertexShaderType::PosNormal, submeshes)
+    }
+
+    fn create(self, handle: ModelHandle, _device: &Device, _inst_sh_impls: &mut [InstShaderImplType], _ui_manager: UIManagerRc) -> Self::Model {
+        Bomb::new(self, handle)
+    }
+}
+
+pub struct Bomb {
+    param: BombParam,
+    handle: ModelHandle,
+    inner: RefCell<Inner>,
+}
+
+struct Inner {
+    scale: f32,
+    pos: Vector3<f32>,
+}
+
+impl Bomb {
+    fn new(param: BombParam, handle: ModelHandle) -> Self {
+        Self {
+            param,
+            handle,
+            inner: RefCell::new(Inner {
+                scale: 1.0,
+                pos: Vector3::new(0.0, 0.0, 0.0),
+            }),
+        }
+    }
+
+    pub fn get_radius(&self) -> f32 {
+        RADIUS * self.inner.borrow().scale
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        self.handle.set_visible(0, visible);
+    }
+
+    pub fn set_scale(&self, scale: f32) {
+        self.inner.borrow_mut().scale = scale;
+    }
+
+    pub fn set_pos(&self, pos: &Vector3<f32>) {
+        self.inner.borrow_mut().pos = *pos;
+    }
+}
+
+impl Model for Bomb {
+    fn fill_phong_color(&self, inst_index: u32) -> InstPhongColorBuf {
+        assert!(inst_index == 0);
+
+        let inner = self.inner.borrow();
+        let model_m = Matrix4::from_translation(inner.pos) * Matrix4::from_scale(inner.scale);
+        InstPhongColorBuf::fill(&self.param.color, &self.param.phong_param, &model_m)
+    }
+}
@@ -1,6 +1,6 @@
 use std::cell::RefCell;
 
-use cgmath::{Matrix4, Vector3};
+use cgmath::{Matrix4, Quaternion, Vector3};
 use wgpu::{BufferUsages, Device};
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 
@@ -82,6 +82,7 @@ pub struct Floor {
 
 struct Inner {
     pos: Vector3<f32>,
+    rot: Quaternion<f32>,
 }
 
 impl Floor {
@@ -91,6 +92,7 @@ impl Floor {
             handle,
             inner: RefCell::new(Inner {
                 pos: Vector3::new(0.0, 0.0, 0.0),
+                rot: Quaternion::new(1.0, 0.0, 0.0, 0.0),
             }),
         }
     }
@@ -102,6 +104,10 @@ impl Floor {
     pub fn set_pos(&self, pos: &Vector3<f32>) {
         self.inner.borrow_mut().pos = *pos;
     }
+
+    pub fn set_rot(&self, rot: &Quaternion<f32>) {
+        self.inner.borrow_mut().rot = *rot;
+    }
 }
 
 impl Model for Floor {
@@ -109,7 +115,7 @@ impl Model for Floor {
         assert!(inst_index == 0);
 
         let inner = self.inner.borrow();
-        let model_m = Matrix4::from_translation(inner.pos);
+        let model_m = Matrix4::from_translation(inner.pos) * Matrix4::from(inner.rot);
         InstGridBuf::fill(&self.param.color, &model_m)
     }
 }
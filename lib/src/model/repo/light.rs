@@ -0,0 +1,135 @@
+use std::cell::RefCell;
+
+use cgmath::{Matrix4, Quaternion, Vector3};
+use wgpu::{BufferUsages, Device};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+use crate::asset::AssetManagerRc;
+use crate::model::{Color, InstShaderImplType, InstShaderType, InstSimpleColorBuf, Mesh, Model, ModelFactory, ModelHandle, PrimitiveStateType, Submesh, VertexPos, VertexShaderType};
+use crate::ui::UIManagerRc;
+
+pub struct LightParam {
+    color: Color,
+}
+
+impl LightParam {
+    pub fn new(color: &Color) -> Self {
+        Self {
+            color: *color,
+        }
+    }
+}
+
+impl ModelFactory for LightParam {
+    type Model = Light;
+
+    fn get_name() -> &'static str {
+        "light"
+    }
+
+    fn get_mesh(_asset_mgr: AssetManagerRc, device: &Device) -> Mesh {
+        // We don't have .obj file for light, calculate mesh (unit box with its center at the origin).
+
+        let vertexes = [
+            VertexPos { pos: [-0.5, -0.5, -0.5] },
+            VertexPos { pos: [0.5, -0.5, -0.5] },
+            VertexPos { pos: [-0.5, 0.5, -0.5] },
+            VertexPos { pos: [0.5, 0.5, -0.5] },
+            VertexPos { pos: [-0.5, -0.5, 0.5] },
+            VertexPos { pos: [0.5, -0.5, 0.5] },
+            VertexPos { pos: [-0.5, 0.5, 0.5] },
+            VertexPos { pos: [0.5, 0.5, 0.5] },
+        ];
+
+        let indexes: [u16; 36] = [
+            0, 2, 1, 1, 2, 3, // -z
+            4, 5, 6, 5, 7, 6, // +z
+            0, 1, 5, 0, 5, 4, // -y
+            2, 6, 7, 2, 7, 3, // +y
+            0, 4, 6, 0, 6, 2, // -x
+            1, 3, 7, 1, 7, 5, // +x
+        ];
+
+        let submesh = Submesh::new(0, indexes.len() as u32, 0, PrimitiveStateType::TriangleList, InstShaderType::SimpleColor); // 0
+
+        // Create buffers.
+
+        let vertex_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertexes),
+            usage: BufferUsages::VERTEX,
+        });
+
+        let index_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&indexes),
+            usage: BufferUsages::INDEX,
+        });
+
+        let submeshes = Box::from([submesh]);
+
+        Mesh::new(vertex_buf, index_buf, VertexShaderType::Pos, submeshes)
+    }
+
+    fn create(self, handle: ModelHandle, _device: &Device, _inst_sh_impls: &mut [InstShaderImplType], _ui_manager: UIManagerRc) -> Self::Model {
+        Light::new(self, handle)
+    }
+}
+
+// Light is an unlit box (e.g. a laser beam), its color already contains the brightness.
+
+pub struct Light {
+    handle: ModelHandle,
+    inner: RefCell<Inner>,
+}
+
+struct Inner {
+    color: Color,
+    size: Vector3<f32>,
+    pos: Vector3<f32>,
+    rot: Quaternion<f32>,
+}
+
+impl Light {
+    fn new(param: LightParam, handle: ModelHandle) -> Self {
+        Self {
+            handle,
+            inner: RefCell::new(Inner {
+                color: param.color,
+                size: Vector3::new(1.0, 1.0, 1.0),
+                pos: Vector3::new(0.0, 0.0, 0.0),
+                rot: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            }),
+        }
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        self.handle.set_visible(0, visible);
+    }
+
+    pub fn set_color(&self, color: &Color) {
+        self.inner.borrow_mut().color = *color;
+    }
+
+    pub fn set_size(&self, size: &Vector3<f32>) {
+        self.inner.borrow_mut().size = *size;
+    }
+
+    pub fn set_pos(&self, pos: &Vector3<f32>) {
+        self.inner.borrow_mut().pos = *pos;
+    }
+
+    pub fn set_rot(&self, rot: &Quaternion<f32>) {
+        self.inner.borrow_mut().rot = *rot;
+    }
+}
+
+impl Model for Light {
+    fn fill_simple_color(&self, inst_index: u32) -> InstSimpleColorBuf {
+        assert!(inst_index == 0);
+
+        let inner = self.inner.borrow();
+        let model_m = Matrix4::from_translation(inner.pos) * Matrix4::from(inner.rot) * Matrix4::from_nonuniform_scale(inner.size.x, inner.size.y, inner.size.z);
+        InstSimpleColorBuf::fill(&inner.color, &model_m)
+    }
+}
@@ -1,14 +1,29 @@
+mod bomb;
+pub use bomb::*;
+
 mod cube;
 pub use cube::*;
 
 mod floor;
 pub use floor::*;
 
+mod light;
+pub use light::*;
+
 mod pointer;
 pub use pointer::*;
 
+mod ribbon;
+pub use ribbon::*;
+
 mod saber;
 pub use saber::*;
 
+mod vignette;
+pub use vignette::*;
+
+mod wall;
+pub use wall::*;
+
 mod window;
 pub use window::*;
@@ -0,0 +1,123 @@
+use std::cell::RefCell;
+
+use cgmath::Vector3;
+use wgpu::{BufferUsages, Device};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+use crate::asset::AssetManagerRc;
+use crate::model::{Color, InstRibbonBuf, InstShaderImplType, InstShaderType, Mesh, Model, ModelFactory, ModelHandle, PrimitiveStateType, Submesh, VertexPos, VertexShaderType};
+use crate::ui::UIManagerRc;
+
+const SEGMENTS: u16 = 24;
+
+pub struct RibbonParam {
+    color: Color,
+    alpha: f32,
+    width: f32,
+}
+
+impl RibbonParam {
+    pub fn new(color: &Color, alpha: f32, width: f32) -> Self {
+        Self {
+            color: *color,
+            alpha,
+            width,
+        }
+    }
+}
+
+impl ModelFactory for RibbonParam {
+    type Model = Ribbon;
+
+    fn get_name() -> &'static str {
+        "ribbon"
+    }
+
+    fn get_mesh(_asset_mgr: AssetManagerRc, device: &Device) -> Mesh {
+        // We don't have .obj file for ribbon, calculate mesh. It is a flat strip, the
+        // curve is evaluated by the shader (x: curve parameter, y: side).
+
+        let mut vertexes = Vec::new();
+
+        for segment in 0..=SEGMENTS {
+            let t = segment as f32 / SEGMENTS as f32;
+
+            vertexes.push(VertexPos { pos: [t, -0.5, 0.0] });
+            vertexes.push(VertexPos { pos: [t, 0.5, 0.0] });
+        }
+
+        let mut indexes: Vec<u16> = Vec::new();
+
+        for segment in 0..SEGMENTS {
+            let a = 2 * segment;
+            let b = 2 * (segment + 1);
+            let c = b + 1;
+            let d = a + 1;
+
+            indexes.extend([a, c, b, a, d, c]);
+        }
+
+        let submesh = Submesh::new(0, indexes.len() as u32, 0, PrimitiveStateType::TriangleList, InstShaderType::Ribbon); // 0
+
+        // Create buffers.
+
+        let vertex_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertexes),
+            usage: BufferUsages::VERTEX,
+        });
+
+        let index_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&indexes),
+            usage: BufferUsages::INDEX,
+        });
+
+        let submeshes = Box::from([submesh]);
+
+        Mesh::new(vertex_buf, index_buf, VertexShaderType::Pos, submeshes)
+    }
+
+    fn create(self, handle: ModelHandle, _device: &Device, _inst_sh_impls: &mut [InstShaderImplType], _ui_manager: UIManagerRc) -> Self::Model {
+        Ribbon::new(self, handle)
+    }
+}
+
+pub struct Ribbon {
+    param: RibbonParam,
+    handle: ModelHandle,
+    inner: RefCell<Inner>,
+}
+
+struct Inner {
+    ctrl_points: [Vector3<f32>; 4],
+}
+
+impl Ribbon {
+    fn new(param: RibbonParam, handle: ModelHandle) -> Self {
+        Self {
+            param,
+            handle,
+            inner: RefCell::new(Inner {
+                ctrl_points: [Vector3::new(0.0, 0.0, 0.0); 4],
+            }),
+        }
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        self.handle.set_visible(0, visible);
+    }
+
+    pub fn set_ctrl_points(&self, ctrl_points: &[Vector3<f32>; 4]) {
+        self.inner.borrow_mut().ctrl_points = *ctrl_points;
+    }
+}
+
+impl Model for Ribbon {
+    fn fill_ribbon(&self, inst_index: u32) -> InstRibbonBuf {
+        assert!(inst_index == 0);
+
+        let inner = self.inner.borrow();
+        InstRibbonBuf::fill(&self.param.color, self.param.alpha, self.param.width, &inner.ctrl_points)
+    }
+}
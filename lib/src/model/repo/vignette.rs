@@ -0,0 +1,122 @@
+use std::cell::RefCell;
+
+use cgmath::{Matrix4, Vector3};
+use wgpu::{BufferUsages, Device};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+use crate::asset::AssetManagerRc;
+use crate::model::{Color, InstShaderImplType, InstShaderType, InstVignetteBuf, Mesh, Model, ModelFactory, ModelHandle, PrimitiveStateType, Submesh, VertexPos, VertexShaderType};
+use crate::ui::UIManagerRc;
+
+const RADIUS: f32 = 0.25; // Has to be larger than NEAR_Z.
+
+pub struct VignetteParam {
+    color: Color,
+    alpha: f32,
+}
+
+impl VignetteParam {
+    pub fn new(color: &Color, alpha: f32) -> Self {
+        Self {
+            color: *color,
+            alpha,
+        }
+    }
+}
+
+impl ModelFactory for VignetteParam {
+    type Model = Vignette;
+
+    fn get_name() -> &'static str {
+        "vignette"
+    }
+
+    fn get_mesh(_asset_mgr: AssetManagerRc, device: &Device) -> Mesh {
+        // We don't have .obj file for vignette, calculate mesh. It is a box around the
+        // camera with its faces pointing inwards, the actual effect is done by the shader.
+
+        let vertexes = [
+            VertexPos { pos: [-RADIUS, -RADIUS, -RADIUS] },
+            VertexPos { pos: [RADIUS, -RADIUS, -RADIUS] },
+            VertexPos { pos: [-RADIUS, RADIUS, -RADIUS] },
+            VertexPos { pos: [RADIUS, RADIUS, -RADIUS] },
+            VertexPos { pos: [-RADIUS, -RADIUS, RADIUS] },
+            VertexPos { pos: [RADIUS, -RADIUS, RADIUS] },
+            VertexPos { pos: [-RADIUS, RADIUS, RADIUS] },
+            VertexPos { pos: [RADIUS, RADIUS, RADIUS] },
+        ];
+
+        let indexes: [u16; 36] = [
+            0, 1, 2, 1, 3, 2, // -z
+            4, 6, 5, 5, 6, 7, // +z
+            0, 5, 1, 0, 4, 5, // -y
+            2, 7, 6, 2, 3, 7, // +y
+            0, 6, 4, 0, 2, 6, // -x
+            1, 7, 3, 1, 5, 7, // +x
+        ];
+
+        let submesh = Submesh::new(0, indexes.len() as u32, 0, PrimitiveStateType::TriangleList, InstShaderType::Vignette); // 0
+
+        // Create buffers.
+
+        let vertex_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertexes),
+            usage: BufferUsages::VERTEX,
+        });
+
+        let index_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&indexes),
+            usage: BufferUsages::INDEX,
+        });
+
+        let submeshes = Box::from([submesh]);
+
+        Mesh::new(vertex_buf, index_buf, VertexShaderType::Pos, submeshes)
+    }
+
+    fn create(self, handle: ModelHandle, _device: &Device, _inst_sh_impls: &mut [InstShaderImplType], _ui_manager: UIManagerRc) -> Self::Model {
+        Vignette::new(self, handle)
+    }
+}
+
+pub struct Vignette {
+    param: VignetteParam,
+    handle: ModelHandle,
+    inner: RefCell<Inner>,
+}
+
+struct Inner {
+    pos: Vector3<f32>,
+}
+
+impl Vignette {
+    fn new(param: VignetteParam, handle: ModelHandle) -> Self {
+        Self {
+            param,
+            handle,
+            inner: RefCell::new(Inner {
+                pos: Vector3::new(0.0, 0.0, 0.0),
+            }),
+        }
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        self.handle.set_visible(0, visible);
+    }
+
+    pub fn set_pos(&self, pos: &Vector3<f32>) {
+        self.inner.borrow_mut().pos = *pos;
+    }
+}
+
+impl Model for Vignette {
+    fn fill_vignette(&self, inst_index: u32) -> InstVignetteBuf {
+        assert!(inst_index == 0);
+
+        let inner = self.inner.borrow();
+        let model_m = Matrix4::from_translation(inner.pos);
+        InstVignetteBuf::fill(&self.param.color, self.param.alpha, &model_m)
+    }
+}
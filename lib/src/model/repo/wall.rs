@@ -0,0 +1,150 @@
+use std::cell::RefCell;
+
+use cgmath::{Matrix4, Quaternion, Vector3};
+use wgpu::{BufferUsages, Device};
+use wgpu::util::{BufferInitDescriptor, DeviceExt};
+
+use crate::asset::AssetManagerRc;
+use crate::model::{Color, InstShaderImplType, InstShaderType, InstSimpleColorBuf, InstTransColorBuf, Mesh, Model, ModelFactory, ModelHandle, PrimitiveStateType, Submesh, VertexPos, VertexShaderType};
+use crate::ui::UIManagerRc;
+
+pub struct WallParam {
+    color: Color,
+    alpha: f32,
+}
+
+impl WallParam {
+    pub fn new(color: &Color, alpha: f32) -> Self {
+        Self {
+            color: *color,
+            alpha,
+        }
+    }
+}
+
+impl ModelFactory for WallParam {
+    type Model = Wall;
+
+    fn get_name() -> &'static str {
+        "wall"
+    }
+
+    fn get_mesh(_asset_mgr: AssetManagerRc, device: &Device) -> Mesh {
+        // We don't have .obj file for wall, calculate mesh (unit box with its center at the origin).
+
+        let vertexes = [
+            VertexPos { pos: [-0.5, -0.5, -0.5] },
+            VertexPos { pos: [0.5, -0.5, -0.5] },
+            VertexPos { pos: [-0.5, 0.5, -0.5] },
+            VertexPos { pos: [0.5, 0.5, -0.5] },
+            VertexPos { pos: [-0.5, -0.5, 0.5] },
+            VertexPos { pos: [0.5, -0.5, 0.5] },
+            VertexPos { pos: [-0.5, 0.5, 0.5] },
+            VertexPos { pos: [0.5, 0.5, 0.5] },
+        ];
+
+        let indexes: [u16; 60] = [
+            // Faces
+            0, 2, 1, 1, 2, 3, // -z
+            4, 5, 6, 5, 7, 6, // +z
+            0, 1, 5, 0, 5, 4, // -y
+            2, 6, 7, 2, 7, 3, // +y
+            0, 4, 6, 0, 6, 2, // -x
+            1, 3, 7, 1, 7, 5, // +x
+            // Edges
+            0, 1, 2, 3, 4, 5, 6, 7,
+            0, 2, 1, 3, 4, 6, 5, 7,
+            0, 4, 1, 5, 2, 6, 3, 7,
+        ];
+
+        let face_submesh = Submesh::new(0, 36, 0, PrimitiveStateType::TriangleList, InstShaderType::TransColor); // 0
+        let edge_submesh = Submesh::new(36, indexes.len() as u32, 0, PrimitiveStateType::LineList, InstShaderType::SimpleColor); // 1
+
+        // Create buffers.
+
+        let vertex_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&vertexes),
+            usage: BufferUsages::VERTEX,
+        });
+
+        let index_buf = device.create_buffer_init(&BufferInitDescriptor {
+            label: None,
+            contents: bytemuck::cast_slice(&indexes),
+            usage: BufferUsages::INDEX,
+        });
+
+        let submeshes = Box::from([face_submesh, edge_submesh]);
+
+        Mesh::new(vertex_buf, index_buf, VertexShaderType::Pos, submeshes)
+    }
+
+    fn create(self, handle: ModelHandle, _device: &Device, _inst_sh_impls: &mut [InstShaderImplType], _ui_manager: UIManagerRc) -> Self::Model {
+        Wall::new(self, handle)
+    }
+}
+
+pub struct Wall {
+    param: WallParam,
+    handle: ModelHandle,
+    inner: RefCell<Inner>,
+}
+
+struct Inner {
+    min: Vector3<f32>,
+    max: Vector3<f32>,
+    rot: Quaternion<f32>,
+}
+
+impl Wall {
+    fn new(param: WallParam, handle: ModelHandle) -> Self {
+        Self {
+            param,
+            handle,
+            inner: RefCell::new(Inner {
+                min: Vector3::new(0.0, 0.0, 0.0),
+                max: Vector3::new(0.0, 0.0, 0.0),
+                rot: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            }),
+        }
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        self.handle.set_visible(0, visible);
+        self.handle.set_visible(1, visible);
+    }
+
+    pub fn set_bounds(&self, min: &Vector3<f32>, max: &Vector3<f32>) {
+        let mut inner = self.inner.borrow_mut();
+
+        inner.min = *min;
+        inner.max = *max;
+    }
+
+    // The bounds are rotated about the origin.
+
+    pub fn set_rot(&self, rot: &Quaternion<f32>) {
+        self.inner.borrow_mut().rot = *rot;
+    }
+
+    fn calc_model_m(&self) -> Matrix4<f32> {
+        let inner = self.inner.borrow();
+        let size = inner.max - inner.min;
+
+        Matrix4::from(inner.rot) * Matrix4::from_translation((inner.min + inner.max) / 2.0) * Matrix4::from_nonuniform_scale(size.x, size.y, size.z)
+    }
+}
+
+impl Model for Wall {
+    fn fill_trans_color(&self, inst_index: u32) -> InstTransColorBuf {
+        assert!(inst_index == 0);
+
+        InstTransColorBuf::fill(&self.param.color, self.param.alpha, &self.calc_model_m())
+    }
+
+    fn fill_simple_color(&self, inst_index: u32) -> InstSimpleColorBuf {
+        assert!(inst_index == 1);
+
+        InstSimpleColorBuf::fill(&self.param.color, &self.calc_model_m())
+    }
+}
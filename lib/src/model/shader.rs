@@ -2,8 +2,8 @@ use std::mem;
 use std::num::NonZeroU32;
 
 use bytemuck::{Pod, Zeroable};
-use cgmath::Matrix4;
-use wgpu::{vertex_attr_array, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Device, Face, FrontFace, PolygonMode, PrimitiveState, PrimitiveTopology, Sampler, SamplerBindingType, ShaderStages, TextureView, TextureSampleType, TextureViewDimension, VertexAttribute, VertexBufferLayout, VertexStepMode};
+use cgmath::{Matrix4, Vector3};
+use wgpu::{vertex_attr_array, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState, Device, Face, FrontFace, PolygonMode, PrimitiveState, PrimitiveTopology, Sampler, SamplerBindingType, ShaderStages, TextureView, TextureSampleType, TextureViewDimension, VertexAttribute, VertexBufferLayout, VertexStepMode};
 
 use crate::util::IndexMap;
 
@@ -115,6 +115,21 @@ impl Color {
     pub fn from_srgb_float(r: f32, g: f32, b: f32) -> Self {
         Self::from_srgb_byte((r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8) // TODO: clamping?
     }
+
+    pub fn to_srgb_byte(self) -> [u8; 3] {
+        // Convert linear to sRGB, the inverse of from_srgb_byte().
+
+        self.0.map(|linear| {
+            #[allow(clippy::excessive_precision)]
+            let srgb = if linear < 0.0031308 {
+                linear * 12.92
+            } else {
+                linear.powf(1.0 / 2.4) * 1.055 - 0.055
+            };
+
+            (srgb * u8::MAX as f32).round() as u8
+        })
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -283,6 +298,141 @@ impl InstGridBuf {
     }
 }
 
+const INST_TRANSCOLOR_ATTRS: [VertexAttribute; 6] = vertex_attr_array![ // See vertex shader->@location().
+    10 => Float32x3, // color
+    11 => Float32, // alpha
+    12 => Float32x4, // model_m
+    13 => Float32x4,
+    14 => Float32x4,
+    15 => Float32x4,
+];
+
+pub struct InstTransColor;
+
+impl InstTransColor {
+    fn new() -> Self {
+        Self {
+        }
+    }
+
+    fn get_bind_layouts(&self) -> BindLayouts {
+        empty_bind_layouts()
+    }
+
+    fn create_bind_group(&self, _device: &Device, _bg_layout: &BindGroupLayout) -> BindGroup {
+        panic!("No bind entries");
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct InstTransColorBuf {
+    color: Color,
+    alpha: f32,
+    model_m: [[f32; 4]; 4],
+}
+
+impl InstTransColorBuf {
+    pub fn fill(color: &Color, alpha: f32, model_m: &Matrix4<f32>) -> Self {
+        Self {
+            color: *color,
+            alpha,
+            model_m: (*model_m).into(),
+        }
+    }
+}
+
+const INST_VIGNETTE_ATTRS: [VertexAttribute; 6] = vertex_attr_array![ // See vertex shader->@location().
+    10 => Float32x3, // color
+    11 => Float32, // alpha
+    12 => Float32x4, // model_m
+    13 => Float32x4,
+    14 => Float32x4,
+    15 => Float32x4,
+];
+
+pub struct InstVignette;
+
+impl InstVignette {
+    fn new() -> Self {
+        Self {
+        }
+    }
+
+    fn get_bind_layouts(&self) -> BindLayouts {
+        empty_bind_layouts()
+    }
+
+    fn create_bind_group(&self, _device: &Device, _bg_layout: &BindGroupLayout) -> BindGroup {
+        panic!("No bind entries");
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct InstVignetteBuf {
+    color: Color,
+    alpha: f32,
+    model_m: [[f32; 4]; 4],
+}
+
+impl InstVignetteBuf {
+    pub fn fill(color: &Color, alpha: f32, model_m: &Matrix4<f32>) -> Self {
+        Self {
+            color: *color,
+            alpha,
+            model_m: (*model_m).into(),
+        }
+    }
+}
+
+const INST_RIBBON_ATTRS: [VertexAttribute; 7] = vertex_attr_array![ // See vertex shader->@location().
+    9 => Float32x3, // color
+    10 => Float32, // alpha
+    11 => Float32, // width
+    12 => Float32x3, // p0 (bezier control points)
+    13 => Float32x3, // p1
+    14 => Float32x3, // p2
+    15 => Float32x3, // p3
+];
+
+pub struct InstRibbon;
+
+impl InstRibbon {
+    fn new() -> Self {
+        Self {
+        }
+    }
+
+    fn get_bind_layouts(&self) -> BindLayouts {
+        empty_bind_layouts()
+    }
+
+    fn create_bind_group(&self, _device: &Device, _bg_layout: &BindGroupLayout) -> BindGroup {
+        panic!("No bind entries");
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Pod, Zeroable)]
+pub struct InstRibbonBuf {
+    color: Color,
+    alpha: f32,
+    width: f32,
+    p: [[f32; 3]; 4],
+}
+
+impl InstRibbonBuf {
+    pub fn fill(color: &Color, alpha: f32, width: f32, p: &[Vector3<f32>; 4]) -> Self {
+        Self {
+            color: *color,
+            alpha,
+            width,
+            p: p.map(|p| p.into()),
+        }
+    }
+}
+
 const INST_WINDOW_ATTRS: [VertexAttribute; 5] = vertex_attr_array![ // See vertex shader->@location().
     11 => Uint32x2, // bind_id
     12 => Float32x4, // model_m
@@ -364,6 +514,9 @@ pub mod InstShaderSize {
     pub static SimpleColor: usize = mem::size_of::<super::InstSimpleColorBuf>();
     pub static PhongColor: usize = mem::size_of::<super::InstPhongColorBuf>();
     pub static Grid: usize = mem::size_of::<super::InstGridBuf>();
+    pub static TransColor: usize = mem::size_of::<super::InstTransColorBuf>();
+    pub static Vignette: usize = mem::size_of::<super::InstVignetteBuf>();
+    pub static Ribbon: usize = mem::size_of::<super::InstRibbonBuf>();
     pub static Window: usize = mem::size_of::<super::InstWindowBuf>();
 }
 
@@ -372,6 +525,9 @@ pub enum InstShaderType {
     SimpleColor,
     PhongColor,
     Grid,
+    TransColor,
+    Vignette,
+    Ribbon,
     Window,
 }
 
@@ -381,15 +537,44 @@ impl InstShaderType {
             InstShaderType::SimpleColor => "simplec",
             InstShaderType::PhongColor => "phongc",
             InstShaderType::Grid => "grid",
+            InstShaderType::TransColor => "transc",
+            InstShaderType::Vignette => "vignette",
+            InstShaderType::Ribbon => "ribbon",
             InstShaderType::Window => "window",
         }
     }
 
+    pub fn get_blend_state(&self) -> BlendState {
+        match self {
+            InstShaderType::TransColor | InstShaderType::Vignette | InstShaderType::Ribbon => BlendState::ALPHA_BLENDING,
+            _ => BlendState::REPLACE,
+        }
+    }
+
+    pub fn get_depth_write(&self) -> bool {
+        // Translucent objects shouldn't hide the ones behind them.
+
+        !matches!(self, InstShaderType::TransColor | InstShaderType::Vignette | InstShaderType::Ribbon)
+    }
+
+    pub fn get_render_order(&self) -> u32 {
+        // Translucent objects are rendered after the opaque ones, overlays are rendered last.
+
+        match self {
+            InstShaderType::TransColor | InstShaderType::Ribbon => 1,
+            InstShaderType::Vignette => 2,
+            _ => 0,
+        }
+    }
+
     pub fn get_layout(&self) -> VertexBufferLayout<'_> {
         let (array_stride, attributes) = match self {
             InstShaderType::SimpleColor => (InstShaderSize::SimpleColor, INST_SIMPLECOLOR_ATTRS.as_slice()),
             InstShaderType::PhongColor => (InstShaderSize::PhongColor, INST_PHONGCOLOR_ATTRS.as_slice()),
             InstShaderType::Grid => (InstShaderSize::Grid, INST_GRID_ATTRS.as_slice()),
+            InstShaderType::TransColor => (InstShaderSize::TransColor, INST_TRANSCOLOR_ATTRS.as_slice()),
+            InstShaderType::Vignette => (InstShaderSize::Vignette, INST_VIGNETTE_ATTRS.as_slice()),
+            InstShaderType::Ribbon => (InstShaderSize::Ribbon, INST_RIBBON_ATTRS.as_slice()),
             InstShaderType::Window => (InstShaderSize::Window, INST_WINDOW_ATTRS.as_slice()),
         };
 
@@ -405,6 +590,9 @@ impl InstShaderType {
             InstShaderType::SimpleColor => InstShaderImplType::SimpleColor(InstSimpleColor::new()),
             InstShaderType::PhongColor => InstShaderImplType::PhongColor(InstPhongColor::new()),
             InstShaderType::Grid => InstShaderImplType::Grid(InstGrid::new()),
+            InstShaderType::TransColor => InstShaderImplType::TransColor(InstTransColor::new()),
+            InstShaderType::Vignette => InstShaderImplType::Vignette(InstVignette::new()),
+            InstShaderType::Ribbon => InstShaderImplType::Ribbon(InstRibbon::new()),
             InstShaderType::Window => InstShaderImplType::Window(InstWindow::new()),
         }
     }
@@ -414,6 +602,9 @@ pub enum InstShaderImplType {
     SimpleColor(InstSimpleColor),
     PhongColor(InstPhongColor),
     Grid(InstGrid),
+    TransColor(InstTransColor),
+    Vignette(InstVignette),
+    Ribbon(InstRibbon),
     Window(InstWindow),
 }
 
@@ -463,6 +654,9 @@ impl InstShaderImplType {
             InstShaderImplType::SimpleColor(inst_sh_impl) => inst_sh_impl.create_bind_group(device, bg_layout),
             InstShaderImplType::PhongColor(inst_sh_impl) => inst_sh_impl.create_bind_group(device, bg_layout),
             InstShaderImplType::Grid(inst_sh_impl) => inst_sh_impl.create_bind_group(device, bg_layout),
+            InstShaderImplType::TransColor(inst_sh_impl) => inst_sh_impl.create_bind_group(device, bg_layout),
+            InstShaderImplType::Vignette(inst_sh_impl) => inst_sh_impl.create_bind_group(device, bg_layout),
+            InstShaderImplType::Ribbon(inst_sh_impl) => inst_sh_impl.create_bind_group(device, bg_layout),
             InstShaderImplType::Window(inst_sh_impl) => inst_sh_impl.create_bind_group(device, bg_layout),
         }
     }
@@ -472,6 +666,9 @@ impl InstShaderImplType {
             InstShaderImplType::SimpleColor(inst_sh_impl) => inst_sh_impl.get_bind_layouts(),
             InstShaderImplType::PhongColor(inst_sh_impl) => inst_sh_impl.get_bind_layouts(),
             InstShaderImplType::Grid(inst_sh_impl) => inst_sh_impl.get_bind_layouts(),
+            InstShaderImplType::TransColor(inst_sh_impl) => inst_sh_impl.get_bind_layouts(),
+            InstShaderImplType::Vignette(inst_sh_impl) => inst_sh_impl.get_bind_layouts(),
+            InstShaderImplType::Ribbon(inst_sh_impl) => inst_sh_impl.get_bind_layouts(),
             InstShaderImplType::Window(inst_sh_impl) => inst_sh_impl.get_bind_layouts(),
         }
     }
@@ -6,7 +6,7 @@ use serde::Deserialize;
 use url::Url;
 
 use crate::net::Request;
-use crate::songdef::SongDifficulty;
+use crate::songdef::{self, SongCharacteristic, SongDifficulty};
 
 static API: LazyLock<Url> = LazyLock::new(|| Url::parse("https://beatsaver.com/api/").expect("Invalid url"));
 
@@ -130,6 +130,7 @@ impl BeatSaverSongStats {
 
 #[derive(Deserialize)]
 pub struct BeatSaverSongVersion {
+    hash: String,
     state: String,
     #[serde(rename = "coverURL")]
     cover_url: Url,
@@ -142,6 +143,10 @@ pub struct BeatSaverSongVersion {
 }
 
 impl BeatSaverSongVersion {
+    pub fn get_hash(&self) -> &str {
+        &self.hash
+    }
+
     pub fn get_state(&self) -> &str {
         &self.state
     }
@@ -165,16 +170,68 @@ impl BeatSaverSongVersion {
 
 #[derive(Deserialize)]
 pub struct BeatSaverSongVariant {
-    characteristic: String,
+    characteristic: SongCharacteristic,
     difficulty: SongDifficulty,
+    #[serde(default)]
+    chroma: bool,
+    #[serde(default)]
+    cinema: bool,
+    #[serde(default)]
+    me: bool,
+    #[serde(default)]
+    ne: bool,
+    #[serde(default)]
+    nps: f32,
+    #[serde(rename = "paritySummary")]
+    parity_summary_opt: Option<BeatSaverParitySummary>,
+}
+
+#[derive(Deserialize)]
+pub struct BeatSaverParitySummary {
+    errors: u32,
+    warns: u32,
+    resets: u32,
 }
 
 impl BeatSaverSongVariant {
-    pub fn get_characteristic(&self) -> &str {
-        &self.characteristic
+    pub fn get_characteristic(&self) -> SongCharacteristic {
+        self.characteristic
     }
 
     pub fn get_difficulty(&self) -> SongDifficulty {
         self.difficulty
     }
+
+    // BeatSaver doesn't tell apart the requirements and the suggestions, these are the mods used by the variant.
+
+    pub fn get_mods(&self) -> Vec<&'static str> {
+        [
+            (self.chroma, songdef::REQUIREMENT_CHROMA),
+            (self.cinema, songdef::REQUIREMENT_CINEMA),
+            (self.me, songdef::REQUIREMENT_MAPPING_EXTENSIONS),
+            (self.ne, songdef::REQUIREMENT_NOODLE_EXTENSIONS),
+        ].into_iter().filter_map(|(used, name)| used.then_some(name)).collect()
+    }
+
+    pub fn get_nps(&self) -> f32 {
+        self.nps
+    }
+
+    pub fn get_parity_summary(&self) -> Option<&BeatSaverParitySummary> {
+        self.parity_summary_opt.as_ref()
+    }
+}
+
+impl BeatSaverParitySummary {
+    pub fn get_errors(&self) -> u32 {
+        self.errors
+    }
+
+    pub fn get_warns(&self) -> u32 {
+        self.warns
+    }
+
+    pub fn get_resets(&self) -> u32 {
+        self.resets
+    }
 }
@@ -31,15 +31,7 @@ impl Request for ImageRequest {
             .bytes()
             .await?;
 
-        // Decode image, so we are independent of the actual UI toolkit.
-
-        let reader = Cursor::new(buf);
-        let image = ImageReader::new(reader)
-            .with_guessed_format().map_err(|_| image_Error::Decoding(DecodingError::from_format_hint(ImageFormatHint::Unknown)))? // TODO: Use Content-Type from response to avoid format guess?
-            .decode()?
-            .into_rgba8();
-
-        Ok(ImageResponse::new(image.width(), image.height(), image.into_raw().into_boxed_slice()))
+        Ok(ImageResponse::decode(&buf)?) // TODO: Use Content-Type from response to avoid format guess?
     }
 }
 
@@ -58,6 +50,18 @@ impl ImageResponse {
         }
     }
 
+    // Decode image, so we are independent of the actual UI toolkit (also used for the images of the songs).
+
+    pub fn decode(buf: &[u8]) -> Result<Self, image_Error> {
+        let reader = Cursor::new(buf);
+        let image = ImageReader::new(reader)
+            .with_guessed_format().map_err(|_| image_Error::Decoding(DecodingError::from_format_hint(ImageFormatHint::Unknown)))?
+            .decode()?
+            .into_rgba8();
+
+        Ok(Self::new(image.width(), image.height(), image.into_raw().into_boxed_slice()))
+    }
+
     pub fn get_width(&self) -> u32 {
         self.width
     }
@@ -3,7 +3,7 @@ use std::num::NonZeroU32;
 use std::rc::Rc;
 
 use bytemuck::NoUninit;
-use cgmath::Vector3;
+use cgmath::{Quaternion, Vector3};
 use wgpu::{Adapter, Device, Extent3d, Features, Limits, Queue, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView};
 
 cfg_select! {
@@ -110,6 +110,7 @@ pub trait Frame {
     fn get_multisample_view(&self) -> Option<&TextureView>;
     fn get_depth_view(&self) -> &TextureView;
     fn get_cam_pos(&self) -> Vector3<f32>; // TODO: For stereo rendering, is a single cam_pos (used for lighting calcs) sufficient? // TODO: return with &?
+    fn get_cam_rot(&self) -> Quaternion<f32>; // Identity looks at +y.
     fn get_view_m(&self) -> Self::OutputViewMat;
     fn end(self);
 }
@@ -1,6 +1,6 @@
 use std::cell::RefCell;
 
-use cgmath::{Angle, Deg, InnerSpace, Matrix, Matrix3, Matrix4, Point3, Rad, Vector3};
+use cgmath::{Angle, Deg, InnerSpace, Matrix, Matrix3, Matrix4, Point3, Quaternion, Rad, Rotation3, Vector3};
 use wgpu::{CompositeAlphaMode, CurrentSurfaceTexture, Device, DeviceDescriptor, Instance, InstanceDescriptor, PresentMode, Queue, RequestAdapterOptions, Surface, SurfaceConfiguration, SurfaceTarget, SurfaceTexture, TextureFormat, TextureUsages, TextureView};
 
 use crate::output::{DEPTH_FORMAT, NEAR_Z, FAR_Z, Frame, OutputInfo, ViewMat, create_texture, get_default_features, get_default_limits, get_sample_count};
@@ -146,7 +146,12 @@ impl WindowOutput {
 
         let inv_cam_m = Matrix3::from_cols(cam_m.x.truncate(),cam_m.y.truncate(),cam_m.z.truncate()).transpose();
         
-        let frame = WindowFrame::new(surface_texture, multisample_view, depth_view, view_m.into(), *cam_pos, inv_cam_m, aspect);
+        // Camera rotation of cam_dir without roll, like the view matrix above.
+
+        let dir = cam_dir.normalize();
+        let cam_rot = Quaternion::from_angle_z(Rad((-dir.x).atan2(dir.y))) * Quaternion::from_angle_x(Rad(dir.z.asin()));
+
+        let frame = WindowFrame::new(surface_texture, multisample_view, depth_view, view_m.into(), *cam_pos, cam_rot, inv_cam_m, aspect);
         WindowBegin::Frame(frame)
     }
 }
@@ -165,12 +170,14 @@ pub struct WindowFrame {
     depth_view: TextureView,
     view_m: OutputViewMat,
     cam_pos: Vector3<f32>,
+    cam_rot: Quaternion<f32>,
     inv_cam_m: Matrix3<f32>,
     aspect: f32,
 }
 
 impl WindowFrame {
-    fn new(surface_texture: SurfaceTexture, multisample_view: Option<TextureView>, depth_view: TextureView, view_m: OutputViewMat, cam_pos: Vector3<f32>, inv_cam_m: Matrix3<f32>, aspect: f32) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    fn new(surface_texture: SurfaceTexture, multisample_view: Option<TextureView>, depth_view: TextureView, view_m: OutputViewMat, cam_pos: Vector3<f32>, cam_rot: Quaternion<f32>, inv_cam_m: Matrix3<f32>, aspect: f32) -> Self {
         let color_view = surface_texture.texture.create_view(&Default::default());
 
         Self {
@@ -180,6 +187,7 @@ impl WindowFrame {
             depth_view,
             view_m,
             cam_pos,
+            cam_rot,
             inv_cam_m,
             aspect,
         }
@@ -216,6 +224,10 @@ impl Frame for WindowFrame {
         self.cam_pos
     }
 
+    fn get_cam_rot(&self) -> Quaternion<f32> {
+        self.cam_rot
+    }
+
     fn get_view_m(&self) -> Self::OutputViewMat {
         self.view_m
     }
@@ -6,7 +6,7 @@ use std::thread;
 use std::time::{Duration, Instant};
 
 use ash::vk::Handle;
-use cgmath::{Angle, Deg, Matrix4, Quaternion, Rad, Rotation3, Vector2, Vector3, Zero};
+use cgmath::{Angle, Deg, InnerSpace, Matrix4, Quaternion, Rad, Rotation3, Vector2, Vector3, Zero};
 use wgpu::{Device, DeviceDescriptor, Extent3d, Features, Instance, Queue, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView};
 
 #[cfg(target_os = "android")]
@@ -646,6 +646,8 @@ impl XROutput {
                         let scene_input = SceneInput {
                             pose_l_opt: pose_l_opt.as_ref().map(|pose| pose as &dyn ScenePose),
                             pose_r_opt: pose_r_opt.as_ref().map(|pose| pose as &dyn ScenePose),
+                            cam_pos: frame.get_cam_pos(),
+                            cam_rot: frame.get_cam_rot(),
                         };
 
                         main.render(frame, &scene_input);
@@ -733,6 +735,7 @@ impl XROutput {
 
         let mut view_calc_m = [Matrix4::zero(), Matrix4::zero()];
         let mut cam_pos = Vector3::zero();
+        let mut cam_rot = Quaternion::zero();
         let mut yaw = Vector2::zero();
 
         for (view, view_calc_m_single) in views.iter().zip(view_calc_m.iter_mut()) {
@@ -756,7 +759,12 @@ impl XROutput {
             cam_pos.y -= pos.z / 2.0;
             cam_pos.z += pos.y / 2.0;
 
-            let dir = Quaternion::new(rot.w, rot.x, -rot.z, rot.y) * Vector3::new(0.0, 1.0, 0.0);
+            // Camera rotation is the averaged rotation of the two views (they are close to each other).
+
+            let view_rot = Quaternion::new(rot.w, rot.x, -rot.z, rot.y);
+            cam_rot += view_rot / 2.0;
+
+            let dir = view_rot * Vector3::new(0.0, 1.0, 0.0);
             yaw += dir.truncate() / 2.0;
         }
 
@@ -781,6 +789,11 @@ impl XROutput {
         let origin = inner.origin_opt.as_ref().unwrap();
         let origin_calc_m = MY_TO_OPENXR_M * Matrix4::from_translation(origin.pos) * Matrix4::from(origin.rot);
 
+        // Transform camera position from xr space to game space, see calc_pose().
+
+        let cam_pos = origin.rot.conjugate() * (cam_pos - origin.pos);
+        let cam_rot = origin.rot.conjugate() * cam_rot.normalize();
+
         for (view_calc_m_single, view_m_single) in view_calc_m.iter().zip(view_m.iter_mut()) {
             *view_m_single = (view_calc_m_single * origin_calc_m).into();
         }
@@ -816,7 +829,7 @@ impl XROutput {
         let pose_l_opt = self.calc_pose(focused, origin, &left_location, click_l, scroll_l, &self.xr_left_haptic);
         let pose_r_opt = self.calc_pose(focused, origin, &right_location, click_r, scroll_r, &self.xr_right_haptic);
         
-        let frame = XRFrame::new(xr_swapchain, xr_stream, &self.xr_space, self.width, self.height, display_t, views, color_view, self.multisample_view.clone(), self.depth_view.clone(), view_m, cam_pos);
+        let frame = XRFrame::new(xr_swapchain, xr_stream, &self.xr_space, self.width, self.height, display_t, views, color_view, self.multisample_view.clone(), self.depth_view.clone(), view_m, cam_pos, cam_rot);
         Begin::Frame((frame, pose_l_opt, pose_r_opt))
     }
 
@@ -924,11 +937,12 @@ struct XRFrame<'a> {
     depth_view: TextureView,
     view_m: OutputViewMat,
     cam_pos: Vector3<f32>,
+    cam_rot: Quaternion<f32>,
 }
 
 impl<'a> XRFrame<'a> {
     #[allow(clippy::too_many_arguments)]
-    fn new(xr_swapchain: &'a mut openxr::Swapchain<openxr::Vulkan>, xr_stream: &'a mut openxr::FrameStream<openxr::Vulkan>, xr_space: &'a openxr::Space, width: u32, height: u32, display_t: openxr::Time, views: Vec<openxr::View>, color_view: TextureView, multisample_view: Option<TextureView>, depth_view: TextureView, view_m: OutputViewMat, cam_pos: Vector3<f32>) -> Self {
+    fn new(xr_swapchain: &'a mut openxr::Swapchain<openxr::Vulkan>, xr_stream: &'a mut openxr::FrameStream<openxr::Vulkan>, xr_space: &'a openxr::Space, width: u32, height: u32, display_t: openxr::Time, views: Vec<openxr::View>, color_view: TextureView, multisample_view: Option<TextureView>, depth_view: TextureView, view_m: OutputViewMat, cam_pos: Vector3<f32>, cam_rot: Quaternion<f32>) -> Self {
         Self {
             xr_swapchain,
             xr_stream,
@@ -942,6 +956,7 @@ impl<'a> XRFrame<'a> {
             depth_view,
             view_m,
             cam_pos,
+            cam_rot,
         }
     }
 }
@@ -965,6 +980,10 @@ impl<'a> Frame for XRFrame<'a> {
         self.cam_pos
     }
 
+    fn get_cam_rot(&self) -> Quaternion<f32> {
+        self.cam_rot
+    }
+
     fn get_view_m(&self) -> Self::OutputViewMat {
         self.view_m
     }
@@ -12,7 +12,7 @@ use crate::asset::AssetManagerRc;
 use crate::audio::AudioEngineRc;
 use crate::output::{Frame, OutputInfoRc, ViewMat};
 use crate::scene::{MenuParam, SceneInput, SceneManager};
-use crate::util::StatsRc;
+use crate::util::{SettingsRc, StatsRc};
 
 const QUERY_COUNT: u32 = 2;
 const QUERY_SIZE: u64 = QUERY_COUNT as u64 * mem::size_of::<u64>() as u64; // TODO: use constant wgpu::QUERY_SIZE.
@@ -48,7 +48,7 @@ struct Inner {
 }
 
 impl Render {
-    pub fn new(asset_mgr: AssetManagerRc, output_info: OutputInfoRc, stats: StatsRc, audio_engine: AudioEngineRc) -> Self {
+    pub fn new(asset_mgr: AssetManagerRc, output_info: OutputInfoRc, stats: StatsRc, settings: SettingsRc, audio_engine: AudioEngineRc) -> Self {
         let device = output_info.get_device();
 
         // Create query set to measure GPU execution time.
@@ -115,7 +115,7 @@ impl Render {
 
         // Create scene manager and load start scene.
 
-        let scene_mgr = SceneManager::new(Arc::clone(&asset_mgr), Rc::clone(&output_info), Arc::clone(&stats), uni_bg_layout, audio_engine);
+        let scene_mgr = SceneManager::new(Arc::clone(&asset_mgr), Rc::clone(&output_info), Arc::clone(&stats), settings, uni_bg_layout, audio_engine);
         scene_mgr.load(MenuParam::new()).expect("Unable to load scene");
 
         let inner = Inner {
@@ -0,0 +1,187 @@
+use std::rc::Rc;
+
+use cgmath::{Deg, Quaternion, Rotation3, Vector3};
+
+use crate::model::*;
+use crate::songinfo::{ColorScheme, LightColor, LightEvent, LightGroup, LightValue};
+
+const BEAM_THICKNESS: f32 = 0.3; // [m]
+
+// Everything should be closer than FAR_Z (see output).
+
+const BACK_LASER_DIST: f32 = 70.0; // [m]
+const BACK_LASER_LEN: f32 = 40.0; // [m]
+const SIDE_LASER_X: f32 = 20.0; // [m]
+const SIDE_LASER_LEN: f32 = 40.0; // [m]
+const RING_HALF_SIZE: f32 = 11.0; // [m]
+const RING_CENTER_Z: f32 = 12.0; // [m]
+const CENTER_LIGHT_X: f32 = 3.0; // [m]
+
+const FLASH_T: f32 = 0.6; // [s]
+const FADE_T: f32 = 1.5; // [s]
+const FLASH_BOOST: f32 = 1.5; // Brightness multiplier at the start of flash and fade
+
+// The environment is a set of light groups, which are controlled by the lighting events.
+
+pub struct Environment {
+    groups: Box<[LightGroupInfo]>,
+}
+
+struct LightGroupInfo {
+    events: Box<[LightEventInfo]>,
+    lights: Box<[Rc<Light>]>,
+}
+
+struct LightEventInfo {
+    ts: f32,
+    effect: LightEffect,
+    color: Color,
+    brightness: f32,
+}
+
+#[derive(Clone, Copy)]
+enum LightEffect {
+    Off,
+    On,
+    Flash,
+    Fade,
+    Transition,
+}
+
+impl Environment {
+    pub fn new<F: Fn(f32) -> Option<f32>>(model_reg: &mut ModelRegistry, color_scheme: &ColorScheme, light_events: &[LightEvent], calc_ts: F) -> Self {
+        // Setup geometry of the light groups (all of them are made of beams).
+
+        let mut create_beam = |pos: Vector3<f32>, size: Vector3<f32>, rot: Quaternion<f32>| {
+            let light = model_reg.create(LightParam::new(&COLOR_WHITE));
+            light.set_pos(&pos);
+            light.set_size(&size);
+            light.set_rot(&rot);
+
+            light
+        };
+
+        let beam_size = |len| Vector3::new(BEAM_THICKNESS, BEAM_THICKNESS, len);
+
+        let back_lasers = Vec::from_iter((-2..=2).map(|i| {
+            let pos = Vector3::new(i as f32 * 10.0, BACK_LASER_DIST, BACK_LASER_LEN / 2.0);
+            create_beam(pos, beam_size(BACK_LASER_LEN), Quaternion::from_angle_y(Deg(i as f32 * 15.0)))
+        }));
+
+        let left_lasers = Vec::from_iter((0..4).map(|i| {
+            let pos = Vector3::new(-SIDE_LASER_X, 20.0 + i as f32 * 15.0, SIDE_LASER_LEN / 2.0);
+            create_beam(pos, beam_size(SIDE_LASER_LEN), Quaternion::from_angle_y(Deg(30.0)))
+        }));
+
+        let right_lasers = Vec::from_iter((0..4).map(|i| {
+            let pos = Vector3::new(SIDE_LASER_X, 20.0 + i as f32 * 15.0, SIDE_LASER_LEN / 2.0);
+            create_beam(pos, beam_size(SIDE_LASER_LEN), Quaternion::from_angle_y(Deg(-30.0)))
+        }));
+
+        let mut ring_lights = Vec::new();
+
+        for i in 0..6 {
+            let y = 25.0 + i as f32 * 12.0;
+            let len = 2.0 * RING_HALF_SIZE;
+            let no_rot = Quaternion::from_angle_y(Deg(0.0));
+
+            ring_lights.push(create_beam(Vector3::new(0.0, y, RING_CENTER_Z + RING_HALF_SIZE), Vector3::new(len, BEAM_THICKNESS, BEAM_THICKNESS), no_rot));
+            ring_lights.push(create_beam(Vector3::new(0.0, y, RING_CENTER_Z - RING_HALF_SIZE), Vector3::new(len, BEAM_THICKNESS, BEAM_THICKNESS), no_rot));
+            ring_lights.push(create_beam(Vector3::new(-RING_HALF_SIZE, y, RING_CENTER_Z), beam_size(len), no_rot));
+            ring_lights.push(create_beam(Vector3::new(RING_HALF_SIZE, y, RING_CENTER_Z), beam_size(len), no_rot));
+        }
+
+        let center_lights = Vec::from_iter([-CENTER_LIGHT_X, CENTER_LIGHT_X].into_iter().map(|x| {
+            create_beam(Vector3::new(x, 45.0, 0.0), Vector3::new(0.1, 80.0, 0.05), Quaternion::from_angle_y(Deg(0.0)))
+        }));
+
+        // Collect events of the light groups.
+
+        let groups = Box::from_iter([
+            (LightGroup::BackLasers, back_lasers),
+            (LightGroup::RingLights, ring_lights),
+            (LightGroup::LeftLasers, left_lasers),
+            (LightGroup::RightLasers, right_lasers),
+            (LightGroup::CenterLights, center_lights),
+        ].into_iter().map(|(group, lights)| {
+            let events = Box::from_iter(light_events.iter().filter(|light_event| light_event.get_group() == group).filter_map(|light_event| {
+                calc_ts(light_event.get_bpm_pos()).map(|ts| {
+                    let get_color = |light_color| match light_color {
+                        LightColor::Left => *color_scheme.get_env_color_l(),
+                        LightColor::Right => *color_scheme.get_env_color_r(),
+                        LightColor::White => COLOR_WHITE,
+                    };
+
+                    let (effect, color) = match light_event.get_value() {
+                        LightValue::Off => (LightEffect::Off, Color([0.0, 0.0, 0.0])),
+                        LightValue::On(light_color) => (LightEffect::On, get_color(light_color)),
+                        LightValue::Flash(light_color) => (LightEffect::Flash, get_color(light_color)),
+                        LightValue::Fade(light_color) => (LightEffect::Fade, get_color(light_color)),
+                        LightValue::Transition(light_color) => (LightEffect::Transition, get_color(light_color)),
+                    };
+
+                    LightEventInfo {
+                        ts,
+                        effect,
+                        color,
+                        brightness: light_event.get_brightness(),
+                    }
+                })
+            }));
+
+            LightGroupInfo {
+                events,
+                lights: lights.into_boxed_slice(),
+            }
+        }));
+
+        Self {
+            groups,
+        }
+    }
+
+    pub fn update(&self, ts: f32) {
+        for group in &self.groups {
+            let (color, brightness) = Self::calc_light(&group.events, ts);
+            let visible = brightness > 0.0;
+            let color = Color(color.0.map(|value| value * brightness));
+
+            for light in &group.lights {
+                light.set_visible(visible);
+                light.set_color(&color);
+            }
+        }
+    }
+
+    fn calc_light(events: &[LightEventInfo], ts: f32) -> (Color, f32) {
+        // The light is determined by the last event and by the next event, if it is a transition.
+
+        let index = events.partition_point(|event| event.ts <= ts); // First index, where event.ts > ts
+        if index == 0 {
+            return (Color([0.0, 0.0, 0.0]), 0.0);
+        }
+
+        let event = &events[index - 1];
+        let dt = ts - event.ts;
+
+        let mut color = event.color;
+        let mut brightness = match event.effect {
+            LightEffect::Off => 0.0,
+            LightEffect::On | LightEffect::Transition => event.brightness,
+            LightEffect::Flash => event.brightness * (1.0 + (FLASH_BOOST - 1.0) * (1.0 - dt / FLASH_T).max(0.0)),
+            LightEffect::Fade => event.brightness * FLASH_BOOST * (1.0 - dt / FADE_T).max(0.0),
+        };
+
+        if let Some(next_event) = events.get(index) && matches!(next_event.effect, LightEffect::Transition) {
+            let factor = dt / (next_event.ts - event.ts);
+
+            for (value, next_value) in color.0.iter_mut().zip(next_event.color.0) {
+                *value += (next_value - *value) * factor;
+            }
+
+            brightness += (next_event.brightness - brightness) * factor;
+        }
+
+        (color, brightness)
+    }
+}
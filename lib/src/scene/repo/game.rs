@@ -1,33 +1,74 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 #[cfg(feature = "test")]
-use std::time::Instant;
+use std::time::Duration;
 
-use cgmath::{Angle, Deg, InnerSpace, Matrix4, Quaternion, Rotation3, Vector3};
+use cgmath::{Angle, Deg, InnerSpace, Matrix4, Quaternion, Rotation3, Vector3, Zero};
 
+use crate::APP_VERSION;
 use crate::asset::AssetManagerRc;
-use crate::audio::{AudioEngineRc, AudioFile, AudioFileHandle, AudioTimestamp};
+use crate::audio::{AudioEngineRc, AudioFader, AudioFaderHandle, AudioFile, AudioFileHandle, AudioTimestamp};
+use crate::bsor::{self, Replay, ReplayCutInfo, ReplayFrame, ReplayInfo, ReplayNote, ReplayNoteEvent, ReplayNoteKind, ReplayPause, ReplayPose, ReplayWall};
+use crate::mailbox::{self, Receiver, TryRecvError};
 use crate::model::*;
 use crate::net::NetManager;
 use crate::output::OutputInfoRc;
-use crate::scene::{MenuParam, Scene, SceneFactory, SceneInput, SceneManager, ScenePose, create_floor, create_saber, create_stats_window};
-use crate::songinfo::{BPMInfo, NoteCutDir, NoteType, SongInfo};
-use crate::ui::{GameStatsWindow, UILoop};
+use crate::scene::{Environment, MenuParam, ResultsParam, Scene, SceneFactory, SceneInput, SceneManager, ScenePose, create_floor, create_pointer, create_saber, create_stats_window, place_stats_window};
+use crate::score::{self, ACCURACY_DIST, Multiplier, SCORE_ACCURACY, SCORE_CHAIN_HEAD, SCORE_CHAIN_LINK, SCORE_NOTE, SCORE_POST_SWING, SCORE_PRE_SWING};
+use crate::songdef::SongCharacteristic;
+use crate::songinfo::{ArcMidAnchor, ArcPoint, BeatmapInfo, Note, NoteCustomData, NoteCutDir, NoteType, SongArc, SongInfo, Timing};
+use crate::ui::{FeedbackWindow, FeedbackWindowKind, GameStatsWindow, PauseWindow, UILoop};
 use crate::ui::slintimpl;
-use crate::util::StatsRc;
+use crate::util::{self, SettingsRc, StatsRc};
 
 const CUBE_SIZE: f32 = 0.5; // [m]
 const CUBE_SPACING: f32 = 0.10; // [m]
 const CUBE_FLOOR: f32 = 0.6; // [m]
+const CHAIN_LINK_SCALE: f32 = 0.4; // Relative to CUBE_SIZE
+
+const COLOR_BOMB: Color = Color([0.02, 0.02, 0.02]);
+
+const WALL_ALPHA: f32 = 0.3;
+const ARC_ALPHA: f32 = 0.6;
+const ARC_WIDTH: f32 = 0.05; // [m]
+const ARC_CTRL_DIST: f32 = 1.0; // Distance of the bezier control point from the head/tail, if multiplier is 1 [m]
+const ARC_POINT_TOLERANCE: f32 = 0.001; // Notes are at the head/tail of an arc, if they are this close to it [beat]
+const VIGNETTE_ALPHA: f32 = 0.8;
+const LANE_FOLLOW_SPEED: f32 = 4.0; // Floor and HUD windows are following the lane direction with this rate [1/s]
+const FEEDBACK_T: f32 = 0.6; // Feedback of the failed notes is displayed for this long [s]
+const FAIL_T: f32 = 2.0; // Length of the fail animation [s]
+const FAIL_FADE_RATE: u8 = 40; // [dB/s]
+const RESUME_T: f32 = 3.0; // Countdown before the game is resumed [s]
+
+const PRE_SWING_ANGLE: f32 = 100.0; // [deg]
+const POST_SWING_ANGLE: f32 = 60.0; // [deg]
+const POST_SWING_T: f32 = 0.4; // The post-swing is rated at most for this long after the cut [s]
+const SWING_END_ANGLE: f32 = 5.0; // The swing is over, once the saber turns back by this angle [deg]
+const SWING_HISTORY_T: f32 = 0.5; // [s]
+const ENERGY_START: f32 = 0.5; // The energy is between 0 and 1, the song is failed at 0
+const ENERGY_CUT: f32 = 0.01;
+const ENERGY_MISS: f32 = -0.15;
+const ENERGY_CHAIN_LINK_MISS: f32 = -0.03;
+const ENERGY_BAD_CUT: f32 = -0.1;
+const ENERGY_BOMB_HIT: f32 = -0.15;
+const ENERGY_WALL: f32 = -1.3; // While the head is inside an obstacle [1/s]
+
+const CUT_DIR_TOLERANCE: f32 = 60.0; // Maximum angle between the cut direction and the movement of the saber [deg]
+const CUT_MIN_SPEED: f32 = 1.5; // Slower cuts are bad cuts, measured at the tip of the saber [m/s]
+const CUT_SPEED_T: f32 = 0.05; // The speed is averaged for this long [s]
 
 const OFFSET_Y: f32 = CUBE_SIZE / 2.0 + 1.0; // When ts == cube_info.ts, then distance between the player and center of the cube [m]
 
-// TODO: These are depending on songinfo + environmental geometry:
+// TODO: These are depending on environmental geometry (zones 2 and 3 are depending on the jump of the beatmap):
 const ZONE_IN1_DIST: f32 = 100.0; // [m]
 const ZONE_IN1_V: f32 = 200.0; // [m/s]
-const ZONE_IN2_DIST: f32 = 10.0; // [m]
-const ZONE_IN3_DIST: f32 = 10.0; // [m]
 const ZONE_OUT_DIST: f32 = 15.0; // [m]
 
 const G: f32 = 9.8; // [m/s2]
@@ -36,6 +77,7 @@ pub struct GameParam {
     asset_mgr: AssetManagerRc,
     song_info: SongInfo,
     beatmap_info_index: usize, // TODO: usize or smaller?
+    practice_opt: Option<PracticeParam>,
     #[cfg(feature = "test")]
     test: bool,
 }
@@ -46,30 +88,121 @@ impl GameParam {
             asset_mgr,
             song_info,
             beatmap_info_index,
+            practice_opt: None,
             #[cfg(feature = "test")]
             test,
         }
     }
+
+    pub fn with_practice(mut self, practice: PracticeParam) -> Self {
+        self.practice_opt = Some(practice);
+        self
+    }
+}
+
+impl GameParam {
+    // The song info is owned by the game, so it is loaded again when the same beatmap is played again.
+
+    pub fn reload(asset_mgr: AssetManagerRc, beatmap_info_index: usize, #[cfg(feature = "test")] test: bool) -> Result<Self, String> {
+        #[cfg(feature = "test")]
+        if test {
+            let song_info = SongInfo::test(Arc::clone(&asset_mgr));
+            return Ok(Self::new(asset_mgr, song_info, beatmap_info_index, test));
+        }
+
+        let song_info = SongInfo::load(Arc::clone(&asset_mgr)).map_err(|e| format!("Unable to load song info: {}", e))?;
+        Ok(Self::new(asset_mgr, song_info, beatmap_info_index, #[cfg(feature = "test")] test))
+    }
+}
+
+// Practice mode: the song is started from start, and if there is a loop (start, end), then the song is
+// rewound to its start, once its end is reached [s]. The song is played at speed (the objects are
+// following the song).
+#[derive(Clone, Copy)]
+pub struct PracticeParam {
+    start: f32,
+    loop_opt: Option<(f32, f32)>,
+    speed: f32,
+}
+
+impl PracticeParam {
+    pub fn new(start: f32, loop_opt: Option<(f32, f32)>, speed: f32) -> Result<Self, String> {
+        if !start.is_finite() || start < 0.0 {
+            return Err(format!("Invalid practice start: {}", start));
+        }
+
+        if !speed.is_finite() || speed <= 0.0 {
+            return Err(format!("Invalid practice speed: {}", speed));
+        }
+
+        if let Some((loop_start, loop_end)) = loop_opt && !(loop_start >= 0.0 && loop_start < loop_end) {
+            return Err(format!("Invalid practice loop: {} - {}", loop_start, loop_end));
+        }
+
+        Ok(Self {
+            start,
+            loop_opt,
+            speed,
+        })
+    }
 }
 
 impl SceneFactory for GameParam {
     type Scene = Game;
     type Error = String;
 
-    fn load(self, _asset_mgr: AssetManagerRc, model_reg: &mut ModelRegistry, _output_info: OutputInfoRc, stats: StatsRc, audio_engine: AudioEngineRc, ui_loop: &UILoop, _net_manager: &NetManager) -> Result<Self::Scene, Self::Error> {
-        Game::new(self, model_reg, stats, audio_engine, ui_loop)
+    fn load(self, _asset_mgr: AssetManagerRc, model_reg: &mut ModelRegistry, _output_info: OutputInfoRc, stats: StatsRc, settings: SettingsRc, audio_engine: AudioEngineRc, ui_loop: &UILoop, _net_manager: &NetManager) -> Result<Self::Scene, Self::Error> {
+        Game::new(self, model_reg, stats, settings, audio_engine, ui_loop)
     }
 }
 
 pub struct Game {
+    asset_mgr: AssetManagerRc,
+    beatmap_info_index: usize, // For restarting the game
+    song_name: String, // For the results
+    practice_opt: Option<PracticeParam>,
+    replay_dir_opt: Option<PathBuf>, // Set, if the game is recorded
+    #[cfg(feature = "test")]
+    test: bool,
+    ui_loop: UILoop,
+    pause_window: Rc<Window>,
+    pause_window_weak: slintimpl::Weak<PauseWindow>,
+    pause_window_rx: Receiver<PauseMessage>,
+    playfield: Playfield,
+    inner: RefCell<Inner>,
+}
+
+// The objects of a beatmap, which are played along the song. They are driven by the sabers of the
+// player (see Game) or by the recorded ones (see Playback), the outcome is collected by GameStats.
+pub struct Playfield {
+    asset_mgr: AssetManagerRc,
+    song_filename: String, // For rewinding the song
+    speed: f32, // Of the song, see PracticeParam
+    audio_engine: AudioEngineRc,
     ui_loop: UILoop,
+    jump_dist: f32, // For the replays [m]
     zone_info: Rc<ZoneInfo>,
     cube_infos: Box<[Rc<CubeInfo>]>,
+    bomb_infos: Box<[Rc<BombInfo>]>,
+    obstacle_infos: Box<[Rc<ObstacleInfo>]>,
+    arc_infos: Box<[Rc<ArcInfo>]>,
+    chain_link_infos: Box<[Rc<ChainLinkInfo>]>,
+    lane_rotation_infos: Box<[LaneRotationInfo]>,
+    game_stats_window: Rc<Window>,
     game_stats_window_weak: slintimpl::Weak<GameStatsWindow>,
+    feedback_window: Rc<Window>,
+    feedback_window_weak: slintimpl::Weak<FeedbackWindow>,
+    stats_window: Rc<Window>,
+    floor: Rc<Floor>,
     saber_l: Rc<Saber>,
     saber_r: Rc<Saber>,
-    audio_info_opt: Option<AudioInfo>,
-    inner: RefCell<Inner>,
+    pointer: Rc<Pointer>,
+    saber_history_l: Rc<SaberHistory>,
+    saber_history_r: Rc<SaberHistory>,
+    one_saber: bool,
+    vignette: Rc<Vignette>,
+    environment: Environment,
+    inner: RefCell<PlayfieldInner>,
 }
 
 struct ZoneInfo {
@@ -86,28 +219,142 @@ struct ZoneInfo {
 
 struct CubeInfo {
     ts: f32,
+    note_id: i32, // See bsor::calc_note_id
     x: f32,
     z: f32,
     note_type: NoteType,
     angle: f32,
+    lane_rot: Quaternion<f32>,
     any: bool,
+    spawn_effect: bool, // If false, then the cube appears at the end of zone 1 (instead of flying in)
+    cube: Rc<Cube>,
+    chain_info_opt: Option<Rc<ChainInfo>>, // Set, if the cube is a chain head
+}
+
+struct BombInfo {
+    ts: f32,
+    note_id: i32,
+    x: f32,
+    z: f32,
+    lane_rot: Quaternion<f32>,
+    bomb: Rc<Bomb>,
+}
+
+struct ObstacleInfo {
+    ts: f32,
+    wall_id: i32, // See bsor::calc_wall_id
+    ts_end: f32,
+    x_min: f32,
+    x_max: f32,
+    z_min: f32,
+    z_max: f32,
+    lane_rot: Quaternion<f32>,
+    wall: Rc<Wall>,
+}
+
+struct ArcInfo {
+    head: ArcEndInfo,
+    tail: ArcEndInfo,
+    mid_anchor: f32, // 0: straight, 1: clockwise, -1: counter-clockwise
+    lane_rot: Quaternion<f32>, // The whole arc is on the lane of its head
+    ribbon: Rc<Ribbon>,
+}
+
+struct ArcEndInfo {
+    ts: f32,
+    x: f32,
+    z: f32,
+    dir: Vector3<f32>,
+    multiplier: f32,
+}
+
+// Shared by the chain head and its links.
+struct ChainInfo {
+    head_cut: Cell<bool>,
+}
+
+struct ChainLinkInfo {
+    ts: f32,
+    note_id: i32,
+    x: f32,
+    z: f32,
+    note_type: NoteType,
+    angle: f32,
+    lane_rot: Quaternion<f32>,
     cube: Rc<Cube>,
+    chain_info: Rc<ChainInfo>,
+}
+
+// Recent poses of a saber, the swings are rated by them.
+struct SaberHistory {
+    entries: RefCell<VecDeque<SaberHistoryEntry>>,
+}
+
+struct SaberHistoryEntry {
+    ts: f32,
+    pos: Vector3<f32>,
+    dir: Vector3<f32>, // Normalized
+}
+
+// The lane direction, which is faced by the player from the given ts.
+struct LaneRotationInfo {
+    ts: f32,
+    rotation: f32,
 }
 
 struct AudioInfo {
     handle: AudioFileHandle,
+    fader_handle: AudioFaderHandle,
     ts: AudioTimestamp,
 }
 
+#[derive(Clone, Copy)]
+enum PlayState {
+    Playing,
+    Paused(Option<Instant>), // The resume countdown has been started at the instant
+    Failing(f32), // The fail animation has been started at ts
+    Failed, // The fail animation is over, the results are shown
+}
+
+// Position of the song at an update.
+pub enum SongPos {
+    At(f32), // Song timestamp [s]
+    End,
+    Unknown, // The audio has no timestamp yet
+}
+
 struct Inner {
+    prev_click: bool,
+    prev_poses: bool, // Any of the poses were present at the previous update
+    pause_countdown: u32, // Displayed on the pause window [s]
+    pause_start: Instant, // For the pauses of the replay
+    replay_opt: Option<Replay>, // Frames and pauses are recorded here, the events are collected by GameStats
+}
+
+struct PlayfieldInner {
+    audio_info_opt: Option<AudioInfo>, // Replaced, when the song is rewound
+    audio_start: f32, // Song timestamp, where the audio file has been started [s]
+    seek_ts_opt: Option<f32>, // The objects before are skipped at the next update [s]
     start: bool,
     #[cfg(feature = "test")]
     start_time: Instant,
     alive_objs: AliveObjs,
     cube_range_end: usize,
+    bomb_range_end: usize,
+    obstacle_range_end: usize,
+    arc_range_end: usize,
+    chain_link_range_end: usize,
     prev_audio_ts: f32,
-    prev_click: bool,
+    lane_rotation: f32,
     game_stats: GameStats,
+    feedback_end_ts: f32,
+    play_state: PlayState,
+}
+
+enum PauseMessage {
+    Resume,
+    Restart,
+    Quit,
 }
 
 type AliveObjs = Vec<Box<dyn Obj>>;
@@ -123,14 +370,152 @@ enum UpdateResult {
     Replace(AliveObjs),
 }
 
-impl Game {
-    fn new(param: GameParam, model_reg: &mut ModelRegistry, stats: StatsRc, audio_engine: AudioEngineRc, ui_loop: &UILoop) -> Result<Self, String> {
-        let song_info = param.song_info;
+impl ZoneInfo {
+    // Calculate y, z_base and angle of an object, which reaches the player at ts == 0.
+
+    fn calc_placement(&self, ts: f32, angle: f32) -> (f32, f32, f32) {
+        if ts <= 0.0 {
+            (ts * self.out_v, CUBE_FLOOR, angle)
+        } else if ts <= self.in3_t {
+            (ts * self.in3_v, CUBE_FLOOR, angle)
+        } else if ts <= self.in23_t {
+            let factor = (self.in23_t - ts) / (self.in23_t - self.in3_t);
+            (self.in2_dist * (1.0 - factor) + self.in3_dist, CUBE_FLOOR * Deg(90.0 * factor).sin(), angle * factor)
+        } else {
+            let factor = (self.in123_t - ts) / (self.in123_t - self.in23_t);
+            (self.in1_dist * (1.0 - factor) + self.in2_dist + self.in3_dist, 0.0, 0.0)
+        }
+    }
+}
 
-        // Determine color scheme.
+fn calc_cut_dir_vec(cut_dir: NoteCutDir) -> Vector3<f32> {
+    // Direction of the swing in the XZ plane.
+
+    let (x, z) = cut_dir.get_dir();
+    Vector3::new(x, 0.0, z)
+}
+
+fn calc_grid_pos(x_val: f32, y_val: f32) -> (f32, f32) {
+    // The grid is 4 columns wide, the values are not necessarily integers (see chain links).
+
+    let x = (x_val - 1.5) * (CUBE_SIZE + CUBE_SPACING);
+    let z = y_val * (CUBE_SIZE + CUBE_SPACING) + CUBE_SIZE / 2.0;
+
+    (x, z)
+}
+
+fn calc_lane_rot(rotation: f32) -> Quaternion<f32> {
+    // Lanes are rotated about the player, positive rotation is clockwise (seen from above).
+
+    Quaternion::from_angle_z(Deg(-rotation))
+}
+
+fn create_audio_info(asset_mgr: &AssetManagerRc, song_filename: &str, audio_engine: &AudioEngineRc, start: f32, speed: f32) -> Result<AudioInfo, String> {
+    let asset_file = asset_mgr.open(song_filename).map_err(|e| format!("Unable to open audio file: {:?}", e))?; // TODO: instead of debug, use display trait for formatting error msg?
+
+    let (file_input, handle) = AudioFile::new_at(asset_file, start, speed);
+    let (fader_input, fader_handle) = AudioFader::new(file_input);
+    let ts = audio_engine.add(fader_input);
+
+    Ok(AudioInfo {
+        handle,
+        fader_handle,
+        ts,
+    })
+}
+
+fn create_replay(song_info: &SongInfo, beatmap_info: &BeatmapInfo, jump_dist: f32) -> Replay {
+    // The hash identifies the song for the BSOR tools, the recording is still useful without it.
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+
+    let info = ReplayInfo {
+        version: APP_VERSION.clone(),
+        timestamp: timestamp.to_string(),
+        platform: std::env::consts::OS.to_string(),
+        hash: song_info.calc_hash().unwrap_or_default(),
+        song_name: song_info.get_title().to_string(),
+        mapper: song_info.get_metadata().get_level_author().to_string(),
+        difficulty: util::to_serde_name(&beatmap_info.get_difficulty()),
+        mode: util::to_serde_name(&beatmap_info.get_characteristic()),
+        environment: song_info.get_environment_name(beatmap_info).unwrap_or_default().to_string(),
+        jump_distance: jump_dist,
+        ..ReplayInfo::default()
+    };
+
+    Replay {
+        info,
+        frames: Vec::new(),
+        notes: Vec::new(),
+        walls: Vec::new(),
+        heights: Vec::new(),
+        pauses: Vec::new(),
+    }
+}
+
+// Arc points are sorted by their position in beats.
+fn is_arc_point(arc_points: &[(f32, u8, u8, NoteType)], note: &Note) -> bool {
+    let bpm_pos = note.get_bpm_pos();
+    let start = arc_points.partition_point(|(arc_bpm_pos, _, _, _)| *arc_bpm_pos < bpm_pos - ARC_POINT_TOLERANCE);
+
+    arc_points[start..].iter().take_while(|(arc_bpm_pos, _, _, _)| *arc_bpm_pos <= bpm_pos + ARC_POINT_TOLERANCE).any(|(_, x, y, note_type)| {
+        *x == note.get_x() && *y == note.get_y() && *note_type == note.get_note_type()
+    })
+}
+
+fn record_frame(replay: &mut Replay, audio_ts: f32, scene_input: &SceneInput) {
+    // Missing sabers are recorded at the origin.
+
+    let calc_saber_pose = |pose_opt: Option<&dyn ScenePose>| {
+        pose_opt.map_or(ReplayPose::empty(), |pose| ReplayPose::from_saber(pose.get_pos(), pose.get_rot()))
+    };
+
+    if replay.frames.is_empty() {
+        replay.info.height = scene_input.cam_pos.z;
+    }
+
+    let ts_diff = replay.frames.last().map_or(0.0, |frame| audio_ts - frame.time);
+
+    replay.frames.push(ReplayFrame {
+        time: audio_ts,
+        fps: if ts_diff > 0.0 { (1.0 / ts_diff).round() as i32 } else { 0 },
+        head: ReplayPose::from_head(&scene_input.cam_pos, &scene_input.cam_rot),
+        left: calc_saber_pose(scene_input.pose_l_opt),
+        right: calc_saber_pose(scene_input.pose_r_opt),
+    });
+}
+
+fn place_pause_window(window: &Window, lane_rot: &Quaternion<f32>) {
+    window.set_pos(&(lane_rot * Vector3::new(0.0, 2.5, 1.6)));
+    window.set_rot(lane_rot);
+}
 
+fn place_feedback_window(window: &Window, lane_rot: &Quaternion<f32>) {
+    window.set_pos(&(lane_rot * Vector3::new(0.0, 4.0, 0.3)));
+    window.set_rot(lane_rot);
+}
+
+fn place_game_stats_window(window: &Window, lane_rot: &Quaternion<f32>) {
+    window.set_pos(&(lane_rot * Vector3::new(-3.0, 6.0, 3.0)));
+    window.set_rot(&(lane_rot * Quaternion::from_angle_z(Deg(20.0))));
+}
+
+impl Playfield {
+    pub fn new(param: &GameParam, replay_info_opt: Option<&ReplayInfo>, model_reg: &mut ModelRegistry, stats: StatsRc, settings: &SettingsRc, audio_engine: AudioEngineRc, ui_loop: &UILoop) -> Result<Self, String> {
+        let song_info = &param.song_info;
         let beatmap_info = &song_info.get_beatmap_infos()[param.beatmap_info_index];
 
+        // Refuse beatmaps, which would be played incorrectly. Per-note custom data (Chroma/Noodle Extensions) is opt-in.
+
+        let custom_data = settings.get_inner().custom_data;
+
+        let unsupported_requirements = beatmap_info.get_unsupported_requirements(custom_data);
+        if !unsupported_requirements.is_empty() {
+            return Err(format!("The beatmap requires unsupported mods: {}", unsupported_requirements.join(", ")));
+        }
+
+        // Determine color scheme.
+
         let color_scheme = if let Some(color_scheme_index) = beatmap_info.get_color_scheme_index_opt() && let Some(color_scheme) = song_info.get_color_scheme(color_scheme_index) {
             color_scheme
         } else {
@@ -140,19 +525,49 @@ impl Game {
         let color_l = color_scheme.get_color_l();
         let color_r = color_scheme.get_color_r();
 
-        // Calculate zone info.
-        
-        let notejump_speed = beatmap_info.get_notejump_speed();
+        // Determine rules of the characteristic (90Degree and 360Degree are handled by the rotation events).
+
+        let characteristic = beatmap_info.get_characteristic();
+        let one_saber = characteristic == SongCharacteristic::OneSaber;
+        let no_arrows = characteristic == SongCharacteristic::NoArrows;
+
+        // Load beatmap and timing.
+
+        let bpm_info = song_info.get_bpm_info().map_err(|e| format!("Unable to load bpm info: {}", e))?;
+        let beatmap = beatmap_info.load().map_err(|e| format!("Unable to load beatmap: {}", e))?;
+
+        let timing = Timing::new(&bpm_info, beatmap.get_bpm_changes()).with_offset(song_info.get_metadata().get_song_time_offset());
+        let calc_ts = |bpm_pos| timing.get_ts(bpm_pos);
+
+        // Calculate zone info: zones 2 and 3 are covering the half jump distance, so the cubes are
+        // spawned (at the end of zone 1) half jump duration before they reach the player.
+
+        let spb = timing.get_spb(0.0).ok_or("Unable to determine BPM at the start")?;
+
+        // Replays are played with their recorded jump distance (if any), see BeatmapInfo::calc_jump_info.
+
+        let reaction_time_opt = match replay_info_opt {
+            Some(replay_info) => {
+                let jump_dist = replay_info.jump_distance;
+                let notejump_speed = beatmap_info.calc_jump_info(spb, None).get_notejump_speed();
+
+                (jump_dist > 0.0 && notejump_speed > 0.0).then(|| jump_dist / (2.0 * notejump_speed))
+            },
+            None => settings.get_inner().reaction_time_opt,
+        };
+
+        let jump_info = beatmap_info.calc_jump_info(spb, reaction_time_opt);
+        let notejump_speed = jump_info.get_notejump_speed();
 
         let in1_dist = ZONE_IN1_DIST;
         let in1_v = ZONE_IN1_V;
         let in1_t = in1_dist / in1_v;
 
-        let in2_dist = ZONE_IN2_DIST;
+        let in2_dist = jump_info.get_half_jump_dist() / 2.0;
         let in2_v = notejump_speed;
         let in2_t = in2_dist / in2_v;
 
-        let in3_dist = ZONE_IN3_DIST;
+        let in3_dist = jump_info.get_half_jump_dist() / 2.0;
         let in3_v = notejump_speed;
         let in3_t = in3_dist / in3_v;
 
@@ -177,30 +592,42 @@ impl Game {
 
         // Setup cubes.
 
-        let bpm_info = song_info.get_bpm_info().map_err(|e| format!("Unable to load bpm info: {:?}", e))?; // TODO: instead of debug, use display trait for formatting error msg?
-
         let body_phong_param = PhongParam::new(0.1, 0.3, 0.6, 16.0);
         let symbol_phong_param = PhongParam::new(0.5, 0.3, 0.6, 16.0);
 
-        let beatmap = beatmap_info.load().map_err(|e| format!("Unable to load beatmap: {:?}", e))?; // TODO: instead of debug, use display trait for formatting error msg?
-        let cube_infos = Box::from_iter(beatmap.get_notes().iter().filter_map(|note| {
-            let bpm_pos = note.get_bpm_pos();
+        // Chain heads are cubes as well, but cutting them breaks the links of the chain.
 
-            let ts_opt = match &bpm_info {
-                BPMInfo::Fixed(bpm) => {
-                    Some(60.0 / bpm * bpm_pos)
-                },
-                BPMInfo::Mapped(bpm_map) => {
-                    bpm_map.get_ts(bpm_pos)
-                },
-            };
+        let chain_infos = Vec::from_iter(beatmap.get_chains().iter().map(|_| {
+            Rc::new(ChainInfo {
+                head_cut: Cell::new(false),
+            })
+        }));
+
+        // Notes at the head or tail of an arc are recorded with their own scoring type in the replays.
+
+        let calc_arc_points = |get_point: fn(&SongArc) -> &ArcPoint| {
+            let mut arc_points = Vec::from_iter(beatmap.get_arcs().iter().map(|arc| {
+                let arc_point = get_point(arc);
+                (arc_point.get_bpm_pos(), arc_point.get_x(), arc_point.get_y(), arc.get_note_type())
+            }));
+
+            arc_points.sort_by(|a, b| a.0.total_cmp(&b.0));
+            arc_points
+        };
+
+        let arc_heads = calc_arc_points(SongArc::get_head);
+        let arc_tails = calc_arc_points(SongArc::get_tail);
 
-            if let Some(ts) = ts_opt {
+        let notes = beatmap.get_notes().iter().map(|note| (note, None));
+        let chain_heads = beatmap.get_chains().iter().zip(&chain_infos).map(|(chain, chain_info)| (chain.get_head(), Some(Rc::clone(chain_info))));
+
+        let mut cube_infos = Vec::from_iter(notes.chain(chain_heads).filter_map(|(note, chain_info_opt): (&Note, Option<Rc<ChainInfo>>)| {
+            if let Some(ts) = calc_ts(note.get_bpm_pos()) {
                 let note_type = note.get_note_type();
                 let mut any = false;
                 let mut symbol = CubeSymbol::Arrow;
 
-                let angle = match note.get_cut_dir() {
+                let mut angle = match note.get_cut_dir() {
                     NoteCutDir::Up => match note_type {
                         NoteType::Left => -180.0,
                         NoteType::Right => 180.0,
@@ -219,9 +646,20 @@ impl Game {
                     }
                 };
 
-                let color = match note_type {
-                    NoteType::Left => color_l,
-                    NoteType::Right => color_r,
+                // Without arrows, the cubes can be cut in any direction.
+
+                if no_arrows {
+                    any = true;
+                    symbol = CubeSymbol::Dot;
+                    angle = 0.0;
+                }
+
+                let custom_data_opt = note.get_custom_data().filter(|_| custom_data);
+
+                let color = match (custom_data_opt.and_then(NoteCustomData::get_color), note_type) {
+                    (Some(color), _) => color,
+                    (None, NoteType::Left) => color_l,
+                    (None, NoteType::Right) => color_r,
                 };
 
                 let cube_param = CubeParam::new(symbol, color, &body_phong_param, &COLOR_WHITE, &symbol_phong_param);
@@ -232,21 +670,35 @@ impl Game {
                 // - Its bounding box is unit (1m) sized and the object center is at the origin.
                 // - It is scaled to CUBE_SIZE.
 
-                let x_val = note.get_x() as f32;
-                let (x_index, right) = if x_val >= 2.0 { (x_val - 2.0, 1.0) } else { (1.0 - x_val, -1.0) };
-                let x = right * (CUBE_SPACING / 2.0 + x_index * (CUBE_SIZE + CUBE_SPACING) + CUBE_SIZE / 2.0);
+                let (x_val, y_val) = custom_data_opt.and_then(NoteCustomData::get_pos).unwrap_or((note.get_x() as f32, note.get_y() as f32));
+                let (x, z) = calc_grid_pos(x_val, y_val);
+                let lane_rot = calc_lane_rot(beatmap.get_rotation(note.get_bpm_pos()));
+                let spawn_effect = !custom_data_opt.is_some_and(NoteCustomData::is_spawn_effect_disabled);
+
+                let kind = if chain_info_opt.is_some() {
+                    ReplayNoteKind::ChainHead
+                } else if is_arc_point(&arc_heads, note) {
+                    ReplayNoteKind::ArcHead
+                } else if is_arc_point(&arc_tails, note) {
+                    ReplayNoteKind::ArcTail
+                } else {
+                    ReplayNoteKind::Normal
+                };
 
-                let y_val = note.get_y() as f32;
-                let z = y_val * (CUBE_SIZE + CUBE_SPACING) + CUBE_SIZE / 2.0;
+                let note_id = bsor::calc_note_id(kind, note.get_x().into(), note.get_y().into(), note_type as i32, note.get_cut_dir() as i32);
 
                 let cube_info = Rc::new(CubeInfo {
                     ts,
+                    note_id,
                     x,
                     z,
                     note_type,
                     angle,
+                    lane_rot,
                     any,
+                    spawn_effect,
                     cube,
+                    chain_info_opt,
                 });
 
                 Some(cube_info)
@@ -255,28 +707,232 @@ impl Game {
             }
         }));
 
+        cube_infos.sort_by(|cube_info1, cube_info2| cube_info1.ts.total_cmp(&cube_info2.ts));
+        let cube_infos = cube_infos.into_boxed_slice();
+
+        // Setup chain links, they are small cubes with a dot.
+
+        let mut chain_link_infos = Vec::new();
+
+        for (chain, chain_info) in beatmap.get_chains().iter().zip(&chain_infos) {
+            let color = match chain.get_head().get_note_type() {
+                NoteType::Left => color_l,
+                NoteType::Right => color_r,
+            };
+
+            for link in chain.get_links() {
+                if let Some(ts) = calc_ts(link.get_bpm_pos()) {
+                    let cube_param = CubeParam::new(CubeSymbol::Dot, color, &body_phong_param, &COLOR_WHITE, &symbol_phong_param);
+                    let cube = model_reg.create(cube_param);
+                    cube.set_scale(CUBE_SIZE * CHAIN_LINK_SCALE);
+
+                    // The link is rotated the same way as a cube with the cut direction of the curve.
+
+                    let (dir_x, dir_y) = link.get_dir();
+                    let angle = Deg::atan2(-dir_x, -dir_y).0;
+
+                    let (x, z) = calc_grid_pos(link.get_x(), link.get_y());
+                    let lane_rot = calc_lane_rot(beatmap.get_rotation(link.get_bpm_pos()));
+                    let note_id = bsor::calc_note_id(ReplayNoteKind::ChainLink, link.get_x().round() as i32, link.get_y().round() as i32, chain.get_head().get_note_type() as i32, 0);
+
+                    let chain_link_info = Rc::new(ChainLinkInfo {
+                        ts,
+                        note_id,
+                        x,
+                        z,
+                        note_type: chain.get_head().get_note_type(),
+                        angle,
+                        lane_rot,
+                        cube,
+                        chain_info: Rc::clone(chain_info),
+                    });
+
+                    chain_link_infos.push(chain_link_info);
+                }
+            }
+        }
+
+        chain_link_infos.sort_by(|link_info1, link_info2| link_info1.ts.total_cmp(&link_info2.ts));
+        let chain_link_infos = chain_link_infos.into_boxed_slice();
+
+        // Setup bombs.
+
+        let bomb_phong_param = PhongParam::new(0.2, 0.3, 0.8, 32.0);
+
+        let bomb_infos = Box::from_iter(beatmap.get_bombs().iter().filter_map(|bomb| {
+            if let Some(ts) = calc_ts(bomb.get_bpm_pos()) {
+                let bomb_param = BombParam::new(&COLOR_BOMB, &bomb_phong_param);
+                let bomb_model = model_reg.create(bomb_param);
+                bomb_model.set_scale(CUBE_SIZE);
+
+                // Bombs are laid out on the same grid as the cubes.
+
+                let (x, z) = calc_grid_pos(bomb.get_x() as f32, bomb.get_y() as f32);
+                let lane_rot = calc_lane_rot(beatmap.get_rotation(bomb.get_bpm_pos()));
+                let note_id = bsor::calc_note_id(ReplayNoteKind::Bomb, bomb.get_x().into(), bomb.get_y().into(), 0, 0);
+
+                let bomb_info = Rc::new(BombInfo {
+                    ts,
+                    note_id,
+                    x,
+                    z,
+                    lane_rot,
+                    bomb: bomb_model,
+                });
+
+                Some(bomb_info)
+            } else {
+                None
+            }
+        }));
+
+        // Setup arcs.
+
+        let calc_arc_end = |arc_point: &ArcPoint| {
+            calc_ts(arc_point.get_bpm_pos()).map(|ts| {
+                let (x, z) = calc_grid_pos(arc_point.get_x() as f32, arc_point.get_y() as f32);
+
+                ArcEndInfo {
+                    ts,
+                    x,
+                    z,
+                    dir: calc_cut_dir_vec(arc_point.get_cut_dir()),
+                    multiplier: arc_point.get_multiplier(),
+                }
+            })
+        };
+
+        let arc_infos = Box::from_iter(beatmap.get_arcs().iter().filter_map(|arc| {
+            if let Some(head) = calc_arc_end(arc.get_head()) && let Some(tail) = calc_arc_end(arc.get_tail()) {
+                let color = match arc.get_note_type() {
+                    NoteType::Left => color_l,
+                    NoteType::Right => color_r,
+                };
+
+                let ribbon = model_reg.create(RibbonParam::new(color, ARC_ALPHA, ARC_WIDTH));
+
+                let mid_anchor = match arc.get_mid_anchor() {
+                    ArcMidAnchor::Straight => 0.0,
+                    ArcMidAnchor::Clockwise => 1.0,
+                    ArcMidAnchor::CounterClockwise => -1.0,
+                };
+
+                let lane_rot = calc_lane_rot(beatmap.get_rotation(arc.get_head().get_bpm_pos()));
+
+                let arc_info = Rc::new(ArcInfo {
+                    head,
+                    tail,
+                    mid_anchor,
+                    lane_rot,
+                    ribbon,
+                });
+
+                Some(arc_info)
+            } else {
+                None
+            }
+        }));
+
+        // Setup obstacles.
+
+        let wall_color = Color::from_srgb_float(1.0, 0.1882353, 0.1882353); // See https://bsmg.wiki/mapping/lighting-defaults.html#_1-19-0-colors .
+
+        let obstacle_infos = Box::from_iter(beatmap.get_obstacles().iter().filter_map(|obstacle| {
+            let bpm_pos = obstacle.get_bpm_pos();
+
+            if let Some(ts) = calc_ts(bpm_pos) && let Some(ts_end) = calc_ts(bpm_pos + obstacle.get_duration()) {
+                let wall = model_reg.create(WallParam::new(&wall_color, WALL_ALPHA));
+
+                // Obstacles are laid out on the same grid as the cubes, but they
+                // are standing on the floor (without CUBE_FLOOR).
+
+                let cell_size = CUBE_SIZE + CUBE_SPACING;
+
+                let x_min = (obstacle.get_x() as f32 - 2.0) * cell_size;
+                let x_max = x_min + obstacle.get_width() as f32 * cell_size;
+
+                let y_val = obstacle.get_y();
+                let z_min = if y_val <= 0 { 0.0 } else { y_val as f32 * cell_size - CUBE_SPACING / 2.0 };
+                let z_max = ((y_val + obstacle.get_height()) as f32 * cell_size - CUBE_SPACING / 2.0).max(z_min);
+
+                let lane_rot = calc_lane_rot(beatmap.get_rotation(bpm_pos));
+                wall.set_rot(&lane_rot);
+
+                let obstacle_info = Rc::new(ObstacleInfo {
+                    ts,
+                    wall_id: bsor::calc_wall_id(obstacle.get_x(), y_val, obstacle.get_width()),
+                    ts_end: ts_end.max(ts),
+                    x_min,
+                    x_max,
+                    z_min,
+                    z_max,
+                    lane_rot,
+                    wall,
+                });
+
+                Some(obstacle_info)
+            } else {
+                None
+            }
+        }));
+
+        // Setup lane rotations, the floor and the HUD windows are following the lane faced by the player.
+
+        let mut lane_rotation = 0.0;
+
+        let lane_rotation_infos = Box::from_iter(beatmap.get_rotation_events().iter().filter_map(|rotation_event| {
+            lane_rotation += rotation_event.get_rotation();
+
+            calc_ts(rotation_event.get_bpm_pos()).map(|ts| {
+                LaneRotationInfo {
+                    ts,
+                    rotation: lane_rotation,
+                }
+            })
+        }));
+
         // Setup stat window.
 
-        let window_param = WindowParam::new(500, 250, || {
+        let window_param = WindowParam::new(500, 400, || {
             GameStatsWindow::new().unwrap()
         });
 
         let game_stats_window = model_reg.create(window_param);
         game_stats_window.set_visible(true);
-        game_stats_window.set_scale(2.0, 1.0);
-        game_stats_window.set_pos(&Vector3::new(-3.0, 6.0, 3.0));
-        game_stats_window.set_rot(&Quaternion::from_angle_z(Deg(20.0)));
+        game_stats_window.set_scale(2.0, 1.6);
+        place_game_stats_window(&game_stats_window, &calc_lane_rot(0.0));
 
         let game_stats_window_weak = game_stats_window.as_weak();
 
+        // Setup feedback window, it is displayed below the notes.
+
+        let window_param = WindowParam::new(500, 100, || {
+            FeedbackWindow::new().unwrap()
+        });
+
+        let feedback_window = model_reg.create(window_param);
+        feedback_window.set_scale(2.0, 0.4);
+        place_feedback_window(&feedback_window, &calc_lane_rot(0.0));
+
+        let feedback_window_weak = feedback_window.as_weak();
+
         // Setup floor.
 
-        create_floor(model_reg);
-        create_stats_window(model_reg, stats, ui_loop);
+        let floor = create_floor(model_reg);
+        let stats_window = create_stats_window(model_reg, stats, ui_loop);
+
+        // Setup environment, its lights are controlled by the lighting events.
+
+        let environment = Environment::new(model_reg, color_scheme, beatmap.get_light_events(), calc_ts);
 
         // Setup sabers.
 
         let (saber_l, saber_r) = create_saber(model_reg, color_l, color_r);
+        let pointer = create_pointer(model_reg); // For the pause and replay windows
+
+        // Setup vignette, which is displayed while the head is inside an obstacle.
+
+        let vignette = model_reg.create(VignetteParam::new(&wall_color, VIGNETTE_ALPHA));
 
         // Setup audio.
 
@@ -288,62 +944,356 @@ impl Game {
             test = param.test;
         }
 
-        let audio_info_opt = if !test {
-            let asset_file = param.asset_mgr.open(song_info.get_song_filename()).map_err(|e| format!("Unable to open audio file: {:?}", e))?; // TODO: instead of debug, use display trait for formatting error msg?
-
-            let (input, handle) = AudioFile::new(asset_file);
-            let ts = audio_engine.add(input);
+        let (audio_start, speed) = param.practice_opt.map_or((0.0, 1.0), |practice| (practice.start, practice.speed));
 
-            let audio_info = AudioInfo {
-                handle,
-                ts,
-            };
-
-            Some(audio_info)
+        let audio_info_opt = if !test {
+            Some(create_audio_info(&param.asset_mgr, song_info.get_song_filename(), &audio_engine, audio_start, speed)?)
         } else {
             None
         };
 
-        let inner = Inner {
+        // Replays are played with their recorded modifiers.
+
+        let no_fail = replay_info_opt.map_or(settings.get_inner().no_fail, |replay_info| replay_info.modifiers.split(',').any(|modifier| modifier == "NF"));
+
+        // The maximum score depends on the order of the notes, because of the multiplier.
+
+        let note_scores = cube_infos.iter().map(|cube_info| {
+            (cube_info.ts, if cube_info.chain_info_opt.is_some() { SCORE_CHAIN_HEAD } else { SCORE_NOTE })
+        }).chain(chain_link_infos.iter().map(|chain_link_info| (chain_link_info.ts, SCORE_CHAIN_LINK))).collect();
+
+        let max_score = score::calc_max_score(note_scores);
+
+        let inner = PlayfieldInner {
+            audio_info_opt,
+            audio_start,
+            seek_ts_opt: Some(audio_start),
             start: true,
             #[cfg(feature = "test")]
             start_time: Instant::now(),
             alive_objs: Vec::new(),
             cube_range_end: 0,
+            bomb_range_end: 0,
+            obstacle_range_end: 0,
+            arc_range_end: 0,
+            chain_link_range_end: 0,
             prev_audio_ts: 0.0, // TODO: is this correct to default it to 0?
-            prev_click: true,
-            game_stats: GameStats::new(cube_infos.len().try_into().unwrap()),
+            lane_rotation: 0.0,
+            feedback_end_ts: 0.0,
+            game_stats: GameStats::new((cube_infos.len() + chain_link_infos.len()).try_into().unwrap(), max_score, no_fail),
+            play_state: PlayState::Playing,
         };
-        
+
         Ok(Self {
+            asset_mgr: Arc::clone(&param.asset_mgr),
+            song_filename: song_info.get_song_filename().to_string(),
+            speed,
+            audio_engine,
             ui_loop: ui_loop.clone(),
+            jump_dist: jump_info.get_jump_dist(),
             zone_info,
             cube_infos,
+            bomb_infos,
+            obstacle_infos,
+            arc_infos,
+            chain_link_infos,
+            lane_rotation_infos,
+            game_stats_window,
             game_stats_window_weak,
+            feedback_window,
+            feedback_window_weak,
+            stats_window,
+            floor,
             saber_l,
             saber_r,
-            audio_info_opt,
+            pointer,
+            saber_history_l: Rc::new(SaberHistory::new()),
+            saber_history_r: Rc::new(SaberHistory::new()),
+            one_saber,
+            vignette,
+            environment,
             inner: RefCell::new(inner),
         })
     }
 
-    fn update_objs(&self, inner: &mut Inner, audio_ts: f32, scene_input: &SceneInput) {
-        let alive_objs = &mut inner.alive_objs;
+    // Position of the song at this update, the audio is started at the first one.
+    pub fn get_song_pos(&self) -> SongPos {
+        let inner = &mut *self.inner.borrow_mut();
 
-        // Show incoming cubes.
+        if let Some(audio_info) = &inner.audio_info_opt {
+            if inner.start {
+                audio_info.handle.play();
+                inner.start = false;
+            }
 
-        let zone_info = &self.zone_info;
+            // If the song ends during the fail animation, then it is still failed.
+
+            if audio_info.handle.at_eof() {
+                if let PlayState::Failing(_) = inner.play_state {
+                    inner.play_state = PlayState::Failed;
+                }
+
+                return SongPos::End;
+            }
+
+            // The audio file is played from audio_start at the speed of practice mode.
+
+            if let Some(ts) = audio_info.ts.get_timestamp() {
+                return SongPos::At(inner.audio_start + self.speed * ts as f32); // TODO: or use 64 bit ts?
+            }
+        } else {
+            #[cfg(feature = "test")]
+            {
+                if inner.start {
+                    inner.start_time = Instant::now();
+                    inner.start = false;
+                }
+
+                if inner.alive_objs.is_empty() && inner.cube_range_end == self.cube_infos.len() {
+                    return SongPos::End;
+                }
+
+                return SongPos::At(inner.start_time.elapsed().as_secs_f32());
+            }
+        }
+
+        SongPos::Unknown
+    }
+
+    pub fn update(&self, audio_ts: f32, scene_input: &SceneInput) {
+        let inner = &mut *self.inner.borrow_mut();
+
+        self.update_play_state(inner, audio_ts, &self.filter_input(scene_input));
+    }
+
+    pub fn update_sabers(&self, scene_input_opt: Option<&SceneInput>) {
+        // Both are hidden, unless the song is played.
+
+        if let Some(scene_input) = scene_input_opt && matches!(self.inner.borrow().play_state, PlayState::Playing) {
+            let scene_input = self.filter_input(scene_input);
+
+            Self::update_saber(&self.saber_l, &scene_input.pose_l_opt);
+            Self::update_saber(&self.saber_r, &scene_input.pose_r_opt);
+        } else {
+            Self::update_saber(&self.saber_l, &None);
+            Self::update_saber(&self.saber_r, &None);
+        }
+    }
+
+    pub fn update_ui(&self, scene_mgr: &SceneManager, windows: &[&Rc<Window>], scene_input: &SceneInput) {
+        scene_mgr.get_ui_subr().update(&self.saber_l, &self.saber_r, &self.pointer, windows, scene_input);
+    }
+
+    pub fn rewind(&self, ts: f32) -> Result<(), String> {
+        // The audio file is replaced by a new one, which starts from ts. If it can't be opened, then the song is left as it is.
+
+        let inner = &mut *self.inner.borrow_mut();
+
+        let audio_info = create_audio_info(&self.asset_mgr, &self.song_filename, &self.audio_engine, ts, self.speed)?;
+        audio_info.handle.play();
+
+        inner.audio_info_opt = Some(audio_info);
+        inner.audio_start = ts;
+        inner.seek_ts_opt = Some(ts);
+
+        Ok(())
+    }
+
+    pub fn seek(&self, ts: f32) -> Result<(), String> {
+        // The song is played again from ts (even if it has been failed), the stats are counted from there.
+
+        self.rewind(ts)?;
+
+        self.feedback_window.set_visible(false);
+        self.vignette.set_visible(false);
+
+        let inner = &mut *self.inner.borrow_mut();
+
+        inner.game_stats.reset();
+        inner.play_state = PlayState::Playing;
+
+        Ok(())
+    }
+
+    pub fn set_audio_playing(&self, playing: bool) {
+        let inner = &mut *self.inner.borrow_mut();
+
+        if let Some(audio_info) = &inner.audio_info_opt {
+            if playing {
+                audio_info.handle.play();
+            } else {
+                audio_info.handle.pause();
+            }
+        } else {
+            #[cfg(feature = "test")]
+            if playing {
+                inner.start_time = Instant::now() - Duration::from_secs_f32(inner.prev_audio_ts);
+            }
+        }
+    }
+
+    pub fn get_stats(&self) -> GameStatsInner {
+        self.inner.borrow().game_stats.get_inner()
+    }
+
+    pub fn take_replay_events(&self) -> (Vec<ReplayNote>, Vec<ReplayWall>) {
+        self.inner.borrow_mut().game_stats.take_replay_events()
+    }
+
+    fn filter_input<'a>(&self, scene_input: &SceneInput<'a>) -> SceneInput<'a> {
+        // The left saber is not used in case of OneSaber.
+
+        SceneInput {
+            pose_l_opt: scene_input.pose_l_opt.filter(|_| !self.one_saber),
+            pose_r_opt: scene_input.pose_r_opt,
+            cam_pos: scene_input.cam_pos,
+            cam_rot: scene_input.cam_rot,
+        }
+    }
+
+    fn get_play_state(&self) -> PlayState {
+        self.inner.borrow().play_state
+    }
+
+    fn set_play_state(&self, play_state: PlayState) {
+        self.inner.borrow_mut().play_state = play_state;
+    }
+
+    fn get_prev_audio_ts(&self) -> f32 {
+        self.inner.borrow().prev_audio_ts
+    }
+
+    fn get_lane_rotation(&self) -> f32 {
+        self.inner.borrow().lane_rotation
+    }
+
+    fn clear_saber_histories(&self) {
+        self.saber_history_l.clear();
+        self.saber_history_r.clear();
+    }
+
+    fn seek_objs(&self, inner: &mut PlayfieldInner, ts: f32) {
+        // Hide the alive objects: all models are hidden, since there is no reference to them from the objects.
+
+        inner.alive_objs.clear();
+
+        for cube_info in &self.cube_infos {
+            cube_info.cube.set_visible(false);
+
+            if let Some(chain_info) = &cube_info.chain_info_opt {
+                chain_info.head_cut.set(false);
+            }
+        }
+
+        for bomb_info in &self.bomb_infos {
+            bomb_info.bomb.set_visible(false);
+        }
+
+        for obstacle_info in &self.obstacle_infos {
+            obstacle_info.wall.set_visible(false);
+        }
+
+        for arc_info in &self.arc_infos {
+            arc_info.ribbon.set_visible(false);
+        }
+
+        for chain_link_info in &self.chain_link_infos {
+            chain_link_info.cube.set_visible(false);
+        }
+
+        // The objects are spawned from ts (obstacles and arcs are skipped, if they are started before).
+
+        inner.cube_range_end = self.cube_infos.partition_point(|cube_info| cube_info.ts < ts);
+        inner.bomb_range_end = self.bomb_infos.partition_point(|bomb_info| bomb_info.ts < ts);
+        inner.obstacle_range_end = self.obstacle_infos.partition_point(|obstacle_info| obstacle_info.ts < ts);
+        inner.arc_range_end = self.arc_infos.partition_point(|arc_info| arc_info.head.ts < ts);
+        inner.chain_link_range_end = self.chain_link_infos.partition_point(|chain_link_info| chain_link_info.ts < ts);
+
+        inner.prev_audio_ts = ts;
+        inner.feedback_end_ts = 0.0;
+
+        self.saber_history_l.clear();
+        self.saber_history_r.clear();
+    }
+
+    fn update_play_state(&self, inner: &mut PlayfieldInner, audio_ts: f32, scene_input: &SceneInput) {
+        match inner.play_state {
+            PlayState::Playing => {
+                self.update_objs(inner, audio_ts, scene_input);
+
+                if inner.game_stats.is_failed() {
+                    self.fail(inner, audio_ts);
+                }
+            },
+            PlayState::Failing(fail_ts) => {
+                // The objects are frozen, while the song fades out.
+
+                if audio_ts - fail_ts >= FAIL_T {
+                    if let Some(audio_info) = &inner.audio_info_opt {
+                        audio_info.handle.pause();
+                    }
+
+                    inner.play_state = PlayState::Failed;
+                }
+            },
+            PlayState::Paused(_) | PlayState::Failed => (),
+        }
+    }
+
+    fn fail(&self, inner: &mut PlayfieldInner, audio_ts: f32) {
+        // Start the fail animation, the game stats window keeps the results.
+
+        if let Some(audio_info) = &inner.audio_info_opt {
+            audio_info.fader_handle.fade_out(FAIL_FADE_RATE);
+        }
+
+        self.ui_loop.add_callback({
+            let window_weak = self.feedback_window_weak.clone();
+
+            move || {
+                window_weak.unwrap().set_kind(FeedbackWindowKind::Failed);
+            }
+        });
+
+        self.feedback_window.set_visible(true);
+        self.vignette.set_visible(true);
+
+        inner.play_state = PlayState::Failing(audio_ts);
+    }
+
+    fn update_objs(&self, inner: &mut PlayfieldInner, audio_ts: f32, scene_input: &SceneInput) {
+        // Skip the objects before the seeked position (see practice mode).
+
+        if let Some(seek_ts) = inner.seek_ts_opt.take() {
+            self.seek_objs(inner, seek_ts);
+        }
+
+        let has_audio = inner.audio_info_opt.is_some();
+        let alive_objs = &mut inner.alive_objs;
+
+        // Show incoming cubes, bombs, obstacles, arcs and chain links.
+
+        let zone_info = &self.zone_info;
         let cube_infos = &self.cube_infos;
         let cube_range_end = &mut inner.cube_range_end;
-
-        if self.audio_info_opt.is_some() {
+        let bomb_infos = &self.bomb_infos;
+        let bomb_range_end = &mut inner.bomb_range_end;
+        let obstacle_infos = &self.obstacle_infos;
+        let obstacle_range_end = &mut inner.obstacle_range_end;
+        let arc_infos = &self.arc_infos;
+        let arc_range_end = &mut inner.arc_range_end;
+        let chain_link_infos = &self.chain_link_infos;
+        let chain_link_range_end = &mut inner.chain_link_range_end;
+
+        if has_audio {
             let ts_in = audio_ts + zone_info.in123_t;
 
             for i in *cube_range_end..cube_infos.len() {
                 let cube_info = &cube_infos[i];
 
                 if cube_info.ts <= ts_in {
-                    let obj = CubeObj::new(Rc::clone(zone_info), Rc::clone(cube_info), #[cfg(feature = "test")] false);
+                    let (saber_history, other_saber_history) = self.get_saber_histories(cube_info.note_type);
+                    let obj = CubeObj::new(Rc::clone(zone_info), Rc::clone(cube_info), Rc::clone(saber_history), Rc::clone(other_saber_history), #[cfg(feature = "test")] false);
                     alive_objs.push(Box::new(obj));
 
                     *cube_range_end = i + 1;
@@ -351,13 +1301,66 @@ impl Game {
                     break;
                 }
             }
+
+            for i in *bomb_range_end..bomb_infos.len() {
+                let bomb_info = &bomb_infos[i];
+
+                if bomb_info.ts <= ts_in {
+                    let obj = BombObj::new(Rc::clone(zone_info), Rc::clone(bomb_info));
+                    alive_objs.push(Box::new(obj));
+
+                    *bomb_range_end = i + 1;
+                } else {
+                    break;
+                }
+            }
+
+            for i in *obstacle_range_end..obstacle_infos.len() {
+                let obstacle_info = &obstacle_infos[i];
+
+                if obstacle_info.ts <= ts_in {
+                    let obj = ObstacleObj::new(Rc::clone(zone_info), Rc::clone(obstacle_info));
+                    alive_objs.push(Box::new(obj));
+
+                    *obstacle_range_end = i + 1;
+                } else {
+                    break;
+                }
+            }
+
+            for i in *arc_range_end..arc_infos.len() {
+                let arc_info = &arc_infos[i];
+
+                if arc_info.head.ts <= ts_in {
+                    let obj = ArcObj::new(Rc::clone(zone_info), Rc::clone(arc_info));
+                    alive_objs.push(Box::new(obj));
+
+                    *arc_range_end = i + 1;
+                } else {
+                    break;
+                }
+            }
+
+            for i in *chain_link_range_end..chain_link_infos.len() {
+                let chain_link_info = &chain_link_infos[i];
+
+                if chain_link_info.ts <= ts_in {
+                    let obj = ChainLinkObj::new(Rc::clone(zone_info), Rc::clone(chain_link_info));
+                    alive_objs.push(Box::new(obj));
+
+                    *chain_link_range_end = i + 1;
+                } else {
+                    break;
+                }
+            }
         } else {
             #[cfg(feature = "test")]
             {
                 if alive_objs.is_empty() {
                     let cube_info = &cube_infos[*cube_range_end];
 
-                    let obj = CubeObj::new(Rc::clone(zone_info), Rc::clone(cube_info), true);
+                    let (saber_history, other_saber_history) = self.get_saber_histories(cube_info.note_type);
+                    let obj = CubeObj::new(Rc::clone(zone_info), Rc::clone(cube_info), Rc::clone(saber_history), Rc::clone(other_saber_history), true);
                     alive_objs.push(Box::new(obj));
 
                     *cube_range_end += 1;
@@ -365,6 +1368,11 @@ impl Game {
             }
         }
 
+        // Record the sabers for rating the swings.
+
+        self.saber_history_l.record(audio_ts, scene_input.pose_l_opt);
+        self.saber_history_r.record(audio_ts, scene_input.pose_r_opt);
+
         // Update objects.
 
         let prev_audio_ts = &mut inner.prev_audio_ts;
@@ -390,6 +1398,49 @@ impl Game {
             }
         }
 
+        // Follow the lane faced by the player.
+
+        self.update_lane(&mut inner.lane_rotation, audio_ts, ts_diff);
+
+        // Update lights of the environment.
+
+        self.environment.update(audio_ts);
+
+        // Display vignette, if the head is inside an obstacle, that drains the energy.
+
+        let in_wall = game_stats.take_in_wall();
+
+        if in_wall {
+            game_stats.drain_wall(ts_diff);
+        }
+
+        self.vignette.set_visible(in_wall);
+        self.vignette.set_pos(&scene_input.cam_pos);
+
+        // Display feedback of the last failed note for a while.
+
+        if let Some(outcome) = game_stats.take_feedback_opt() {
+            let kind = match outcome {
+                CubeOutcome::BadCut(BadCutReason::WrongColor) => FeedbackWindowKind::WrongColor,
+                CubeOutcome::BadCut(BadCutReason::WrongDir) => FeedbackWindowKind::WrongDirection,
+                CubeOutcome::BadCut(BadCutReason::TooSlow) => FeedbackWindowKind::TooSlow,
+                CubeOutcome::Miss => FeedbackWindowKind::Miss,
+                CubeOutcome::Cut => unreachable!("Cut has no feedback"),
+            };
+
+            self.ui_loop.add_callback({
+                let window_weak = self.feedback_window_weak.clone();
+
+                move || {
+                    window_weak.unwrap().set_kind(kind);
+                }
+            });
+
+            inner.feedback_end_ts = audio_ts + FEEDBACK_T;
+        }
+
+        self.feedback_window.set_visible(audio_ts < inner.feedback_end_ts);
+
         // Display game stats, if changed.
 
         if game_stats.is_changed() {
@@ -400,8 +1451,22 @@ impl Game {
                 move || { // TODO: use slint struct?
                     let window = window_weak.unwrap();
 
-                    window.set_count(stats_inner.count.try_into().unwrap());
-                    window.set_total(stats_inner.total.try_into().unwrap());
+                    window.set_count(stats_inner.get_count().try_into().unwrap());
+                    window.set_total(stats_inner.get_total().try_into().unwrap());
+                    window.set_bomb_hits(stats_inner.get_bomb_hits().try_into().unwrap());
+                    window.set_wall_hits(stats_inner.get_wall_hits().try_into().unwrap());
+                    window.set_misses(stats_inner.get_misses().try_into().unwrap());
+                    window.set_bad_cuts_wrong_color(stats_inner.get_bad_cuts_wrong_color().try_into().unwrap());
+                    window.set_bad_cuts_wrong_direction(stats_inner.get_bad_cuts_wrong_dir().try_into().unwrap());
+                    window.set_bad_cuts_too_slow(stats_inner.get_bad_cuts_too_slow().try_into().unwrap());
+                    window.set_score(stats_inner.get_score().try_into().unwrap());
+                    window.set_max_score(stats_inner.get_max_score().try_into().unwrap());
+                    window.set_percentage(format!("{:.1}%", stats_inner.calc_percentage()).into());
+                    window.set_combo(stats_inner.get_combo().try_into().unwrap());
+                    window.set_max_combo(stats_inner.get_max_combo().try_into().unwrap());
+                    window.set_multiplier(stats_inner.get_multiplier().try_into().unwrap());
+                    window.set_energy(stats_inner.get_energy());
+                    window.set_no_fail_used(stats_inner.is_no_fail_used());
                 }
             });
         }
@@ -409,6 +1474,29 @@ impl Game {
         *prev_audio_ts = audio_ts;
     }
 
+    fn update_lane(&self, lane_rotation: &mut f32, audio_ts: f32, ts_diff: f32) {
+        let index = self.lane_rotation_infos.partition_point(|lane_rotation_info| lane_rotation_info.ts <= audio_ts); // First index, where lane_rotation_info.ts > audio_ts
+        let target_rotation = if index > 0 { self.lane_rotation_infos[index - 1].rotation } else { 0.0 };
+
+        *lane_rotation += (target_rotation - *lane_rotation) * (LANE_FOLLOW_SPEED * ts_diff).clamp(0.0, 1.0);
+
+        let lane_rot = calc_lane_rot(*lane_rotation);
+
+        self.floor.set_rot(&lane_rot);
+        place_stats_window(&self.stats_window, &lane_rot);
+        place_game_stats_window(&self.game_stats_window, &lane_rot);
+        place_feedback_window(&self.feedback_window, &lane_rot);
+    }
+
+    fn get_saber_histories(&self, note_type: NoteType) -> (&Rc<SaberHistory>, &Rc<SaberHistory>) {
+        // The one of the color and the other one.
+
+        match note_type {
+            NoteType::Left => (&self.saber_history_l, &self.saber_history_r),
+            NoteType::Right => (&self.saber_history_r, &self.saber_history_l),
+        }
+    }
+
     fn update_saber(saber: &Saber, pose_opt: &Option<&dyn ScenePose>) {
         if let Some(pose) = pose_opt && pose.get_render() {
             saber.set_visible(SaberVisibility::HandleRay);
@@ -420,51 +1508,228 @@ impl Game {
     }
 }
 
-impl Scene for Game {
-    fn update(&self, scene_mgr: &SceneManager, scene_input: &SceneInput) {
-        let inner = &mut *self.inner.borrow_mut();
-        let mut done = false;
+impl Game {
+    fn new(param: GameParam, model_reg: &mut ModelRegistry, stats: StatsRc, settings: SettingsRc, audio_engine: AudioEngineRc, ui_loop: &UILoop) -> Result<Self, String> {
+        let playfield = Playfield::new(&param, None, model_reg, stats, &settings, audio_engine, ui_loop)?;
 
-        if let Some(audio_info) = &self.audio_info_opt {
-            // Start audio on first update.
-            // TODO: implement lifecycle methods?
+        // Setup pause window, it is displayed in front of the player.
 
-            if inner.start {
-                audio_info.handle.play();
-                inner.start = false;
-            }
+        let (pause_window_tx, pause_window_rx) = mailbox::mailbox();
 
-            // Update cubes.
+        let window_param = WindowParam::new(500, 300, move || {
+            let window = PauseWindow::new().unwrap();
 
-            if audio_info.handle.at_eof() {
-                done = true;
-            } else if let Some(ts) = audio_info.ts.get_timestamp() {
-                self.update_objs(inner, ts as f32, scene_input); // TODO: or use 64 bit ts?
-            }
-        } else {
-            #[cfg(feature = "test")]
-            {
-                if inner.start {
-                    inner.start_time = Instant::now();
-                    inner.start = false;
+            window.on_resume_game({
+                let pause_window_tx = pause_window_tx.clone();
+
+                move || {
+                    pause_window_tx.send(PauseMessage::Resume).unwrap();
                 }
+            });
 
-                let ts = inner.start_time.elapsed().as_secs_f32();
+            window.on_restart_game({
+                let pause_window_tx = pause_window_tx.clone();
 
-                if !(inner.alive_objs.is_empty() && inner.cube_range_end == self.cube_infos.len()) {
-                    self.update_objs(inner, ts, scene_input);
-                } else {
-                    done = true;
+                move || {
+                    pause_window_tx.send(PauseMessage::Restart).unwrap();
                 }
+            });
+
+            window.on_quit_game(move || {
+                pause_window_tx.send(PauseMessage::Quit).unwrap();
+            });
+
+            window
+        });
+
+        let pause_window = model_reg.create(window_param);
+        pause_window.set_scale(1.5, 0.9);
+
+        let pause_window_weak = pause_window.as_weak();
+
+        // Setup replay recording, practice runs and tests are not recorded.
+
+        #[allow(unused_assignments)]
+        #[allow(unused_mut)]
+        let mut test = false;
+        #[cfg(feature = "test")]
+        {
+            test = param.test;
+        }
+
+        let song_info = &param.song_info;
+        let beatmap_info = &song_info.get_beatmap_infos()[param.beatmap_info_index];
+
+        let replay_dir_opt = settings.get_replay_dir_opt().filter(|_| !test && param.practice_opt.is_none()).map(PathBuf::from);
+        let replay_opt = replay_dir_opt.as_ref().map(|_| create_replay(song_info, beatmap_info, playfield.jump_dist));
+
+        let inner = Inner {
+            prev_click: true,
+            prev_poses: false,
+            pause_countdown: 0,
+            pause_start: Instant::now(),
+            replay_opt,
+        };
+
+        Ok(Self {
+            song_name: format!("{} - {}", song_info.get_author(), song_info.get_title()),
+            asset_mgr: param.asset_mgr,
+            beatmap_info_index: param.beatmap_info_index,
+            practice_opt: param.practice_opt,
+            replay_dir_opt,
+            #[cfg(feature = "test")]
+            test,
+            ui_loop: ui_loop.clone(),
+            pause_window,
+            pause_window_weak,
+            pause_window_rx,
+            playfield,
+            inner: RefCell::new(inner),
+        })
+    }
+
+    fn pause(&self, scene_mgr: &SceneManager, inner: &mut Inner) {
+        self.playfield.set_audio_playing(false);
+
+        // The trigger is still pressed, it shouldn't click on the pause window.
+
+        scene_mgr.get_ui_subr().reset();
+
+        self.set_pause_countdown(inner, 0);
+        self.set_pause_error_msg(String::new());
+        place_pause_window(&self.pause_window, &calc_lane_rot(self.playfield.get_lane_rotation()));
+        self.pause_window.set_visible(true);
+
+        self.playfield.set_play_state(PlayState::Paused(None));
+        inner.pause_start = Instant::now();
+    }
+
+    fn resume(&self, inner: &mut Inner) {
+        self.pause_window.set_visible(false);
+        self.playfield.pointer.set_visible(false);
+
+        // The swings are starting again.
+
+        self.playfield.clear_saber_histories();
+
+        if let Some(replay) = &mut inner.replay_opt {
+            replay.pauses.push(ReplayPause {
+                duration: inner.pause_start.elapsed().as_secs().try_into().unwrap(),
+                time: self.playfield.get_prev_audio_ts(),
+            });
+        }
+
+        self.playfield.set_audio_playing(true);
+        self.playfield.set_play_state(PlayState::Playing);
+    }
+
+    fn set_pause_countdown(&self, inner: &mut Inner, pause_countdown: u32) {
+        if inner.pause_countdown != pause_countdown {
+            inner.pause_countdown = pause_countdown;
+
+            self.ui_loop.add_callback({
+                let window_weak = self.pause_window_weak.clone();
+
+                move || {
+                    window_weak.unwrap().set_countdown(pause_countdown.try_into().unwrap());
+                }
+            });
+        }
+    }
+
+    fn restart(&self, scene_mgr: &SceneManager) {
+        // If the game can't be started again, then it stays paused with the error.
+
+        let result = GameParam::reload(Arc::clone(&self.asset_mgr), self.beatmap_info_index, #[cfg(feature = "test")] self.test).and_then(|param| {
+            match self.practice_opt {
+                Some(practice) => scene_mgr.load(param.with_practice(practice)),
+                None => scene_mgr.load(param),
             }
+        });
+
+        if let Err(e) = result {
+            self.set_pause_error_msg(format!("Unable to restart: {}", e));
         }
+    }
+
+    fn set_pause_error_msg(&self, error_msg: String) {
+        self.ui_loop.add_callback({
+            let window_weak = self.pause_window_weak.clone();
+
+            move || {
+                window_weak.unwrap().set_error_msg(error_msg.into());
+            }
+        });
+    }
+
+    fn show_results(&self, scene_mgr: &SceneManager, inner: &mut Inner) {
+        let game_stats = self.playfield.get_stats();
+        let cleared = !matches!(self.playfield.get_play_state(), PlayState::Failed);
+        let mut param = ResultsParam::new(Arc::clone(&self.asset_mgr), self.beatmap_info_index, #[cfg(feature = "test")] self.test, self.practice_opt, self.song_name.clone(), game_stats, cleared);
 
-        // Update sabers.
+        // The outcome of saving the replay is displayed with the results.
 
-        Self::update_saber(&self.saber_l, &scene_input.pose_l_opt);
-        Self::update_saber(&self.saber_r, &scene_input.pose_r_opt);
+        if let Some(replay_dir) = &self.replay_dir_opt && let Some(mut replay) = inner.replay_opt.take() {
+            let (notes, walls) = self.playfield.take_replay_events();
 
-        // TODO: Implement pause menu.
+            // Like in BS, No-Fail is only listed among the modifiers, if it has kept the song going.
+
+            replay.info.score = game_stats.get_score().try_into().unwrap();
+            replay.info.modifiers = if game_stats.is_no_fail_used() { "NF".to_string() } else { String::new() };
+            replay.notes = notes;
+            replay.walls = walls;
+
+            let replay_msg = match Self::save_replay(&replay, replay_dir) {
+                Ok(filename) => format!("Replay saved: {}", filename),
+                Err(e) => format!("Unable to save replay: {}", e),
+            };
+
+            param = param.with_replay_msg(replay_msg);
+        }
+
+        scene_mgr.load(param).expect("Unable to load scene");
+    }
+
+    fn save_replay(replay: &Replay, replay_dir: &Path) -> Result<String, String> {
+        let filename = replay.info.calc_filename();
+
+        fs::create_dir_all(replay_dir).map_err(|e| e.to_string())?;
+
+        let file = File::create(replay_dir.join(&filename)).map_err(|e| e.to_string())?;
+        replay.write(&mut BufWriter::new(file)).map_err(|e| e.to_string())?;
+
+        Ok(filename)
+    }
+
+    fn get_loop_opt(&self) -> Option<(f32, f32)> {
+        self.practice_opt.and_then(|practice| practice.loop_opt)
+    }
+
+    fn update_playfield(&self, inner: &mut Inner, audio_ts: f32, scene_input: &SceneInput) {
+        // The frames are recorded while playing, until the song is failed.
+
+        let playing = matches!(self.playfield.get_play_state(), PlayState::Playing);
+
+        if playing && let Some(replay) = &mut inner.replay_opt {
+            record_frame(replay, audio_ts, scene_input);
+        }
+
+        self.playfield.update(audio_ts, scene_input);
+
+        if playing && let PlayState::Failing(fail_ts) = self.playfield.get_play_state() && let Some(replay) = &mut inner.replay_opt {
+            replay.info.fail_time = fail_ts;
+        }
+    }
+}
+
+impl Scene for Game {
+    fn update(&self, scene_mgr: &SceneManager, scene_input: &SceneInput) {
+        let inner = &mut *self.inner.borrow_mut();
+        let playfield = &self.playfield;
+        let mut done = false;
+        let mut finished = false;
+
+        // Detect trigger (rising edge) click and the loss of the poses (e.g. the XR session is not focused anymore).
 
         let mut click = false;
 
@@ -476,23 +1741,198 @@ impl Scene for Game {
             click |= pose_r.get_click();
         }
 
-        if !inner.prev_click && click {
-            done = true;
-        }
+        let poses = scene_input.pose_l_opt.is_some() || scene_input.pose_r_opt.is_some();
+        let poses_lost = inner.prev_poses && !poses;
+        let clicked = !inner.prev_click && click;
 
         inner.prev_click = click;
+        inner.prev_poses = poses;
 
-        // If we are finished, then go to menu.
+        // Both are pausing the game.
 
-        if done {
+        if matches!(playfield.get_play_state(), PlayState::Playing) && (clicked || poses_lost) {
+            self.pause(scene_mgr, inner);
+        }
+
+        if let PlayState::Paused(resume_time_opt) = playfield.get_play_state() {
+            // The objects are frozen, while the pause window is in use.
+
+            playfield.update_ui(scene_mgr, &[&self.pause_window], scene_input);
+
+            match self.pause_window_rx.try_recv() {
+                Ok(PauseMessage::Resume) => {
+                    playfield.set_play_state(PlayState::Paused(Some(Instant::now())));
+                },
+                Ok(PauseMessage::Restart) => {
+                    self.restart(scene_mgr);
+                },
+                Ok(PauseMessage::Quit) => {
+                    done = true;
+                },
+                Err(e) => {
+                    assert!(matches!(e, TryRecvError::Empty));
+                },
+            }
+
+            if let Some(resume_time) = resume_time_opt {
+                let remaining_t = RESUME_T - resume_time.elapsed().as_secs_f32();
+
+                if remaining_t <= 0.0 {
+                    self.resume(inner);
+                } else {
+                    self.set_pause_countdown(inner, remaining_t.ceil() as u32);
+                }
+            }
+        } else {
+            // Update objects, the song is rewound at the end of the loop of practice mode.
+
+            match playfield.get_song_pos() {
+                SongPos::At(ts) => {
+                    if let Some((loop_start, loop_end)) = self.get_loop_opt() && ts >= loop_end && matches!(playfield.get_play_state(), PlayState::Playing) {
+                        // If the song can't be rewound, then the game is paused with the error.
+
+                        if let Err(e) = playfield.rewind(loop_start) {
+                            self.pause(scene_mgr, inner);
+                            self.set_pause_error_msg(format!("Unable to rewind: {}", e));
+                        }
+                    } else {
+                        self.update_playfield(inner, ts, scene_input);
+                    }
+                },
+                SongPos::End => finished = true,
+                SongPos::Unknown => (),
+            }
+
+            playfield.update_sabers(Some(scene_input));
+        }
+
+        // If the song is over (cleared or failed), then go to results. If the game is quit, then go to menu.
+
+        if finished || matches!(playfield.get_play_state(), PlayState::Failed) {
+            self.show_results(scene_mgr, inner);
+        } else if done {
             scene_mgr.load(MenuParam::new()).expect("Unable to load scene");
         }
     }
 }
 
+impl SaberHistory {
+    fn new() -> Self {
+        Self {
+            entries: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    fn record(&self, ts: f32, pose_opt: Option<&dyn ScenePose>) {
+        let entries = &mut *self.entries.borrow_mut();
+
+        // If the saber disappears, then its swing is interrupted.
+
+        let Some(pose) = pose_opt else {
+            entries.clear();
+            return;
+        };
+
+        entries.push_back(SaberHistoryEntry {
+            ts,
+            pos: *pose.get_pos(),
+            dir: pose.get_rot() * SABER_DIR.normalize(),
+        });
+
+        while let Some(entry) = entries.front() && ts - entry.ts > SWING_HISTORY_T {
+            entries.pop_front();
+        }
+    }
+
+    fn clear(&self) {
+        self.entries.borrow_mut().clear();
+    }
+
+    fn get_dir_opt(&self) -> Option<Vector3<f32>> {
+        self.entries.borrow().back().map(|entry| entry.dir)
+    }
+
+    fn calc_pre_swing_angle(&self) -> f32 {
+        // Go back in time until the saber turns back, that is where the swing started.
+
+        let entries = self.entries.borrow();
+        let Some(last) = entries.back() else {
+            return 0.0;
+        };
+
+        let mut max_angle = 0.0;
+
+        for entry in entries.iter().rev() {
+            let angle = Deg::from(last.dir.angle(entry.dir)).0;
+            if angle < max_angle - SWING_END_ANGLE {
+                break;
+            }
+
+            max_angle = f32::max(max_angle, angle);
+        }
+
+        max_angle
+    }
+
+    fn calc_tip_speed(&self) -> f32 {
+        self.calc_tip_velocity().magnitude()
+    }
+
+    fn calc_tip_velocity(&self) -> Vector3<f32> {
+        // Average velocity of the tip, since CUT_SPEED_T (or the oldest entry).
+
+        let entries = self.entries.borrow();
+        let Some(last) = entries.back() else {
+            return Vector3::zero();
+        };
+
+        let Some(first) = entries.iter().rev().find(|entry| last.ts - entry.ts >= CUT_SPEED_T).or(entries.front()) else {
+            return Vector3::zero();
+        };
+
+        let t = last.ts - first.ts;
+        if t <= 0.0 {
+            return Vector3::zero();
+        }
+
+        let saber_len = SABER_DIR.magnitude();
+        let tip_move = (last.pos + last.dir * saber_len) - (first.pos + first.dir * saber_len);
+
+        tip_move / t
+    }
+
+    fn calc_center_dist(&self, center: &Vector3<f32>) -> f32 {
+        // The cut plane contains the saber and the movement of its tip since the previous update.
+        // Without movement, the distance from the saber is taken.
+
+        let entries = self.entries.borrow();
+        let mut iter = entries.iter().rev();
+
+        let Some(last) = iter.next() else {
+            return ACCURACY_DIST;
+        };
+
+        let saber_len = SABER_DIR.magnitude();
+        let d = center - last.pos;
+
+        if let Some(prev) = iter.next() {
+            let tip_move = (last.pos + last.dir * saber_len) - (prev.pos + prev.dir * saber_len);
+            let normal = last.dir.cross(tip_move);
+
+            if normal.magnitude2() > f32::EPSILON {
+                return d.dot(normal.normalize()).abs();
+            }
+        }
+
+        (d - last.dir * d.dot(last.dir)).magnitude()
+    }
+}
+
 struct CubeObj {
     zone_info: Rc<ZoneInfo>,
     cube_info: Rc<CubeInfo>,
+    saber_history: Rc<SaberHistory>,
+    other_saber_history: Rc<SaberHistory>, // Of the other color, which gives a bad cut
     sliced_status: SlicedStatus,
     #[cfg(feature = "test")]
     test: bool,
@@ -500,19 +1940,37 @@ struct CubeObj {
 
 #[derive(Clone, Copy)]
 enum SlicedStatus {
-    WaitForAbove,
-    WaitForBelow(f32, f32),
-    AtBelow,
+    WaitForTouch,
+    Touching(f32, Vector3<f32>), // Length of saber and position of that point at the first touch (in the coordinates of the cube)
+    Cut,
+    WrongDir,
+}
+
+// How a cube has been resolved.
+#[derive(Clone, Copy)]
+enum CubeOutcome {
+    Cut,
+    BadCut(BadCutReason),
+    Miss, // The cube has passed the player
+}
+
+#[derive(Clone, Copy)]
+enum BadCutReason {
+    WrongColor,
+    WrongDir,
+    TooSlow,
 }
 
 impl CubeObj {
-    fn new(zone_info: Rc<ZoneInfo>, cube_info: Rc<CubeInfo>, #[cfg(feature = "test")] test: bool) -> Self {
+    fn new(zone_info: Rc<ZoneInfo>, cube_info: Rc<CubeInfo>, saber_history: Rc<SaberHistory>, other_saber_history: Rc<SaberHistory>, #[cfg(feature = "test")] test: bool) -> Self {
         cube_info.cube.set_visible(true);
 
         Self {
             zone_info,
             cube_info,
-            sliced_status: SlicedStatus::WaitForAbove,
+            saber_history,
+            other_saber_history,
+            sliced_status: SlicedStatus::WaitForTouch,
             #[cfg(feature = "test")]
             test,
         }
@@ -571,36 +2029,94 @@ impl CubeObj {
     }
 
     fn test_slice(&self, cube_pos: &Vector3<f32>, cube_rot: &Quaternion<f32>, pose: &dyn ScenePose, len: f32, sliced_status: SlicedStatus) -> SlicedStatus {
-        // Check whether the [saber handle..len] passes through the center plane.
+        // Remember the shortest length of saber which intersects the cube. Once this point on the saber
+        // has moved far enough, its movement (ignoring the depth) is compared to the cut direction.
 
-        let calc_z = |len| {
+        let calc_pos = |len| {
             let center_m = self.calc_center_m(cube_pos, cube_rot);
             let saber_pos = pose.get_pos() + pose.get_rot() * SABER_DIR.normalize() * len;
-            let pos = center_m * saber_pos.extend(1.0);
-            pos.z
+            (center_m * saber_pos.extend(1.0)).truncate()
         };
 
-        let mut new_sliced_status = sliced_status;
+        match sliced_status {
+            SlicedStatus::WaitForTouch => SlicedStatus::Touching(len, calc_pos(len)),
+            SlicedStatus::Touching(len, start_pos) => {
+                let d = calc_pos(len) - start_pos;
+                let d = Vector3::new(d.x, 0.0, d.z);
 
-        match new_sliced_status {
-            SlicedStatus::WaitForAbove => {
-                let z = calc_z(len);
-                if z > 0.0 {
-                    // Remember the shortest length of saber which intersects the cube. This point on
-                    // the saber has to move into the direction of the cube center.
-
-                    new_sliced_status = SlicedStatus::WaitForBelow(len, z); 
-                }
-            },
-            SlicedStatus::WaitForBelow(len, z) => {
-                if z - calc_z(len) >= CUBE_SIZE / 4.0 {
-                    new_sliced_status = SlicedStatus::AtBelow;
+                if d.magnitude() < CUBE_SIZE / 4.0 {
+                    sliced_status
+                } else if Deg::from(d.angle(-Vector3::unit_z())).0 <= CUT_DIR_TOLERANCE {
+                    SlicedStatus::Cut
+                } else {
+                    SlicedStatus::WrongDir
                 }
             },
             _ => panic!("Invalid status"),
         }
+    }
+
+    fn test_cut(&mut self, cube_pos: &Vector3<f32>, cube_rot: &Quaternion<f32>, pose: &dyn ScenePose, len: f32) -> Option<CubeOutcome> {
+        let outcome_opt = if self.cube_info.any {
+            // Once the saber touches cube, the cube is becoming cut.
+
+            Some(CubeOutcome::Cut)
+        } else {
+            // The saber should stay in contact with the cube (see test_touch),
+            // while the slicing test is still in progress.
+
+            self.sliced_status = self.test_slice(cube_pos, cube_rot, pose, len, self.sliced_status);
+
+            match self.sliced_status {
+                SlicedStatus::Cut => Some(CubeOutcome::Cut),
+                SlicedStatus::WrongDir => Some(CubeOutcome::BadCut(BadCutReason::WrongDir)),
+                _ => None,
+            }
+        };
+
+        // A cut in the right direction still needs enough speed.
+
+        match outcome_opt {
+            Some(CubeOutcome::Cut) if self.saber_history.calc_tip_speed() < CUT_MIN_SPEED => Some(CubeOutcome::BadCut(BadCutReason::TooSlow)),
+            _ => outcome_opt,
+        }
+    }
 
-        new_sliced_status
+    fn get_max_score(&self) -> u32 {
+        if self.cube_info.chain_info_opt.is_some() {
+            SCORE_CHAIN_HEAD
+        } else {
+            SCORE_NOTE
+        }
+    }
+
+    fn create_cut_info(&self, audio_ts: f32, cube_pos: &Vector3<f32>, outcome: CubeOutcome) -> ReplayCutInfo {
+        // The swing is recorded for the saber, which has touched the cube.
+
+        let wrong_color = matches!(outcome, CubeOutcome::BadCut(BadCutReason::WrongColor));
+        let saber_history = if wrong_color { &self.other_saber_history } else { &self.saber_history };
+
+        let tip_velocity = saber_history.calc_tip_velocity();
+        let saber_dir = if tip_velocity.magnitude2() > f32::EPSILON { tip_velocity.normalize() } else { tip_velocity };
+        let pre_swing_angle = saber_history.calc_pre_swing_angle();
+
+        ReplayCutInfo {
+            speed_ok: !matches!(outcome, CubeOutcome::BadCut(BadCutReason::TooSlow)),
+            direction_ok: !matches!(outcome, CubeOutcome::BadCut(BadCutReason::WrongDir)),
+            saber_type_ok: !wrong_color,
+            was_cut_too_soon: false,
+            saber_speed: tip_velocity.magnitude(),
+            saber_dir: bsor::to_unity_vec(&saber_dir),
+            saber_type: self.cube_info.note_type as i32 ^ wrong_color as i32,
+            time_deviation: self.cube_info.ts - audio_ts,
+            cut_dir_deviation: 0.0, // Not measured
+            cut_point: bsor::to_unity_vec(cube_pos),
+            cut_normal: Vector3::zero(), // Not measured
+            cut_distance_to_center: saber_history.calc_center_dist(cube_pos),
+            cut_angle: pre_swing_angle,
+            before_cut_rating: (pre_swing_angle / PRE_SWING_ANGLE).clamp(0.0, 1.0),
+            after_cut_rating: 0.0, // See SwingRatingObj
+        }
     }
 
     fn calc_center_m(&self, cube_pos: &Vector3<f32>, cube_rot: &Quaternion<f32>) -> Matrix4<f32> {
@@ -621,16 +2137,19 @@ impl Obj for CubeObj {
             test = self.test;
         }
 
-        // Hide outgoing cube.
+        // Hide outgoing cube, it has been missed.
 
         let zone_info = &self.zone_info;
-        let cube_info = &self.cube_info;
+        let cube_info = Rc::clone(&self.cube_info); // Not borrowing self, see test_cut
 
         if !test {
             let ts_out = audio_ts - zone_info.out_t;
 
             if cube_info.ts < ts_out {
                 cube_info.cube.set_visible(false);
+                game_stats.miss(self.get_max_score(), ENERGY_MISS);
+                game_stats.record_note(cube_info.note_id, audio_ts, cube_info.ts, ReplayNoteEvent::Miss, None);
+
                 return UpdateResult::Remove;
             }
         }
@@ -640,76 +2159,155 @@ impl Obj for CubeObj {
         // TODO: always display cubes at z=0 and then move them up?
 
         let ts = if !test { cube_info.ts - audio_ts } else { 0.0 };
+        let (y, z_base, angle) = zone_info.calc_placement(ts, cube_info.angle);
+
+        let pos = cube_info.lane_rot * Vector3::new(cube_info.x, y + OFFSET_Y, cube_info.z + z_base); // TODO: ts_in/ts_out should be offseted because of OFFSET_Y.
+        cube_info.cube.set_pos(&pos);
+
+        let rot = cube_info.lane_rot * Quaternion::from_angle_y(Deg(angle));
+        cube_info.cube.set_rot(&rot);
+
+        if !cube_info.spawn_effect {
+            cube_info.cube.set_visible(ts <= zone_info.in23_t);
+        }
 
-        let (y, z_base, angle) = if ts <= 0.0 {
-            (ts * zone_info.out_v, CUBE_FLOOR, cube_info.angle)
-        } else if ts <= zone_info.in3_t {
-            (ts * zone_info.in3_v, CUBE_FLOOR, cube_info.angle)
-        } else if ts <= zone_info.in23_t {
-            let factor = (zone_info.in23_t - ts) / (zone_info.in23_t - zone_info.in3_t);
-            (zone_info.in2_dist * (1.0 - factor) + zone_info.in3_dist, CUBE_FLOOR * Deg(90.0 * factor).sin(), cube_info.angle * factor)
+        // Select matching saber, the other one gives a bad cut.
+
+        let (pose_opt, other_pose_opt) = match cube_info.note_type {
+            NoteType::Left => (scene_input.pose_l_opt, scene_input.pose_r_opt),
+            NoteType::Right => (scene_input.pose_r_opt, scene_input.pose_l_opt),
+        };
+
+        // Do hit detection.
+
+        let result_opt = if let Some(pose) = other_pose_opt && self.test_touch(&pos, &rot, pose).is_some() {
+            Some((CubeOutcome::BadCut(BadCutReason::WrongColor), pose))
+        } else if let Some(pose) = pose_opt && let Some(len) = self.test_touch(&pos, &rot, pose) {
+            self.test_cut(&pos, &rot, pose, len).map(|outcome| (outcome, pose))
         } else {
-            let factor = (zone_info.in123_t - ts) / (zone_info.in123_t - zone_info.in23_t);
-            (zone_info.in1_dist * (1.0 - factor) + zone_info.in2_dist + zone_info.in3_dist, 0.0, 0.0)
+            // If the saber disappears or it is not touching cube, then restart detection.
+
+            self.sliced_status = SlicedStatus::WaitForTouch;
+            None
         };
 
-        let pos = Vector3::new(cube_info.x, y + OFFSET_Y, cube_info.z + z_base); // TODO: ts_in/ts_out should be offseted because of OFFSET_Y.
-        cube_info.cube.set_pos(&pos);
+        let Some((outcome, pose)) = result_opt else {
+            return UpdateResult::Keep;
+        };
 
-        let rot = Quaternion::from_angle_y(Deg(angle));
-        cube_info.cube.set_rot(&rot);
+        // Both cut and bad cut are breaking the cube.
+
+        cube_info.cube.sliced();
+
+        let mut new_alive_objs: AliveObjs = vec![
+            Box::new(SlicedObj::new(Rc::clone(&cube_info.cube), &rot, &pos, false)),
+            Box::new(SlicedObj::new(Rc::clone(&cube_info.cube), &rot, &pos, true)),
+        ];
+
+        let cut_info = self.create_cut_info(audio_ts, &pos, outcome);
+
+        match outcome {
+            CubeOutcome::Cut => {
+                // Rate the swing before the cut and the accuracy of the cut.
+
+                let multipliers = game_stats.cut();
+
+                let pre_swing_score = score::calc_swing_score(cut_info.before_cut_rating, SCORE_PRE_SWING);
+                let accuracy_score = score::calc_swing_score(1.0 - cut_info.cut_distance_to_center / ACCURACY_DIST, SCORE_ACCURACY);
+                let score = pre_swing_score + accuracy_score;
+
+                // Cutting a chain head breaks its links (see ChainLinkObj) and it is scored by the chain rules,
+                // otherwise the post-swing is still to be rated.
+
+                if let Some(chain_info) = &cube_info.chain_info_opt {
+                    chain_info.head_cut.set(true);
+                    game_stats.add_score(score, SCORE_CHAIN_HEAD, multipliers);
+                    game_stats.record_note(cube_info.note_id, audio_ts, cube_info.ts, ReplayNoteEvent::Good, Some(cut_info));
+                } else {
+                    new_alive_objs.push(Box::new(SwingRatingObj::new(Rc::clone(&self.saber_history), Rc::clone(&cube_info), audio_ts, score, multipliers, cut_info)));
+                }
+            },
+            CubeOutcome::BadCut(reason) => {
+                game_stats.bad_cut(reason, self.get_max_score());
+                game_stats.record_note(cube_info.note_id, audio_ts, cube_info.ts, ReplayNoteEvent::Bad, Some(cut_info));
+            },
+            CubeOutcome::Miss => {
+                game_stats.miss(self.get_max_score(), ENERGY_MISS);
+                game_stats.record_note(cube_info.note_id, audio_ts, cube_info.ts, ReplayNoteEvent::Miss, None);
+            },
+        }
+
+        pose.apply_haptic();
+
+        UpdateResult::Replace(new_alive_objs)
+    }
+}
 
-        // Select matching saber.
+// Rates the swing after the cut of a cube, then the score of the cut is given.
+struct SwingRatingObj {
+    saber_history: Rc<SaberHistory>,
+    cube_info: Rc<CubeInfo>,
+    cut_dir_opt: Option<Vector3<f32>>, // Saber direction at the cut
+    cut_ts: f32,
+    score: u32, // Pre-swing and accuracy score
+    multipliers: CutMultipliers,
+    cut_info: ReplayCutInfo, // Recorded, once the post-swing is rated
+    max_angle: f32, // [deg]
+}
 
-        let pose_opt = match cube_info.note_type {
-            NoteType::Left => scene_input.pose_l_opt,
-            NoteType::Right => scene_input.pose_r_opt,
-        };
+impl SwingRatingObj {
+    fn new(saber_history: Rc<SaberHistory>, cube_info: Rc<CubeInfo>, cut_ts: f32, score: u32, multipliers: CutMultipliers, cut_info: ReplayCutInfo) -> Self {
+        let cut_dir_opt = saber_history.get_dir_opt();
 
-        // Do hit detection.
+        Self {
+            saber_history,
+            cube_info,
+            cut_dir_opt,
+            cut_ts,
+            score,
+            multipliers,
+            cut_info,
+            max_angle: 0.0,
+        }
+    }
+}
 
-        if let Some(pose) = pose_opt && let Some(len) = self.test_touch(&pos, &rot, pose) {
-            let mut sliced = false;
+impl Obj for SwingRatingObj {
+    fn update(&mut self, audio_ts: f32, _ts_diff: f32, _scene_input: &SceneInput, game_stats: &mut GameStats) -> UpdateResult {
+        // The rating is over, once the saber turns back, the full angle is reached, the time is up
+        // or the saber disappears.
 
-            if cube_info.any {
-                // Once the saber touches cube, the cube is becoming sliced.
+        let done = if let Some(cut_dir) = self.cut_dir_opt && let Some(dir) = self.saber_history.get_dir_opt() {
+            let angle = Deg::from(cut_dir.angle(dir)).0;
+            self.max_angle = self.max_angle.max(angle);
 
-                sliced = true;
-            } else {
-                // The saber should stay in contact with the cube (see test_touch above),
-                // while the slicing test is still in progress.
-                
-                self.sliced_status = self.test_slice(&pos, &rot, pose, len, self.sliced_status);
-                if matches!(self.sliced_status, SlicedStatus::AtBelow) {
-                    sliced = true;
-                }
-            }
+            angle < self.max_angle - SWING_END_ANGLE || self.max_angle >= POST_SWING_ANGLE || audio_ts - self.cut_ts >= POST_SWING_T
+        } else {
+            true
+        };
 
-            if sliced {
-                cube_info.cube.sliced();
+        if done {
+            let after_cut_rating = (self.max_angle / POST_SWING_ANGLE).clamp(0.0, 1.0);
+            let post_swing_score = score::calc_swing_score(after_cut_rating, SCORE_POST_SWING);
+            game_stats.add_score(self.score + post_swing_score, SCORE_NOTE, self.multipliers);
 
-                let new_alive_objs: AliveObjs = vec![
-                    Box::new(SlicedObj::new(Rc::clone(cube_info), &pos, false)),
-                    Box::new(SlicedObj::new(Rc::clone(cube_info), &pos, true)),
-                ];
+            let cut_info = ReplayCutInfo {
+                after_cut_rating,
+                ..self.cut_info
+            };
 
-                game_stats.inc_count();
-                pose.apply_haptic();
+            game_stats.record_note(self.cube_info.note_id, self.cut_ts, self.cube_info.ts, ReplayNoteEvent::Good, Some(cut_info));
 
-                return UpdateResult::Replace(new_alive_objs);
-            }
+            UpdateResult::Remove
         } else {
-            // If the saber disappears or it is not touching cube, then restart detection.
-
-            self.sliced_status = SlicedStatus::WaitForAbove;
+            UpdateResult::Keep
         }
-
-        UpdateResult::Keep
     }
 }
 
 struct SlicedObj {
-    cube_info: Rc<CubeInfo>,
+    cube: Rc<Cube>,
+    rot: Quaternion<f32>,
     pos: Vector3<f32>,
     right: bool,
     v: Vector3<f32>, // [m/s]
@@ -719,20 +2317,19 @@ struct SlicedObj {
 }
 
 impl SlicedObj {
-    fn new(cube_info: Rc<CubeInfo>, pos: &Vector3<f32>, right: bool) -> Self {
+    fn new(cube: Rc<Cube>, rot: &Quaternion<f32>, pos: &Vector3<f32>, right: bool) -> Self {
         let factor = if !right {
             -1.0
         } else {
             1.0
         };
 
-        let angle = cube_info.angle;
-
         Self {
-            cube_info,
+            cube,
+            rot: *rot,
             pos: *pos,
             right,
-            v: Quaternion::from_angle_y(Deg(angle)) * Vector3::new(factor * 3.0 + rand::random_range(-1.0..1.0), rand::random_range(-1.0..0.0), rand::random_range(-1.0..1.0)),
+            v: rot * Vector3::new(factor * 3.0 + rand::random_range(-1.0..1.0), rand::random_range(-1.0..0.0), rand::random_range(-1.0..1.0)),
             rot_axis: Vector3::new(rand::random_range(-1.0..1.0), rand::random_range(-1.0..1.0), rand::random_range(-1.0..1.0)).normalize(),
             rot_angle: 4.0 * rand::random_range(30.0..100.0),
             ts_diff_acc: 0.0,
@@ -742,7 +2339,7 @@ impl SlicedObj {
 
 impl Obj for SlicedObj {
     fn update(&mut self, _audio_ts: f32, ts_diff: f32, _scene_input: &SceneInput, _game_stats: &mut GameStats) -> UpdateResult {
-        let cube_info = &self.cube_info;
+        let cube = &self.cube;
 
         self.ts_diff_acc += ts_diff;
 
@@ -752,22 +2349,22 @@ impl Obj for SlicedObj {
         self.pos += self.v * ts_diff;
 
         let visible = self.pos.z > -CUBE_SIZE; // Should be enough.
-        let rot = Quaternion::from_angle_y(Deg(cube_info.angle)) * Quaternion::from_axis_angle(self.rot_axis, Deg(self.rot_angle) * self.ts_diff_acc); // TODO: Calculate rot from previous rot + delta (like self.pos)?
+        let rot = self.rot * Quaternion::from_axis_angle(self.rot_axis, Deg(self.rot_angle) * self.ts_diff_acc); // TODO: Calculate rot from previous rot + delta (like self.pos)?
 
         if !self.right {
             if visible {
-                cube_info.cube.set_pos_l(&self.pos);
-                cube_info.cube.set_rot_l(&rot);
+                cube.set_pos_l(&self.pos);
+                cube.set_rot_l(&rot);
             } else {
-                cube_info.cube.set_visible_l(false);
+                cube.set_visible_l(false);
             }
         } else {
             #[allow(clippy::collapsible_else_if)]
             if visible {
-                cube_info.cube.set_pos_r(&self.pos);
-                cube_info.cube.set_rot_r(&rot);
+                cube.set_pos_r(&self.pos);
+                cube.set_rot_r(&rot);
             } else {
-                cube_info.cube.set_visible_r(false);
+                cube.set_visible_r(false);
             }
         }
 
@@ -779,26 +2376,362 @@ impl Obj for SlicedObj {
     }
 }
 
+struct ChainLinkObj {
+    zone_info: Rc<ZoneInfo>,
+    chain_link_info: Rc<ChainLinkInfo>,
+}
+
+impl ChainLinkObj {
+    fn new(zone_info: Rc<ZoneInfo>, chain_link_info: Rc<ChainLinkInfo>) -> Self {
+        chain_link_info.cube.set_visible(true);
+
+        Self {
+            zone_info,
+            chain_link_info,
+        }
+    }
+}
+
+impl ChainLinkObj {
+    fn create_cut_info(&self, audio_ts: f32, link_pos: &Vector3<f32>) -> ReplayCutInfo {
+        // The link is broken by the cut of its chain head, so there is no swing to be measured.
+
+        ReplayCutInfo {
+            speed_ok: true,
+            direction_ok: true,
+            saber_type_ok: true,
+            was_cut_too_soon: false,
+            saber_speed: 0.0,
+            saber_dir: Vector3::zero(),
+            saber_type: self.chain_link_info.note_type as i32,
+            time_deviation: self.chain_link_info.ts - audio_ts,
+            cut_dir_deviation: 0.0,
+            cut_point: bsor::to_unity_vec(link_pos),
+            cut_normal: Vector3::zero(),
+            cut_distance_to_center: 0.0,
+            cut_angle: 0.0,
+            before_cut_rating: 1.0,
+            after_cut_rating: 1.0,
+        }
+    }
+}
+
+impl Obj for ChainLinkObj {
+    fn update(&mut self, audio_ts: f32, _ts_diff: f32, _scene_input: &SceneInput, game_stats: &mut GameStats) -> UpdateResult {
+        // Hide outgoing chain link, the head of the chain was missed.
+
+        let zone_info = &self.zone_info;
+        let chain_link_info = &self.chain_link_info;
+
+        let ts_out = audio_ts - zone_info.out_t;
+
+        if chain_link_info.ts < ts_out {
+            chain_link_info.cube.set_visible(false);
+            game_stats.miss(SCORE_CHAIN_LINK, ENERGY_CHAIN_LINK_MISS);
+            game_stats.record_note(chain_link_info.note_id, audio_ts, chain_link_info.ts, ReplayNoteEvent::Miss, None);
+            return UpdateResult::Remove;
+        }
+
+        // Update position.
+
+        let ts = chain_link_info.ts - audio_ts;
+        let (y, z_base, angle) = zone_info.calc_placement(ts, chain_link_info.angle);
+
+        let pos = chain_link_info.lane_rot * Vector3::new(chain_link_info.x, y + OFFSET_Y, chain_link_info.z + z_base);
+        chain_link_info.cube.set_pos(&pos);
+
+        let rot = chain_link_info.lane_rot * Quaternion::from_angle_y(Deg(angle));
+        chain_link_info.cube.set_rot(&rot);
+
+        // The link breaks, once the head of the chain is cut.
+
+        if chain_link_info.chain_info.head_cut.get() {
+            chain_link_info.cube.sliced();
+
+            let new_alive_objs: AliveObjs = vec![
+                Box::new(SlicedObj::new(Rc::clone(&chain_link_info.cube), &rot, &pos, false)),
+                Box::new(SlicedObj::new(Rc::clone(&chain_link_info.cube), &rot, &pos, true)),
+            ];
+
+            let multipliers = game_stats.cut();
+            game_stats.add_score(SCORE_CHAIN_LINK, SCORE_CHAIN_LINK, multipliers);
+            game_stats.record_note(chain_link_info.note_id, audio_ts, chain_link_info.ts, ReplayNoteEvent::Good, Some(self.create_cut_info(audio_ts, &pos)));
+
+            return UpdateResult::Replace(new_alive_objs);
+        }
+
+        UpdateResult::Keep
+    }
+}
+
+struct BombObj {
+    zone_info: Rc<ZoneInfo>,
+    bomb_info: Rc<BombInfo>,
+}
+
+impl BombObj {
+    fn new(zone_info: Rc<ZoneInfo>, bomb_info: Rc<BombInfo>) -> Self {
+        bomb_info.bomb.set_visible(true);
+
+        Self {
+            zone_info,
+            bomb_info,
+        }
+    }
+
+    fn test_touch(&self, bomb_pos: &Vector3<f32>, pose: &dyn ScenePose) -> bool {
+        // Calculate the distance between the bomb center and the saber (as a line segment).
+
+        let saber_len = SABER_DIR.magnitude();
+        let saber_dir = pose.get_rot() * SABER_DIR.normalize();
+
+        let d = bomb_pos - pose.get_pos();
+        let len = d.dot(saber_dir).clamp(0.0, saber_len);
+
+        (d - saber_dir * len).magnitude() <= self.bomb_info.bomb.get_radius()
+    }
+}
+
+impl Obj for BombObj {
+    fn update(&mut self, audio_ts: f32, _ts_diff: f32, scene_input: &SceneInput, game_stats: &mut GameStats) -> UpdateResult {
+        // Hide outgoing bomb.
+
+        let zone_info = &self.zone_info;
+        let bomb_info = &self.bomb_info;
+
+        let ts_out = audio_ts - zone_info.out_t;
+
+        if bomb_info.ts < ts_out {
+            bomb_info.bomb.set_visible(false);
+            return UpdateResult::Remove;
+        }
+
+        // Update position.
+
+        let ts = bomb_info.ts - audio_ts;
+        let (y, z_base, _) = zone_info.calc_placement(ts, 0.0);
+
+        let pos = bomb_info.lane_rot * Vector3::new(bomb_info.x, y + OFFSET_Y, bomb_info.z + z_base);
+        bomb_info.bomb.set_pos(&pos);
+
+        // Do hit detection, any saber touching the bomb is a penalty.
+
+        for pose in [scene_input.pose_l_opt, scene_input.pose_r_opt].into_iter().flatten() {
+            if self.test_touch(&pos, pose) {
+                bomb_info.bomb.set_visible(false);
+
+                game_stats.inc_bomb_hits();
+                game_stats.break_combo();
+                game_stats.record_note(bomb_info.note_id, audio_ts, bomb_info.ts, ReplayNoteEvent::Bomb, None);
+                pose.apply_haptic();
+
+                return UpdateResult::Remove;
+            }
+        }
+
+        UpdateResult::Keep
+    }
+}
+
+struct ObstacleObj {
+    zone_info: Rc<ZoneInfo>,
+    obstacle_info: Rc<ObstacleInfo>,
+    in_wall: bool,
+}
+
+impl ObstacleObj {
+    fn new(zone_info: Rc<ZoneInfo>, obstacle_info: Rc<ObstacleInfo>) -> Self {
+        obstacle_info.wall.set_visible(true);
+
+        Self {
+            zone_info,
+            obstacle_info,
+            in_wall: false,
+        }
+    }
+}
+
+impl Obj for ObstacleObj {
+    fn update(&mut self, audio_ts: f32, _ts_diff: f32, scene_input: &SceneInput, game_stats: &mut GameStats) -> UpdateResult {
+        // Hide outgoing obstacle.
+
+        let zone_info = &self.zone_info;
+        let obstacle_info = &self.obstacle_info;
+
+        let ts_out = audio_ts - zone_info.out_t;
+
+        if obstacle_info.ts_end < ts_out {
+            obstacle_info.wall.set_visible(false);
+            return UpdateResult::Remove;
+        }
+
+        // Update position: both the front and the back of the obstacle are moving through the zones,
+        // the front reaches the front face of the cubes at ts == obstacle_info.ts.
+
+        let (y_min, _, _) = zone_info.calc_placement(obstacle_info.ts - audio_ts, 0.0);
+        let (y_max, _, _) = zone_info.calc_placement(obstacle_info.ts_end - audio_ts, 0.0);
+
+        let offset_y = OFFSET_Y - CUBE_SIZE / 2.0;
+
+        let min = Vector3::new(obstacle_info.x_min, y_min + offset_y, obstacle_info.z_min);
+        let max = Vector3::new(obstacle_info.x_max, y_max + offset_y, obstacle_info.z_max);
+        obstacle_info.wall.set_bounds(&min, &max);
+
+        // Check whether the head is inside the obstacle (the bounds are on the lane of the obstacle).
+        // Penalty is given once per obstacle.
+
+        let cam_pos = obstacle_info.lane_rot.conjugate() * scene_input.cam_pos;
+        let in_wall = (min.x..=max.x).contains(&cam_pos.x) && (min.y..=max.y).contains(&cam_pos.y) && (min.z..=max.z).contains(&cam_pos.z);
+
+        if in_wall {
+            if !self.in_wall {
+                game_stats.inc_wall_hits();
+                game_stats.break_combo();
+                game_stats.record_wall(obstacle_info.wall_id, audio_ts, obstacle_info.ts);
+            }
+
+            game_stats.set_in_wall();
+        }
+
+        self.in_wall = in_wall;
+
+        UpdateResult::Keep
+    }
+}
+
+struct ArcObj {
+    zone_info: Rc<ZoneInfo>,
+    arc_info: Rc<ArcInfo>,
+}
+
+impl ArcObj {
+    fn new(zone_info: Rc<ZoneInfo>, arc_info: Rc<ArcInfo>) -> Self {
+        arc_info.ribbon.set_visible(true);
+
+        Self {
+            zone_info,
+            arc_info,
+        }
+    }
+
+    fn calc_pos(&self, audio_ts: f32, end_info: &ArcEndInfo) -> Vector3<f32> {
+        // Arc ends are moving together with the cubes.
+
+        let (y, z_base, _) = self.zone_info.calc_placement(end_info.ts - audio_ts, 0.0);
+        Vector3::new(end_info.x, y + OFFSET_Y, end_info.z + z_base)
+    }
+}
+
+impl Obj for ArcObj {
+    fn update(&mut self, audio_ts: f32, _ts_diff: f32, _scene_input: &SceneInput, _game_stats: &mut GameStats) -> UpdateResult {
+        // Hide outgoing arc.
+
+        let arc_info = &self.arc_info;
+
+        let ts_out = audio_ts - self.zone_info.out_t;
+
+        if arc_info.tail.ts < ts_out {
+            arc_info.ribbon.set_visible(false);
+            return UpdateResult::Remove;
+        }
+
+        // Calculate bezier control points:
+        // - The arc leaves the head in the cut direction of the head and it
+        //   enters the tail from the opposite of the cut direction of the tail.
+        // - The mid-anchor mode pushes the middle of the arc sideways.
+        // - They are calculated on the lane and then rotated with it.
+
+        let head = &arc_info.head;
+        let tail = &arc_info.tail;
+
+        let head_pos = self.calc_pos(audio_ts, head);
+        let tail_pos = self.calc_pos(audio_ts, tail);
+
+        let step_y = Vector3::new(0.0, (tail_pos.y - head_pos.y) / 3.0, 0.0);
+        let anchor = Vector3::new(head.dir.z, 0.0, -head.dir.x) * arc_info.mid_anchor * ARC_CTRL_DIST / 2.0;
+
+        let ctrl_points = [
+            head_pos,
+            head_pos + step_y + head.dir * head.multiplier * ARC_CTRL_DIST + anchor,
+            tail_pos - step_y - tail.dir * tail.multiplier * ARC_CTRL_DIST + anchor,
+            tail_pos,
+        ].map(|ctrl_point| arc_info.lane_rot * ctrl_point);
+
+        arc_info.ribbon.set_ctrl_points(&ctrl_points);
+
+        UpdateResult::Keep
+    }
+}
+
 struct GameStats {
     changed: bool,
+    in_wall: bool,
+    feedback_opt: Option<CubeOutcome>, // The last failed note
+    no_fail: bool,
+    multiplier: Multiplier,
+    max_multiplier: Multiplier, // Multiplier of a perfect play
+    replay_notes: Vec<ReplayNote>, // The events of the notes and the walls are recorded for the replay
+    replay_walls: Vec<ReplayWall>,
     inner: GameStatsInner,
 }
 
 #[derive(Copy, Clone)]
-struct GameStatsInner {
+pub struct GameStatsInner {
     count: u32,
     total: u32,
+    bomb_hits: u32,
+    wall_hits: u32,
+    misses: u32,
+    bad_cuts_wrong_color: u32,
+    bad_cuts_wrong_dir: u32,
+    bad_cuts_too_slow: u32,
+    score: u32,
+    max_score: u32, // Of the whole beatmap
+    rated_max_score: u32, // Of the notes, whose score is given
+    combo: u32,
+    max_combo: u32,
+    multiplier: u32,
+    energy: f32,
+    no_fail_used: bool, // No-Fail has kept the song going, since the energy has been depleted (until reset)
+}
+
+// Multipliers at the time of the cut, the score is given after the rating of the swing.
+#[derive(Copy, Clone)]
+struct CutMultipliers {
+    multiplier: u32,
+    max_multiplier: u32,
 }
 
 impl GameStats {
-    fn new(total: u32) -> Self {
+    fn new(total: u32, max_score: u32, no_fail: bool) -> Self {
         let inner = GameStatsInner {
             count: 0,
             total,
+            bomb_hits: 0,
+            wall_hits: 0,
+            misses: 0,
+            bad_cuts_wrong_color: 0,
+            bad_cuts_wrong_dir: 0,
+            bad_cuts_too_slow: 0,
+            score: 0,
+            max_score,
+            rated_max_score: 0,
+            combo: 0,
+            max_combo: 0,
+            multiplier: 1,
+            energy: ENERGY_START,
+            no_fail_used: false,
         };
 
         Self {
             changed: true, // Force change on first update.
+            in_wall: false,
+            feedback_opt: None,
+            no_fail,
+            multiplier: Multiplier::new(),
+            max_multiplier: Multiplier::new(),
+            replay_notes: Vec::new(),
+            replay_walls: Vec::new(),
             inner,
         }
     }
@@ -807,11 +2740,138 @@ impl GameStats {
         self.inner
     }
 
-    fn inc_count(&mut self) {
+    fn reset(&mut self) {
+        *self = Self::new(self.inner.total, self.inner.max_score, self.no_fail);
+    }
+
+    fn cut(&mut self) -> CutMultipliers {
+        // The cut, which raises the multiplier, is already scored with the raised one.
+
+        self.multiplier.hit();
+        self.max_multiplier.hit();
+
+        let multipliers = CutMultipliers {
+            multiplier: self.multiplier.get(),
+            max_multiplier: self.max_multiplier.get(),
+        };
+
         self.inner.count += 1;
+        self.inner.combo += 1;
+        self.inner.max_combo = self.inner.max_combo.max(self.inner.combo);
+        self.inner.multiplier = self.multiplier.get();
+        self.change_energy(ENERGY_CUT);
+
+        multipliers
+    }
+
+    fn add_score(&mut self, score: u32, max_score: u32, multipliers: CutMultipliers) {
+        self.inner.score += score * multipliers.multiplier;
+        self.inner.rated_max_score += max_score * multipliers.max_multiplier;
+        self.changed()
+    }
+
+    fn miss(&mut self, max_score: u32, energy: f32) {
+        self.inner.misses += 1;
+        self.change_energy(energy);
+        self.fail(CubeOutcome::Miss, max_score);
+    }
+
+    fn bad_cut(&mut self, reason: BadCutReason, max_score: u32) {
+        match reason {
+            BadCutReason::WrongColor => self.inner.bad_cuts_wrong_color += 1,
+            BadCutReason::WrongDir => self.inner.bad_cuts_wrong_dir += 1,
+            BadCutReason::TooSlow => self.inner.bad_cuts_too_slow += 1,
+        }
+
+        self.change_energy(ENERGY_BAD_CUT);
+        self.fail(CubeOutcome::BadCut(reason), max_score);
+    }
+
+    fn fail(&mut self, outcome: CubeOutcome, max_score: u32) {
+        // The note is rated with no score.
+
+        self.max_multiplier.hit();
+        self.inner.rated_max_score += max_score * self.max_multiplier.get();
+
+        self.feedback_opt = Some(outcome);
+        self.break_combo();
+    }
+
+    fn break_combo(&mut self) {
+        self.multiplier.miss();
+
+        self.inner.combo = 0;
+        self.inner.multiplier = self.multiplier.get();
+        self.changed()
+    }
+
+    fn inc_bomb_hits(&mut self) {
+        self.inner.bomb_hits += 1;
+        self.change_energy(ENERGY_BOMB_HIT);
+    }
+
+    fn inc_wall_hits(&mut self) {
+        self.inner.wall_hits += 1;
+        self.changed()
+    }
+
+    fn drain_wall(&mut self, ts_diff: f32) {
+        self.change_energy(ENERGY_WALL * ts_diff);
+    }
+
+    fn change_energy(&mut self, energy: f32) {
+        self.inner.energy = (self.inner.energy + energy).clamp(0.0, 1.0);
+
+        if self.no_fail && self.inner.energy <= 0.0 {
+            self.inner.no_fail_used = true;
+        }
+
         self.changed()
     }
 
+    fn is_failed(&self) -> bool {
+        !self.no_fail && self.inner.energy <= 0.0
+    }
+
+    // The in_wall flag is collected during a single update of the objects.
+
+    fn set_in_wall(&mut self) {
+        self.in_wall = true;
+    }
+
+    fn take_in_wall(&mut self) -> bool {
+        let in_wall = self.in_wall;
+        self.in_wall = false;
+        in_wall
+    }
+
+    fn take_feedback_opt(&mut self) -> Option<CubeOutcome> {
+        self.feedback_opt.take()
+    }
+
+    fn record_note(&mut self, note_id: i32, event_time: f32, spawn_time: f32, event_type: ReplayNoteEvent, cut_info_opt: Option<ReplayCutInfo>) {
+        self.replay_notes.push(ReplayNote {
+            note_id,
+            event_time,
+            spawn_time,
+            event_type,
+            cut_info_opt,
+        });
+    }
+
+    fn record_wall(&mut self, wall_id: i32, time: f32, spawn_time: f32) {
+        self.replay_walls.push(ReplayWall {
+            wall_id,
+            energy: self.inner.energy,
+            time,
+            spawn_time,
+        });
+    }
+
+    fn take_replay_events(&mut self) -> (Vec<ReplayNote>, Vec<ReplayWall>) {
+        (std::mem::take(&mut self.replay_notes), std::mem::take(&mut self.replay_walls))
+    }
+
     fn changed(&mut self) {
         self.changed = true;
     }
@@ -822,3 +2882,75 @@ impl GameStats {
         changed
     }
 }
+
+impl GameStatsInner {
+    pub fn get_count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn get_total(&self) -> u32 {
+        self.total
+    }
+
+    pub fn get_bomb_hits(&self) -> u32 {
+        self.bomb_hits
+    }
+
+    pub fn get_wall_hits(&self) -> u32 {
+        self.wall_hits
+    }
+
+    pub fn get_misses(&self) -> u32 {
+        self.misses
+    }
+
+    pub fn get_bad_cuts_wrong_color(&self) -> u32 {
+        self.bad_cuts_wrong_color
+    }
+
+    pub fn get_bad_cuts_wrong_dir(&self) -> u32 {
+        self.bad_cuts_wrong_dir
+    }
+
+    pub fn get_bad_cuts_too_slow(&self) -> u32 {
+        self.bad_cuts_too_slow
+    }
+
+    pub fn get_score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn get_max_score(&self) -> u32 {
+        self.max_score
+    }
+
+    pub fn get_combo(&self) -> u32 {
+        self.combo
+    }
+
+    pub fn get_max_combo(&self) -> u32 {
+        self.max_combo
+    }
+
+    pub fn get_multiplier(&self) -> u32 {
+        self.multiplier
+    }
+
+    pub fn get_energy(&self) -> f32 {
+        self.energy
+    }
+
+    pub fn is_no_fail_used(&self) -> bool {
+        self.no_fail_used
+    }
+
+    pub fn calc_percentage(&self) -> f32 {
+        // Relative to the maximum score of the notes so far.
+
+        if self.rated_max_score > 0 {
+            100.0 * self.score as f32 / self.rated_max_score as f32
+        } else {
+            100.0
+        }
+    }
+}
@@ -10,17 +10,19 @@ use crate::asset::{AssetFileBox, AssetManagerRc};
 use crate::audio::{AudioEngineRc, AudioFader, AudioFaderHandle, AudioFile, AudioFileHandle};
 use crate::mailbox::{self, Receiver, TryRecvError};
 use crate::model::*;
-use crate::net::{AssetFileRequest, BeatSaverSearchRequest, ImageRequest, NetManager, SongZipRequest};
+use crate::net::{AssetFileRequest, BeatSaverSearchRequest, ImageRequest, ImageResponse, NetManager, SongZipRequest};
 use crate::output::OutputInfoRc;
-use crate::scene::{GameParam, Scene, SceneFactory, SceneInput, SceneManager, create_floor, create_saber, create_stats_window};
-use crate::songdef::{CHAR_STANDARD, SongDifficulty};
-use crate::songinfo::{SongInfo, ColorScheme};
+use crate::scene::{GameParam, PracticeParam, ReplayParam, Scene, SceneFactory, SceneInput, SceneManager, create_floor, create_pointer, create_saber, create_stats_window};
+use crate::songdef::{self, SongCharacteristic, SongDifficulty};
+use crate::songinfo::{BeatmapAnalysis, BeatmapInfo, ColorScheme, NoteType, Parity, SongInfo, SwingStatus, Timing};
 use crate::ui::{AboutWindow, PoweredByWindow, SearchWindow, SearchWindowItem, SearchWindowMode, UILoop, VirtualKeyboardWindow};
 use crate::ui::slintimpl::{self, ComponentHandle as slintimpl_ComponentHandle, Model as slintimpl_Model, WindowUtil as slintimpl_WindowUtil};
-use crate::util::StatsRc;
+use crate::util::{SettingsRc, StatsRc};
 
-const POINTER_COLOR: Color = Color([0.4, 0.4, 0.4]);
 const FADE_RATE: u8 = 80; // [dB/s]
+const REACTION_TIMES: [Option<f32>; 6] = [None, Some(0.4), Some(0.5), Some(0.6), Some(0.8), Some(1.0)]; // None: use the reaction time of the beatmap [s]
+const OVERVIEW_PARITY_ERROR_COUNT: usize = 5;
+const PRACTICE_SPEEDS: [f32; 9] = [0.5, 0.6, 0.7, 0.8, 0.9, 1.0, 1.1, 1.2, 1.5];
 
 pub struct MenuParam;
 
@@ -36,8 +38,8 @@ impl SceneFactory for MenuParam {
     type Scene = Menu;
     type Error = ();
 
-    fn load(self, asset_mgr: AssetManagerRc, model_reg: &mut ModelRegistry, output_info: OutputInfoRc, stats: StatsRc, audio_engine: AudioEngineRc, ui_loop: &UILoop, net_manager: &NetManager) -> Result<Self::Scene, Self::Error> {
-        Menu::new(self, asset_mgr, model_reg, output_info, stats, audio_engine, ui_loop, net_manager)
+    fn load(self, asset_mgr: AssetManagerRc, model_reg: &mut ModelRegistry, output_info: OutputInfoRc, stats: StatsRc, settings: SettingsRc, audio_engine: AudioEngineRc, ui_loop: &UILoop, net_manager: &NetManager) -> Result<Self::Scene, Self::Error> {
+        Menu::new(self, asset_mgr, model_reg, output_info, stats, settings, audio_engine, ui_loop, net_manager)
     }
 }
 
@@ -66,7 +68,8 @@ struct Inner {
 enum SearchMessage {
     PreviewStart(AssetFileBox, usize),
     PreviewStop,
-    GameStart(AssetManagerRc, SongInfo, usize),
+    GameStart(AssetManagerRc, Box<SongInfo>, usize, Option<PracticeParam>),
+    ReplayStart(AssetManagerRc, Box<SongInfo>, usize), // The last replay of the beatmap is played
     #[cfg(feature = "test")]
     TestStart,
 }
@@ -74,6 +77,13 @@ enum SearchMessage {
 struct SearchState {
     active_info_opt: Option<ActiveInfo>,
     preview_serial: usize,
+    pending_game_opt: Option<PendingGame>, // Downloaded song, shown in the overview until started
+}
+
+struct PendingGame {
+    asset_mgr: AssetManagerRc,
+    song_info: Box<SongInfo>,
+    beatmap_info_index: usize,
 }
 
 struct ActiveInfo {
@@ -106,7 +116,7 @@ enum UpdateItemOp {
 
 impl Menu {
     #[allow(clippy::too_many_arguments)]
-    fn new(_param: MenuParam, asset_mgr: AssetManagerRc, model_reg: &mut ModelRegistry, output_info: OutputInfoRc, stats: StatsRc, audio_engine: AudioEngineRc, ui_loop: &UILoop, net_manager: &NetManager) -> Result<Self, ()> {
+    fn new(_param: MenuParam, asset_mgr: AssetManagerRc, model_reg: &mut ModelRegistry, output_info: OutputInfoRc, stats: StatsRc, settings: SettingsRc, audio_engine: AudioEngineRc, ui_loop: &UILoop, net_manager: &NetManager) -> Result<Self, ()> {
         // Implementation notes:
         // - Weak window references in event handlers (on_*):
         //   - If a weak reference to its parent window is unwrapped (window_weak.unwrap()),
@@ -210,6 +220,7 @@ impl Menu {
         let search_window_state_mutex = Arc::new(Mutex::new(SearchState {
             active_info_opt: None,
             preview_serial: 0,
+            pending_game_opt: None,
         }));
 
         let window_param = WindowParam::new(1200, 750, {
@@ -221,6 +232,73 @@ impl Menu {
                 let window = SearchWindow::new().unwrap();
                 window.set_items(slintimpl::ModelRc::new(slintimpl::VecModel::default()));
 
+                // Setup reaction time selector, it is stored in the settings.
+
+                let reaction_time_strs: Vec<_> = REACTION_TIMES.iter().map(|reaction_time_opt| {
+                    match reaction_time_opt {
+                        Some(reaction_time) => format!("{:.0} ms", reaction_time * 1000.0),
+                        None => "Beatmap".to_string(),
+                    }.into()
+                }).collect();
+                let reaction_time_strs_model = slintimpl::VecModel::default();
+                reaction_time_strs_model.set_vec(reaction_time_strs);
+
+                let reaction_time_index = REACTION_TIMES.iter().position(|reaction_time_opt| *reaction_time_opt == settings.get_inner().reaction_time_opt).unwrap_or(0);
+
+                window.set_reaction_time_strs(slintimpl::ModelRc::new(reaction_time_strs_model));
+                window.set_reaction_time_index(reaction_time_index.try_into().unwrap());
+
+                window.on_change_reaction_time({
+                    let window_weak = window.as_weak();
+                    let settings = Arc::clone(&settings);
+
+                    move || {
+                        let window = window_weak.unwrap();
+                        let reaction_time_index: usize = window.get_reaction_time_index().try_into().unwrap();
+
+                        settings.set_reaction_time_opt(REACTION_TIMES[reaction_time_index]);
+                    }
+                });
+
+                // Setup custom data switch, it is stored in the settings as well.
+
+                window.set_custom_data(settings.get_inner().custom_data);
+
+                window.on_change_custom_data({
+                    let window_weak = window.as_weak();
+                    let settings = Arc::clone(&settings);
+
+                    move || {
+                        let window = window_weak.unwrap();
+                        settings.set_custom_data(window.get_custom_data());
+                    }
+                });
+
+                // Setup No-Fail switch, it is stored in the settings as well.
+
+                window.set_no_fail(settings.get_inner().no_fail);
+
+                window.on_change_no_fail({
+                    let window_weak = window.as_weak();
+                    let settings = Arc::clone(&settings);
+
+                    move || {
+                        let window = window_weak.unwrap();
+                        settings.set_no_fail(window.get_no_fail());
+                    }
+                });
+
+                // Setup practice mode, it is only used for the next game (see get_practice_opt).
+
+                let practice_speed_strs: Vec<_> = PRACTICE_SPEEDS.iter().map(|speed| format!("{:.0}%", speed * 100.0).into()).collect();
+                let practice_speed_strs_model = slintimpl::VecModel::default();
+                practice_speed_strs_model.set_vec(practice_speed_strs);
+
+                let practice_speed_index = PRACTICE_SPEEDS.iter().position(|speed| *speed == 1.0).unwrap();
+
+                window.set_practice_speed_strs(slintimpl::ModelRc::new(practice_speed_strs_model));
+                window.set_practice_speed_index(practice_speed_index.try_into().unwrap());
+
                 let handles_mutex = Arc::new(Mutex::new(Vec::new()));
 
                 // Construct search method:
@@ -285,39 +363,53 @@ impl Menu {
                                             let mut handles = handles_mutex.lock().unwrap();
 
                                             for (item_index, (song, version)) in songs.iter().filter_map(|song| song.get_published_version().map(|version| (song, version))).enumerate() {
-                                                // Map difficulties.
+                                                // Map variants and characteristics, the difficulties are set
+                                                // for the selected characteristic (see set_item_characteristic).
 
-                                                let mut difficulties: Box<_> = version.get_variants().iter().filter_map(|variant| {
-                                                    if variant.get_characteristic() == CHAR_STANDARD {
-                                                        Some(variant.get_difficulty())
-                                                    } else {
-                                                        None
-                                                    }
+                                                let variants = version.get_variants();
+
+                                                let variant_characteristic_ints: Vec<i32> = variants.iter().map(|variant| variant.get_characteristic().into()).collect();
+                                                let variant_characteristic_ints_model = slintimpl::VecModel::default();
+                                                variant_characteristic_ints_model.set_vec(variant_characteristic_ints);
+
+                                                let variant_difficulty_ints: Vec<i32> = variants.iter().map(|variant| variant.get_difficulty().into()).collect();
+                                                let variant_difficulty_ints_model = slintimpl::VecModel::default();
+                                                variant_difficulty_ints_model.set_vec(variant_difficulty_ints);
+
+                                                let custom_data = window.get_custom_data();
+                                                let variant_unsupported_strs: Vec<_> = variants.iter().map(|variant| {
+                                                    variant.get_mods().into_iter().filter(|name| !songdef::is_requirement_supported(name, custom_data)).map(Self::get_mod_abbr).collect::<Vec<_>>().join(", ").into()
                                                 }).collect();
-                                                difficulties.sort();
-
-                                                let difficulty_ints: Vec<_> = difficulties.iter().map(|difficulty| (*difficulty).into()).collect();
-                                                let difficulty_ints_model = slintimpl::VecModel::default();
-                                                difficulty_ints_model.set_vec(difficulty_ints);
-
-                                                let difficulty_strs: Vec<_> = difficulties.iter().map(|difficulty| {
-                                                    match difficulty {
-                                                        SongDifficulty::Easy => "Easy",
-                                                        SongDifficulty::Normal => "Normal",
-                                                        SongDifficulty::Hard => "Hard",
-                                                        SongDifficulty::Expert => "Expert",
-                                                        SongDifficulty::ExpertPlus => "Expert Plus",
+                                                let variant_unsupported_strs_model = slintimpl::VecModel::default();
+                                                variant_unsupported_strs_model.set_vec(variant_unsupported_strs);
+
+                                                let variant_stats_strs: Vec<_> = variants.iter().map(|variant| {
+                                                    match variant.get_parity_summary() {
+                                                        Some(parity_summary) => format!("NPS: {:.2}, parity errors: {}, warnings: {}, resets: {}", variant.get_nps(), parity_summary.get_errors(), parity_summary.get_warns(), parity_summary.get_resets()),
+                                                        None => format!("NPS: {:.2}", variant.get_nps()),
                                                     }.into()
                                                 }).collect();
-                                                let difficulty_strs_model = slintimpl::VecModel::default();
-                                                difficulty_strs_model.set_vec(difficulty_strs);
+                                                let variant_stats_strs_model = slintimpl::VecModel::default();
+                                                variant_stats_strs_model.set_vec(variant_stats_strs);
+
+                                                let mut characteristics: Vec<_> = variants.iter().map(|variant| variant.get_characteristic()).filter(|characteristic| *characteristic != SongCharacteristic::Unknown).collect();
+                                                characteristics.sort();
+                                                characteristics.dedup();
+
+                                                let characteristic_ints: Vec<_> = characteristics.iter().map(|characteristic| (*characteristic).into()).collect();
+                                                let characteristic_ints_model = slintimpl::VecModel::default();
+                                                characteristic_ints_model.set_vec(characteristic_ints);
+
+                                                let characteristic_strs: Vec<_> = characteristics.iter().map(|characteristic| Self::get_characteristic_str(*characteristic).into()).collect();
+                                                let characteristic_strs_model = slintimpl::VecModel::default();
+                                                characteristic_strs_model.set_vec(characteristic_strs);
 
                                                 // Create item.
 
                                                 let metadata = song.get_metadata();
                                                 let duration = metadata.get_duration();
 
-                                                let item = SearchWindowItem {
+                                                let mut item = SearchWindowItem {
                                                     name: song.get_name().into(),
                                                     uploader_name: song.get_uploader().get_name().into(),
                                                     cover_img: empty_img.clone(),
@@ -326,12 +418,22 @@ impl Menu {
                                                     score: format!("{:.2}", song.get_stats().get_score() * 100.0).into(),
                                                     preview_url: version.get_preview_url().as_ref().into(),
                                                     download_url: version.get_download_url().as_ref().into(),
-                                                    difficulty_ints: slintimpl::ModelRc::new(difficulty_ints_model),
-                                                    difficulty_strs: slintimpl::ModelRc::new(difficulty_strs_model),
+                                                    hash: version.get_hash().into(),
+                                                    variant_characteristic_ints: slintimpl::ModelRc::new(variant_characteristic_ints_model),
+                                                    variant_difficulty_ints: slintimpl::ModelRc::new(variant_difficulty_ints_model),
+                                                    variant_unsupported_strs: slintimpl::ModelRc::new(variant_unsupported_strs_model),
+                                                    variant_stats_strs: slintimpl::ModelRc::new(variant_stats_strs_model),
+                                                    characteristic_ints: slintimpl::ModelRc::new(characteristic_ints_model),
+                                                    characteristic_strs: slintimpl::ModelRc::new(characteristic_strs_model),
+                                                    difficulty_ints: slintimpl::ModelRc::default(),
+                                                    difficulty_strs: slintimpl::ModelRc::default(),
+                                                    difficulty_stats_strs: slintimpl::ModelRc::default(),
                                                     active: false,
                                                     preview_active: false,
                                                 };
 
+                                                Self::set_item_characteristic(&mut item, 0);
+
                                                 items.push(item);
 
                                                 // Submit cover image fetch.
@@ -341,24 +443,8 @@ impl Menu {
 
                                                     move |r| {
                                                         if let Ok(img_raw) = r {
-                                                            let width = img_raw.get_width();
-                                                            let height = img_raw.get_height();
-
-                                                            if width > 0 && height > 0 {
-                                                                let mut buf = slintimpl::SharedPixelBuffer::<slintimpl::Rgba8Pixel>::new(width, height);
-                                                                let buf_raw = buf.make_mut_bytes();
-                                                                buf_raw.copy_from_slice(img_raw.get_data());
-                                                                let img = slintimpl::Image::from_rgba8(buf);
-
-                                                                // Update model.
-
-                                                                let window = window_weak.unwrap();
-                                                                let model = window.get_items();
-
-                                                                let mut item = model.row_data(item_index).expect("Item expected");
-                                                                item.cover_img = img;
-                                                                model.set_row_data(item_index, item);
-                                                            }
+                                                            let window = window_weak.unwrap();
+                                                            Self::set_item_cover(&window, item_index, &img_raw);
                                                         }
                                                     }
                                                 });
@@ -500,13 +586,17 @@ impl Menu {
                         // TODO: use window->detail_item instead of active_info.item_index?
 
                         let model = window.get_items();
+                        let mut characteristic_int_active_opt = None;
                         let mut difficulty_int_active_opt = None;
 
                         if let Some(active_info) = &search_window_state.active_info_opt {
+                            let characteristic_index = window.get_characteristic_index();
                             let difficulty_index = window.get_difficulty_index();
                             let item = model.row_data(active_info.item_index).expect("Item expected");
                             let difficulty_ints: Box<_> = item.difficulty_ints.iter().collect();
 
+                            characteristic_int_active_opt = item.characteristic_ints.row_data(characteristic_index as usize);
+
                             // If the active item doesn't have any difficulty (difficulty_ints), then
                             // difficulty_index is still set. Check if we have at least one difficulty.
 
@@ -526,21 +616,22 @@ impl Menu {
 
                         Self::update_item(&window, &mut search_window_state, UpdateItemOp::Active(item_index_selected));
 
-                        // Set detail.
+                        // Set detail, keep the characteristic and the difficulty of the previous item (if possible).
 
-                        let item = model.row_data(item_index_selected).expect("Item expected");
+                        let mut item = model.row_data(item_index_selected).expect("Item expected");
                         let preview_url: String = item.preview_url.clone().into();
-                        let difficulty_ints: Box<_> = item.difficulty_ints.iter().collect();
 
-                        window.set_show_detail(true);
-                        window.set_detail_item(item);
-                        window.set_detail_message("".into());
+                        let characteristic_index = characteristic_int_active_opt.and_then(|characteristic_int_active| {
+                            item.characteristic_ints.iter().position(|characteristic_int| characteristic_int == characteristic_int_active)
+                        }).unwrap_or(0);
 
-                        let difficulty_index = difficulty_int_active_opt.map_or(0, |difficulty_int_active| {
-                            difficulty_ints.iter().position(|difficulty_int| *difficulty_int == difficulty_int_active).unwrap_or(0)
-                        });
+                        Self::set_item_characteristic(&mut item, characteristic_index);
+                        model.set_row_data(item_index_selected, item.clone());
 
-                        window.set_difficulty_index(difficulty_index.try_into().unwrap());
+                        window.set_show_detail(true);
+                        window.set_detail_message("".into());
+                        window.set_characteristic_index(characteristic_index.try_into().unwrap());
+                        Self::set_detail_item(&window, item, difficulty_int_active_opt);
 
                         // Submit audio preview fetch.
 
@@ -569,8 +660,29 @@ impl Menu {
                     }
                 });
 
+                window.on_change_characteristic({
+                    let search_window_state_mutex = Arc::clone(&search_window_state_mutex);
+                    let window_weak = window.as_weak();
+
+                    move || {
+                        let window = window_weak.unwrap();
+                        let search_window_state = search_window_state_mutex.lock().unwrap();
+                        let active_info = search_window_state.active_info_opt.as_ref().expect("Active expected");
+
+                        // Keep the difficulty, if the new characteristic has it as well.
+
+                        let model = window.get_items();
+                        let mut item = model.row_data(active_info.item_index).expect("Item expected");
+                        let difficulty_int_active_opt = item.difficulty_ints.row_data(window.get_difficulty_index() as usize);
+
+                        Self::set_item_characteristic(&mut item, window.get_characteristic_index() as usize);
+                        model.set_row_data(active_info.item_index, item.clone());
+
+                        Self::set_detail_item(&window, item, difficulty_int_active_opt);
+                    }
+                });
+
                 window.on_play({
-                    let search_window_tx = search_window_tx.clone();
                     let net_manager_exec = net_manager_exec.clone();
                     let search_window_state_mutex = Arc::clone(&search_window_state_mutex);
                     let window_weak = window.as_weak();
@@ -588,9 +700,10 @@ impl Menu {
                         let model = window.get_items();
                         let item = model.row_data(item_index).expect("Item expected");
 
-                        let difficulty_index = window.get_difficulty_index();
-                        let difficulty_ints: Box<_> = item.difficulty_ints.iter().collect();
-                        let difficulty_int = difficulty_ints[difficulty_index as usize];
+                        let characteristic_int = item.characteristic_ints.row_data(window.get_characteristic_index() as usize).expect("Characteristic expected");
+                        let characteristic: SongCharacteristic = characteristic_int.try_into().unwrap();
+
+                        let difficulty_int = item.difficulty_ints.row_data(window.get_difficulty_index() as usize).expect("Difficulty expected");
                         let difficulty: SongDifficulty = difficulty_int.try_into().unwrap();
 
                         window.set_mode(SearchWindowMode::Message);
@@ -602,9 +715,10 @@ impl Menu {
 
                         let download_url: String = item.download_url.clone().into();
                         let url = Url::parse(&download_url).expect("Invalid url");
+                        let hash: String = item.hash.clone().into();
 
                         let handle = net_manager_exec.submit(SongZipRequest::new(url), { // TODO: cache?
-                            let search_window_tx = search_window_tx.clone();
+                            let search_window_state_mutex = Arc::clone(&search_window_state_mutex);
                             let window_weak = window_weak.clone();
                             let set_input_enabled = Arc::clone(&set_input_enabled);
 
@@ -616,16 +730,49 @@ impl Menu {
                                         // TODO: On which thread should we do the processing of the song data?
                                         match SongInfo::load(Arc::clone(&asset_mgr)) {
                                             Ok(song_info) => {
+                                                // Prefer the cover embedded in the song.
+
+                                                if let Ok(Some(buf)) = song_info.load_cover() && let Ok(img_raw) = ImageResponse::decode(&buf) {
+                                                    let window = window_weak.unwrap();
+                                                    Self::set_item_cover(&window, item_index, &img_raw);
+                                                }
+
                                                 let beatmap_infos = song_info.get_beatmap_infos();
                                                 
-                                                if let Some(beatmap_info_index) = beatmap_infos.iter().position(|beatmap_info| beatmap_info.get_characteristic() == CHAR_STANDARD && beatmap_info.get_difficulty() == difficulty) {
-                                                    search_window_tx.send(SearchMessage::GameStart(asset_mgr, song_info, beatmap_info_index)).unwrap();
+                                                if let Some(beatmap_info_index) = beatmap_infos.iter().position(|beatmap_info| beatmap_info.get_characteristic() == characteristic && beatmap_info.get_difficulty() == difficulty) {
+                                                    // Check the song against the hash of BeatSaver, mismatch is only reported.
+
+                                                    let hash_str = match song_info.calc_hash() {
+                                                        Ok(song_hash) if song_hash.eq_ignore_ascii_case(&hash) => format!("Hash: {}", song_hash),
+                                                        Ok(song_hash) => format!("Hash: {} (BeatSaver: {})", song_hash, hash),
+                                                        Err(e) => format!("Unable to calculate hash: {}", e),
+                                                    };
+
+                                                    // Show the overview, the game is started from there.
+
+                                                    match Self::analyze_beatmap(&song_info, &beatmap_infos[beatmap_info_index]) {
+                                                        Ok(analysis) => {
+                                                            let window = window_weak.unwrap();
+                                                            Self::set_overview(&window, &song_info, &beatmap_infos[beatmap_info_index], &analysis, &hash_str);
+
+                                                            search_window_state_mutex.lock().unwrap().pending_game_opt = Some(PendingGame {
+                                                                asset_mgr,
+                                                                song_info: Box::new(song_info),
+                                                                beatmap_info_index,
+                                                            });
+
+                                                            set_input_enabled(true);
+                                                        },
+                                                        Err(e) => {
+                                                            e_opt = Some(e);
+                                                        },
+                                                    }
                                                 } else {
                                                     e_opt = Some("No such characteristic/difficulty".to_string());
                                                 }
                                             },
                                             Err(e) => {
-                                                e_opt = Some(format!("Unable to load song: {:?}", e)); // TODO: instead of debug, use display trait for formatting error msg?
+                                                e_opt = Some(format!("Unable to load song: {}", e));
                                             },
                                         }
                                     },
@@ -649,8 +796,72 @@ impl Menu {
                     }
                 });
 
+                window.on_start_game({
+                    let search_window_tx = search_window_tx.clone();
+                    let search_window_state_mutex = Arc::clone(&search_window_state_mutex);
+                    let window_weak = window.as_weak();
+                    let set_input_enabled = Arc::clone(&set_input_enabled);
+
+                    move || {
+                        let window = window_weak.unwrap();
+
+                        // The game is not started with invalid practice settings.
+
+                        let practice_opt = match Self::get_practice_opt(&window) {
+                            Ok(practice_opt) => practice_opt,
+                            Err(e) => {
+                                window.set_detail_message(e.into());
+                                return;
+                            },
+                        };
+
+                        let pending_game_opt = search_window_state_mutex.lock().unwrap().pending_game_opt.take();
+
+                        if let Some(pending_game) = pending_game_opt {
+                            window.set_mode(SearchWindowMode::Message);
+                            window.set_message("Loading...".into());
+
+                            set_input_enabled(false);
+
+                            search_window_tx.send(SearchMessage::GameStart(pending_game.asset_mgr, pending_game.song_info, pending_game.beatmap_info_index, practice_opt)).unwrap();
+                        }
+                    }
+                });
+
+                window.on_watch_replay({
+                    let search_window_tx = search_window_tx.clone();
+                    let search_window_state_mutex = Arc::clone(&search_window_state_mutex);
+                    let window_weak = window.as_weak();
+                    let set_input_enabled = Arc::clone(&set_input_enabled);
+
+                    move || {
+                        let window = window_weak.unwrap();
+                        let pending_game_opt = search_window_state_mutex.lock().unwrap().pending_game_opt.take();
+
+                        if let Some(pending_game) = pending_game_opt {
+                            window.set_mode(SearchWindowMode::Message);
+                            window.set_message("Loading...".into());
+
+                            set_input_enabled(false);
+
+                            search_window_tx.send(SearchMessage::ReplayStart(pending_game.asset_mgr, pending_game.song_info, pending_game.beatmap_info_index)).unwrap();
+                        }
+                    }
+                });
+
+                window.on_close_overview({
+                    let search_window_state_mutex = Arc::clone(&search_window_state_mutex);
+                    let window_weak = window.as_weak();
+
+                    move || {
+                        let window = window_weak.unwrap();
+                        search_window_state_mutex.lock().unwrap().pending_game_opt = None;
+
+                        window.set_mode(SearchWindowMode::Item);
+                    }
+                });
+
                 // Setup test, if configured.
-                // TODO: Add support for https://github.com/BeatLeader/BS-Open-Replay ?
 
                 #[cfg(feature = "test")]
                 {
@@ -703,8 +914,7 @@ impl Menu {
 
         // Setup pointer.
 
-        let pointer_param = PointerParam::new(&POINTER_COLOR);
-        let pointer = model_reg.create(pointer_param);
+        let pointer = create_pointer(model_reg);
         
         let inner = Inner {
             audio_info_opt: None,
@@ -729,6 +939,219 @@ impl Menu {
         })
     }
 
+    fn get_practice_opt(window: &SearchWindow) -> Result<Option<PracticeParam>, String> {
+        if !window.get_practice() {
+            return Ok(None);
+        }
+
+        // The loop is ignored, if it is empty.
+
+        let start = window.get_practice_start() as f32;
+        let loop_start = window.get_practice_loop_start() as f32;
+        let loop_end = window.get_practice_loop_end() as f32;
+        let loop_opt = (window.get_practice_loop() && loop_start < loop_end).then_some((loop_start, loop_end));
+
+        let speed_index: usize = window.get_practice_speed_index().try_into().unwrap();
+
+        PracticeParam::new(start, loop_opt, PRACTICE_SPEEDS[speed_index]).map(Some)
+    }
+
+    fn show_start_error(&self, e: String) {
+        self.ui_loop.add_callback({
+            let vkbd_window_weak = self.vkbd_window.as_weak::<VirtualKeyboardWindow>();
+            let search_window_weak = self.search_window.as_weak::<SearchWindow>();
+
+            move || {
+                let vkbd_window_opt = vkbd_window_weak.upgrade();
+                let search_window_opt = search_window_weak.upgrade();
+                if vkbd_window_opt.is_none() || search_window_opt.is_none() {
+                    return;
+                }
+                let vkbd_window = vkbd_window_opt.unwrap();
+                let search_window = search_window_opt.unwrap();
+
+                search_window.set_mode(SearchWindowMode::Item);
+                search_window.set_detail_message(e.into());
+
+                // TODO: Refactor to use a single set_input_enabled implementation.
+                vkbd_window.set_input_enabled(true);
+                search_window.set_input_enabled(true);
+            }
+        });
+    }
+
+    fn set_item_cover(window: &SearchWindow, item_index: usize, img_raw: &ImageResponse) {
+        let width = img_raw.get_width();
+        let height = img_raw.get_height();
+
+        if width > 0 && height > 0 {
+            let mut buf = slintimpl::SharedPixelBuffer::<slintimpl::Rgba8Pixel>::new(width, height);
+            let buf_raw = buf.make_mut_bytes();
+            buf_raw.copy_from_slice(img_raw.get_data());
+            let img = slintimpl::Image::from_rgba8(buf);
+
+            // Update model.
+
+            let model = window.get_items();
+
+            let mut item = model.row_data(item_index).expect("Item expected");
+            item.cover_img = img;
+            model.set_row_data(item_index, item);
+        }
+    }
+
+    fn set_item_characteristic(item: &mut SearchWindowItem, characteristic_index: usize) {
+        // Collect the difficulties of the variants with the given characteristic.
+
+        let characteristic_int_opt = item.characteristic_ints.row_data(characteristic_index);
+
+        let variants = item.variant_characteristic_ints.iter().zip(item.variant_difficulty_ints.iter()).zip(item.variant_unsupported_strs.iter()).zip(item.variant_stats_strs.iter());
+
+        let mut difficulties: Box<_> = variants.filter_map(|(((characteristic_int, difficulty_int), unsupported_str), stats_str)| {
+            if Some(characteristic_int) == characteristic_int_opt {
+                SongDifficulty::try_from(difficulty_int).ok().map(|difficulty| (difficulty, unsupported_str, stats_str))
+            } else {
+                None
+            }
+        }).collect();
+        difficulties.sort_by_key(|(difficulty, ..)| *difficulty);
+
+        let difficulty_ints: Vec<_> = difficulties.iter().map(|(difficulty, ..)| (*difficulty).into()).collect();
+        let difficulty_ints_model = slintimpl::VecModel::default();
+        difficulty_ints_model.set_vec(difficulty_ints);
+
+        // Difficulties with unsupported mods are flagged, they are refused when played.
+
+        let difficulty_strs: Vec<_> = difficulties.iter().map(|(difficulty, unsupported_str, _)| {
+            let difficulty_str = Self::get_difficulty_str(*difficulty);
+
+            if unsupported_str.is_empty() {
+                difficulty_str.into()
+            } else {
+                format!("{} ({})", difficulty_str, unsupported_str).into()
+            }
+        }).collect();
+        let difficulty_strs_model = slintimpl::VecModel::default();
+        difficulty_strs_model.set_vec(difficulty_strs);
+
+        let difficulty_stats_strs: Vec<_> = difficulties.iter().map(|(_, _, stats_str)| stats_str.clone()).collect();
+        let difficulty_stats_strs_model = slintimpl::VecModel::default();
+        difficulty_stats_strs_model.set_vec(difficulty_stats_strs);
+
+        item.difficulty_ints = slintimpl::ModelRc::new(difficulty_ints_model);
+        item.difficulty_strs = slintimpl::ModelRc::new(difficulty_strs_model);
+        item.difficulty_stats_strs = slintimpl::ModelRc::new(difficulty_stats_strs_model);
+    }
+
+    fn analyze_beatmap(song_info: &SongInfo, beatmap_info: &BeatmapInfo) -> Result<BeatmapAnalysis, String> {
+        let bpm_info = song_info.get_bpm_info().map_err(|e| format!("Unable to load bpm info: {}", e))?;
+        let beatmap = beatmap_info.load().map_err(|e| format!("Unable to load beatmap: {}", e))?;
+
+        let timing = Timing::new(&bpm_info, beatmap.get_bpm_changes()).with_offset(song_info.get_metadata().get_song_time_offset());
+
+        Ok(BeatmapAnalysis::new(&beatmap, &timing))
+    }
+
+    fn set_overview(window: &SearchWindow, song_info: &SongInfo, beatmap_info: &BeatmapInfo, analysis: &BeatmapAnalysis, hash_str: &str) {
+        let format_ts = |ts: f32| {
+            let ts = ts.max(0.0) as u32;
+            format!("{}:{:02}", ts / 60, ts % 60)
+        };
+
+        let format_swings = |note_type| {
+            let swings = analysis.get_swings(note_type);
+
+            match swings.first().map(|swing| swing.get_parity()) {
+                Some(Parity::Forehand) => format!("{} swings, starting with forehand", swings.len()),
+                Some(Parity::Backhand) => format!("{} swings, starting with backhand", swings.len()),
+                None => "no swings".to_string(),
+            }
+        };
+
+        // List the first parity errors, so that they can be checked.
+
+        let mut parity_error_tss: Vec<_> = [NoteType::Left, NoteType::Right].into_iter().flat_map(|note_type| analysis.get_swings(note_type)).filter(|swing| {
+            swing.get_status() == SwingStatus::ParityError
+        }).map(|swing| swing.get_ts()).collect();
+        parity_error_tss.sort_by(f32::total_cmp);
+
+        let mut parity_error_strs: Vec<_> = parity_error_tss.iter().take(OVERVIEW_PARITY_ERROR_COUNT).map(|ts| format_ts(*ts)).collect();
+        if parity_error_tss.len() > OVERVIEW_PARITY_ERROR_COUNT {
+            parity_error_strs.push("...".to_string());
+        }
+
+        let parity_str = if parity_error_strs.is_empty() {
+            format!("Resets: {}, parity errors: 0", analysis.get_reset_count())
+        } else {
+            format!("Resets: {}, parity errors: {} (at {})", analysis.get_reset_count(), analysis.get_parity_error_count(), parity_error_strs.join(", "))
+        };
+
+        let lines = [
+            format!("{} - {}", song_info.get_author(), song_info.get_title()),
+            format!("{}, {}", Self::get_characteristic_str(beatmap_info.get_characteristic()), Self::get_difficulty_str(beatmap_info.get_difficulty())),
+            format!("Notes: {}, duration: {}", analysis.get_note_count(), format_ts(analysis.get_duration())),
+            format!("NPS: {:.2}, peak NPS: {:.2} (at {})", analysis.get_nps(), analysis.get_peak_nps(), format_ts(analysis.get_peak_ts())),
+            format!("Left hand: {}", format_swings(NoteType::Left)),
+            format!("Right hand: {}", format_swings(NoteType::Right)),
+            parity_str,
+            format!("Estimated difficulty: {:.1} ({})", analysis.get_difficulty_rating(), Self::get_difficulty_str(analysis.get_difficulty())),
+            hash_str.to_string(),
+        ];
+
+        // The density graph is scaled to its maximum.
+
+        let density = analysis.get_density();
+        let density_max = density.iter().copied().fold(0.0, f32::max);
+        let overview_density: Vec<_> = density.iter().map(|value| if density_max > 0.0 { value / density_max } else { 0.0 }).collect();
+        let overview_density_model = slintimpl::VecModel::default();
+        overview_density_model.set_vec(overview_density);
+
+        window.set_overview_text(lines.join("\n").into());
+        window.set_overview_density(slintimpl::ModelRc::new(overview_density_model));
+        window.set_mode(SearchWindowMode::Overview);
+    }
+
+    fn set_detail_item(window: &SearchWindow, item: SearchWindowItem, difficulty_int_active_opt: Option<i32>) {
+        let difficulty_index = difficulty_int_active_opt.and_then(|difficulty_int_active| {
+            item.difficulty_ints.iter().position(|difficulty_int| difficulty_int == difficulty_int_active)
+        }).unwrap_or(0);
+
+        window.set_detail_item(item);
+        window.set_difficulty_index(difficulty_index.try_into().unwrap());
+    }
+
+    fn get_mod_abbr(name: &str) -> &str {
+        match name {
+            songdef::REQUIREMENT_MAPPING_EXTENSIONS => "ME",
+            songdef::REQUIREMENT_NOODLE_EXTENSIONS => "NE",
+            _ => name,
+        }
+    }
+
+    fn get_difficulty_str(difficulty: SongDifficulty) -> &'static str {
+        match difficulty {
+            SongDifficulty::Easy => "Easy",
+            SongDifficulty::Normal => "Normal",
+            SongDifficulty::Hard => "Hard",
+            SongDifficulty::Expert => "Expert",
+            SongDifficulty::ExpertPlus => "Expert Plus",
+        }
+    }
+
+    fn get_characteristic_str(characteristic: SongCharacteristic) -> &'static str {
+        match characteristic {
+            SongCharacteristic::Standard => "Standard",
+            SongCharacteristic::OneSaber => "One Saber",
+            SongCharacteristic::NoArrows => "No Arrows",
+            SongCharacteristic::Degree90 => "90 Degree",
+            SongCharacteristic::Degree360 => "360 Degree",
+            SongCharacteristic::Lightshow => "Lightshow",
+            SongCharacteristic::Lawless => "Lawless",
+            SongCharacteristic::Legacy => "Legacy",
+            SongCharacteristic::Unknown => "Unknown",
+        }
+    }
+
     fn update_item(window: &SearchWindow, state: &mut SearchState, op: UpdateItemOp) {
         let model = window.get_items();
 
@@ -873,29 +1296,20 @@ impl Scene for Menu {
 
                         inner.preview_info_opt = None;
                     },
-                    SearchMessage::GameStart(asset_mgr, song_info, beatmap_info_index) => {
-                        if let Err(e) = scene_mgr.load(GameParam::new(asset_mgr, song_info, beatmap_info_index, #[cfg(feature = "test")] false)) {
-                            self.ui_loop.add_callback({
-                                let vkbd_window_weak = self.vkbd_window.as_weak::<VirtualKeyboardWindow>();
-                                let search_window_weak = self.search_window.as_weak::<SearchWindow>();
-
-                                move || {
-                                    let vkbd_window_opt = vkbd_window_weak.upgrade();
-                                    let search_window_opt = search_window_weak.upgrade();
-                                    if vkbd_window_opt.is_none() || search_window_opt.is_none() {
-                                        return;
-                                    }
-                                    let vkbd_window = vkbd_window_opt.unwrap();
-                                    let search_window = search_window_opt.unwrap();
-
-                                    search_window.set_mode(SearchWindowMode::Item);
-                                    search_window.set_detail_message(e.into());
-
-                                    // TODO: Refactor to use a single set_input_enabled implementation.
-                                    vkbd_window.set_input_enabled(true);
-                                    search_window.set_input_enabled(true);
-                                }
-                            });
+                    SearchMessage::GameStart(asset_mgr, song_info, beatmap_info_index, practice_opt) => {
+                        let mut param = GameParam::new(asset_mgr, *song_info, beatmap_info_index, #[cfg(feature = "test")] false);
+
+                        if let Some(practice) = practice_opt {
+                            param = param.with_practice(practice);
+                        }
+
+                        if let Err(e) = scene_mgr.load(param) {
+                            self.show_start_error(e);
+                        }
+                    },
+                    SearchMessage::ReplayStart(asset_mgr, song_info, beatmap_info_index) => {
+                        if let Err(e) = scene_mgr.load(ReplayParam::new(asset_mgr, *song_info, beatmap_info_index)) {
+                            self.show_start_error(e);
                         }
                     },
                     #[cfg(feature = "test")]
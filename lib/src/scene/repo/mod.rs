@@ -10,28 +10,41 @@ use crate::model::*;
 use crate::ui::{StatsWindow, UILoop};
 use crate::util::StatsRc;
 
+mod environment;
+pub use environment::*;
+
 mod game;
 pub use game::*;
 
 mod menu;
 pub use menu::*;
 
+mod replay;
+pub use replay::*;
+
+mod results;
+pub use results::*;
+
 const STATS_BORDER: f32 = 0.01;
 const STATS_REFRESH: f32 = 1.0; // [s]
 
 const SABER_HANDLE_PHONG_PARAM: PhongParam = PhongParam::new(0.1, 0.2, 0.3, 64.0);
 const SABER_RAY_PHONG_PARAM: PhongParam = PhongParam::new(1.0, 0.0, 0.0, 0.0);
 
+const POINTER_COLOR: Color = Color([0.4, 0.4, 0.4]);
+
 // Convenience methods used by scenes.
 
-pub fn create_floor(model_reg: &mut ModelRegistry) {
+pub fn create_floor(model_reg: &mut ModelRegistry) -> Rc<Floor> {
     let floor_param = FloorParam::new(&COLOR_WHITE);
     let floor = model_reg.create(floor_param);
     floor.set_visible(true);
     floor.set_pos(&Vector3::new(0.0, 0.0, 0.0));
+
+    floor
 }
 
-pub fn create_stats_window(model_reg: &mut ModelRegistry, stats: StatsRc, ui_loop: &UILoop) {
+pub fn create_stats_window(model_reg: &mut ModelRegistry, stats: StatsRc, ui_loop: &UILoop) -> Rc<Window> {
     let window_param = WindowParam::new(500, 250, {
         let stats_inner = stats.get_inner();
 
@@ -46,8 +59,7 @@ pub fn create_stats_window(model_reg: &mut ModelRegistry, stats: StatsRc, ui_loo
     let window = model_reg.create(window_param);
     window.set_visible(true);
     window.set_scale(2.0 - 2.0 * STATS_BORDER, 1.0 - 2.0 * STATS_BORDER);
-    window.set_pos(&Vector3::new(0.0, 2.5, 0.001));
-    window.set_rot(&Quaternion::from_angle_x(Deg(-90.0)));
+    place_stats_window(&window, &Quaternion::new(1.0, 0.0, 0.0, 0.0));
 
     thread::spawn({
         let ui_loop = ui_loop.clone();
@@ -83,6 +95,15 @@ pub fn create_stats_window(model_reg: &mut ModelRegistry, stats: StatsRc, ui_loo
             }
         }
     });
+
+    window
+}
+
+// The stats window is lying on the floor in front of the player, rot rotates it about the origin.
+
+pub fn place_stats_window(window: &Window, rot: &Quaternion<f32>) {
+    window.set_pos(&(rot * Vector3::new(0.0, 2.5, 0.001)));
+    window.set_rot(&(rot * Quaternion::from_angle_x(Deg(-90.0))));
 }
 
 pub fn create_saber(model_reg: &mut ModelRegistry, color_l: &Color, color_r: &Color) -> (Rc<Saber>, Rc<Saber>) {
@@ -94,3 +115,8 @@ pub fn create_saber(model_reg: &mut ModelRegistry, color_l: &Color, color_r: &Co
 
     (saber_l, saber_r)
 }
+
+pub fn create_pointer(model_reg: &mut ModelRegistry) -> Rc<Pointer> {
+    let pointer_param = PointerParam::new(&POINTER_COLOR);
+    model_reg.create(pointer_param)
+}
@@ -0,0 +1,485 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+use std::rc::Rc;
+
+use cgmath::{Deg, InnerSpace, Quaternion, Rotation3, Vector3, VectorSpace};
+
+use crate::asset::AssetManagerRc;
+use crate::audio::AudioEngineRc;
+use crate::bsor::{self, Replay, ReplayNote, ReplayNoteEvent, ReplayPose};
+use crate::mailbox::{self, Receiver, TryRecvError};
+use crate::model::*;
+use crate::net::NetManager;
+use crate::output::OutputInfoRc;
+use crate::scene::{GameParam, MenuParam, Playfield, Scene, SceneFactory, SceneInput, SceneManager, ScenePose, ScenePoseScroll, SongPos};
+use crate::score;
+use crate::songdef::{SongCharacteristic, SongDifficulty};
+use crate::songinfo::SongInfo;
+use crate::ui::{ReplayWindow, UILoop, slintimpl};
+use crate::util::{self, SettingsRc, StatsRc};
+
+const REPLAY_EXT: &str = "bsor";
+const SPAWN_TIME_TOLERANCE: f32 = 0.01; // Recorded note events are paired with the notes, which are spawned this close [s]
+
+// Replay playback: the newest replay of the beatmap is played, the sabers are driven by its frames.
+// The note events are recomputed by the playfield, they are compared with the recorded ones.
+pub struct ReplayParam {
+    asset_mgr: AssetManagerRc,
+    song_info: SongInfo,
+    beatmap_info_index: usize,
+}
+
+impl ReplayParam {
+    pub fn new(asset_mgr: AssetManagerRc, song_info: SongInfo, beatmap_info_index: usize) -> Self {
+        Self {
+            asset_mgr,
+            song_info,
+            beatmap_info_index,
+        }
+    }
+}
+
+impl SceneFactory for ReplayParam {
+    type Scene = Playback;
+    type Error = String;
+
+    fn load(self, _asset_mgr: AssetManagerRc, model_reg: &mut ModelRegistry, _output_info: OutputInfoRc, stats: StatsRc, settings: SettingsRc, audio_engine: AudioEngineRc, ui_loop: &UILoop, _net_manager: &NetManager) -> Result<Self::Scene, Self::Error> {
+        let replay_dir = settings.get_replay_dir_opt().ok_or("Replays are not supported")?;
+
+        let beatmap_info = &self.song_info.get_beatmap_infos()[self.beatmap_info_index];
+        let hash = self.song_info.calc_hash().map_err(|e| format!("Unable to calculate hash: {}", e))?;
+        let replay = load_replay(replay_dir, &hash, beatmap_info.get_characteristic(), beatmap_info.get_difficulty())?;
+
+        let param = GameParam::new(self.asset_mgr, self.song_info, self.beatmap_info_index, #[cfg(feature = "test")] false);
+        Playback::new(&param, replay, model_reg, stats, settings, audio_engine, ui_loop)
+    }
+}
+
+fn load_replay(replay_dir: &Path, hash: &str, characteristic: SongCharacteristic, difficulty: SongDifficulty) -> Result<Replay, String> {
+    // The filenames are ending with the timestamp of the recording (see ReplayInfo::calc_filename).
+
+    let prefix = bsor::calc_filename_prefix(hash, &util::to_serde_name(&characteristic), &util::to_serde_name(&difficulty));
+
+    let entries = fs::read_dir(replay_dir).map_err(|_| "No replays found")?;
+
+    let newest_opt = entries.filter_map(|entry| {
+        let filename = entry.ok()?.file_name().into_string().ok()?;
+        let timestamp: u64 = filename.strip_prefix(&prefix)?.strip_suffix(REPLAY_EXT)?.strip_suffix('.')?.parse().ok()?;
+
+        Some((timestamp, filename))
+    }).max();
+
+    let (_, filename) = newest_opt.ok_or("No replay found for the beatmap")?;
+
+    let file = File::open(replay_dir.join(&filename)).map_err(|e| format!("Unable to open replay: {}", e))?;
+    Replay::read(&mut BufReader::new(file)).map_err(|e| format!("Unable to read replay: {}", e))
+}
+
+pub struct Playback {
+    replay: Replay,
+    ui_loop: UILoop,
+    playfield: Playfield,
+    window: Rc<Window>,
+    window_weak: slintimpl::Weak<ReplayWindow>,
+    window_rx: Receiver<PlaybackMessage>,
+    recorded_index_map: HashMap<i32, Vec<(f32, usize)>>, // Spawn time and index of the recorded note events by note id
+    inner: RefCell<Inner>,
+}
+
+struct Inner {
+    playing: bool,
+    note_matches: HashMap<NoteKey, NoteMatch>, // Comparison of the recomputed note events
+    changed: bool,
+    prev_second: u32, // The position is displayed in seconds
+}
+
+enum PlaybackMessage {
+    TogglePlay,
+    Seek(f32), // [s]
+    Quit,
+}
+
+// Sabers and head of a recorded frame.
+#[derive(Clone, Copy)]
+struct PlaybackPoses {
+    pose_l: PlaybackPose,
+    pose_r: PlaybackPose,
+    head: PlaybackPose,
+}
+
+#[derive(Clone, Copy)]
+struct PlaybackPose {
+    pos: Vector3<f32>,
+    rot: Quaternion<f32>,
+}
+
+// Recomputed note events are identified by note id and spawn time.
+type NoteKey = (i32, u32);
+
+struct NoteMatch {
+    spawn_time: f32,
+    matched: bool,
+    desc: String, // Set, if not matched
+}
+
+fn calc_note_key(note: &ReplayNote) -> NoteKey {
+    (note.note_id, note.spawn_time.to_bits())
+}
+
+fn get_event_name(event_type: ReplayNoteEvent) -> &'static str {
+    match event_type {
+        ReplayNoteEvent::Good => "good cut",
+        ReplayNoteEvent::Bad => "bad cut",
+        ReplayNoteEvent::Miss => "miss",
+        ReplayNoteEvent::Bomb => "bomb hit",
+    }
+}
+
+fn format_time(ts: f32) -> String {
+    let seconds = ts.max(0.0) as u32;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+impl Playback {
+    fn new(param: &GameParam, replay: Replay, model_reg: &mut ModelRegistry, stats: StatsRc, settings: SettingsRc, audio_engine: AudioEngineRc, ui_loop: &UILoop) -> Result<Self, String> {
+        // The beatmap is played with the recorded jump distance and modifiers.
+
+        let playfield = Playfield::new(param, Some(&replay.info), model_reg, stats, &settings, audio_engine, ui_loop)?;
+
+        // Setup replay window, it is displayed on the right side (opposite to the game stats window).
+
+        let (window_tx, window_rx) = mailbox::mailbox();
+
+        let player_name = replay.info.player_name.clone();
+        let recorded_score = replay.info.score;
+        let duration = replay.frames.last().map_or(0.0, |frame| frame.time);
+
+        let window_param = WindowParam::new(500, 400, move || {
+            let window = ReplayWindow::new().unwrap();
+
+            window.set_playing(true);
+            window.set_duration(duration);
+            window.set_time_str(format!("{} / {}", format_time(0.0), format_time(duration)).into());
+            window.set_player_name(player_name.into());
+            window.set_recorded_score(recorded_score);
+
+            window.on_toggle_play({
+                let window_tx = window_tx.clone();
+
+                move || {
+                    window_tx.send(PlaybackMessage::TogglePlay).unwrap();
+                }
+            });
+
+            window.on_seek({
+                let window_tx = window_tx.clone();
+
+                move |ts| {
+                    window_tx.send(PlaybackMessage::Seek(ts)).unwrap();
+                }
+            });
+
+            window.on_quit_replay(move || {
+                window_tx.send(PlaybackMessage::Quit).unwrap();
+            });
+
+            window
+        });
+
+        let window = model_reg.create(window_param);
+        window.set_visible(true);
+        window.set_scale(2.0, 1.6);
+        window.set_pos(&Vector3::new(3.0, 6.0, 3.0));
+        window.set_rot(&Quaternion::from_angle_z(Deg(-20.0)));
+
+        let window_weak = window.as_weak();
+
+        // The first recorded event of a note is used for the comparison.
+
+        let mut recorded_index_map: HashMap<i32, Vec<(f32, usize)>> = HashMap::new();
+
+        for (index, note) in replay.notes.iter().enumerate() {
+            let recorded = recorded_index_map.entry(note.note_id).or_default();
+
+            if !recorded.iter().any(|(spawn_time, _)| (spawn_time - note.spawn_time).abs() <= SPAWN_TIME_TOLERANCE) {
+                recorded.push((note.spawn_time, index));
+            }
+        }
+
+        let inner = Inner {
+            playing: true,
+            note_matches: HashMap::new(),
+            changed: true,
+            prev_second: 0,
+        };
+
+        Ok(Self {
+            replay,
+            ui_loop: ui_loop.clone(),
+            playfield,
+            window,
+            window_weak,
+            window_rx,
+            recorded_index_map,
+            inner: RefCell::new(inner),
+        })
+    }
+
+    fn toggle_play(&self, inner: &mut Inner) {
+        inner.playing = !inner.playing;
+        inner.changed = true;
+
+        self.playfield.set_audio_playing(inner.playing);
+    }
+
+    fn seek(&self, inner: &mut Inner, ts: f32) {
+        // The replay is played again from ts (even if it has been failed), the stats are counted from there.
+
+        if let Err(e) = self.playfield.seek(ts) {
+            self.set_error_msg(format!("Unable to seek: {}", e));
+            return;
+        }
+
+        self.set_error_msg(String::new());
+
+        if !inner.playing {
+            self.playfield.set_audio_playing(false);
+        }
+    }
+
+    fn set_error_msg(&self, error_msg: String) {
+        self.ui_loop.add_callback({
+            let window_weak = self.window_weak.clone();
+
+            move || {
+                window_weak.unwrap().set_error_msg(error_msg.into());
+            }
+        });
+    }
+
+    fn calc_poses(&self, ts: f32) -> PlaybackPoses {
+        // Interpolate between the recorded frames around ts.
+
+        let frames = &self.replay.frames;
+        let index = frames.partition_point(|frame| frame.time <= ts); // First index, where frame.time > ts
+
+        let (frame1, frame2) = match (index.checked_sub(1).and_then(|index| frames.get(index)), frames.get(index)) {
+            (Some(frame1), Some(frame2)) => (frame1, frame2),
+            (Some(frame), None) | (None, Some(frame)) => (frame, frame),
+            (None, None) => {
+                let pose = PlaybackPose::new(&ReplayPose::empty());
+
+                return PlaybackPoses {
+                    pose_l: pose,
+                    pose_r: pose,
+                    head: PlaybackPose::from_head(&ReplayPose::empty()),
+                };
+            },
+        };
+
+        let t = frame2.time - frame1.time;
+        let ratio = if t > 0.0 { ((ts - frame1.time) / t).clamp(0.0, 1.0) } else { 0.0 };
+
+        PlaybackPoses {
+            pose_l: PlaybackPose::new(&frame1.left).lerp(&PlaybackPose::new(&frame2.left), ratio),
+            pose_r: PlaybackPose::new(&frame1.right).lerp(&PlaybackPose::new(&frame2.right), ratio),
+            head: PlaybackPose::from_head(&frame1.head).lerp(&PlaybackPose::from_head(&frame2.head), ratio),
+        }
+    }
+
+    fn compare(&self, inner: &mut Inner, notes: Vec<ReplayNote>) {
+        // Compare the recomputed note events with the recorded ones by their type and score.
+
+        for note in notes {
+            let key = calc_note_key(&note);
+            let score = score::calc_note_score(&note);
+
+            let desc_opt = match self.find_recorded(&note) {
+                Some(index) => {
+                    let recorded = &self.replay.notes[index];
+                    let recorded_score = score::calc_note_score(recorded);
+
+                    if recorded.event_type != note.event_type || recorded_score != score {
+                        Some(format!("{}, recorded {} ({}), recomputed {} ({})", format_time(note.spawn_time), get_event_name(recorded.event_type), recorded_score, get_event_name(note.event_type), score))
+                    } else {
+                        None
+                    }
+                },
+                None => Some(format!("{}, not recorded {} ({})", format_time(note.spawn_time), get_event_name(note.event_type), score)),
+            };
+
+            // After seeking, the note events are recomputed again.
+
+            inner.note_matches.insert(key, NoteMatch {
+                spawn_time: note.spawn_time,
+                matched: desc_opt.is_none(),
+                desc: desc_opt.unwrap_or_default(),
+            });
+
+            inner.changed = true;
+        }
+    }
+
+    fn find_recorded(&self, note: &ReplayNote) -> Option<usize> {
+        // The recorded spawn times can differ slightly from the recomputed ones (e.g. in the replays of other games), so the nearest one is paired.
+
+        self.recorded_index_map.get(&note.note_id)?.iter()
+            .map(|(spawn_time, index)| ((spawn_time - note.spawn_time).abs(), *index))
+            .filter(|(diff, _)| *diff <= SPAWN_TIME_TOLERANCE)
+            .min_by(|(diff1, _), (diff2, _)| diff1.total_cmp(diff2))
+            .map(|(_, index)| index)
+    }
+
+    fn update_window(&self, inner: &mut Inner, ts: f32, recomputed_score: u32) {
+        let second = ts.max(0.0) as u32;
+
+        if !inner.changed && second == inner.prev_second {
+            return;
+        }
+
+        inner.changed = false;
+        inner.prev_second = second;
+
+        let compared = inner.note_matches.len();
+        let mismatches = inner.note_matches.values().filter(|note_match| !note_match.matched).count();
+
+        let first_mismatch = inner.note_matches.values()
+            .filter(|note_match| !note_match.matched)
+            .min_by(|note_match1, note_match2| note_match1.spawn_time.total_cmp(&note_match2.spawn_time))
+            .map_or(String::new(), |note_match| note_match.desc.clone());
+
+        let playing = inner.playing;
+        let duration = self.replay.frames.last().map_or(0.0, |frame| frame.time);
+
+        self.ui_loop.add_callback({
+            let window_weak = self.window_weak.clone();
+
+            move || {
+                let window = window_weak.unwrap();
+
+                window.set_playing(playing);
+                window.set_position(ts);
+                window.set_time_str(format!("{} / {}", format_time(ts), format_time(duration)).into());
+                window.set_compared(compared.try_into().unwrap());
+                window.set_mismatches(mismatches.try_into().unwrap());
+                window.set_first_mismatch(first_mismatch.into());
+                window.set_recomputed_score(recomputed_score.try_into().unwrap());
+            }
+        });
+    }
+}
+
+impl Scene for Playback {
+    fn update(&self, scene_mgr: &SceneManager, scene_input: &SceneInput) {
+        let inner = &mut *self.inner.borrow_mut();
+        let mut done = false;
+
+        // The controllers are used for the replay window.
+
+        self.playfield.update_ui(scene_mgr, &[&self.window], scene_input);
+
+        match self.window_rx.try_recv() {
+            Ok(PlaybackMessage::TogglePlay) => {
+                self.toggle_play(inner);
+            },
+            Ok(PlaybackMessage::Seek(ts)) => {
+                self.seek(inner, ts);
+            },
+            Ok(PlaybackMessage::Quit) => {
+                done = true;
+            },
+            Err(e) => {
+                assert!(matches!(e, TryRecvError::Empty));
+            },
+        }
+
+        // The recorded poses are played instead of the controllers. The replay stays at its end, so that it can be seeked.
+
+        if let SongPos::At(ts) = self.playfield.get_song_pos() {
+            let poses = self.calc_poses(ts);
+
+            let playback_input = SceneInput {
+                pose_l_opt: Some(&poses.pose_l),
+                pose_r_opt: Some(&poses.pose_r),
+                cam_pos: poses.head.pos,
+                cam_rot: poses.head.rot,
+            };
+
+            self.playfield.update(ts, &playback_input);
+            self.playfield.update_sabers(Some(&playback_input));
+
+            // Compare the note events of the replay with the recomputed ones.
+
+            let (notes, _) = self.playfield.take_replay_events();
+
+            self.compare(inner, notes);
+            self.update_window(inner, ts, self.playfield.get_stats().get_score());
+        } else {
+            self.playfield.update_sabers(None);
+        }
+
+        if done {
+            scene_mgr.load(MenuParam::new()).expect("Unable to load scene");
+        }
+    }
+}
+
+impl PlaybackPose {
+    fn new(replay_pose: &ReplayPose) -> Self {
+        let (pos, rot) = replay_pose.get_saber_pos_rot();
+
+        Self {
+            pos,
+            rot,
+        }
+    }
+
+    fn from_head(replay_pose: &ReplayPose) -> Self {
+        let (pos, rot) = replay_pose.get_head_pos_rot();
+
+        Self {
+            pos,
+            rot,
+        }
+    }
+
+    fn lerp(&self, other: &Self, ratio: f32) -> Self {
+        // Interpolate the rotation along the shorter path.
+
+        let other_rot = if self.rot.dot(other.rot) < 0.0 { -other.rot } else { other.rot };
+
+        Self {
+            pos: self.pos.lerp(other.pos, ratio),
+            rot: self.rot.nlerp(other_rot, ratio),
+        }
+    }
+}
+
+impl ScenePose for PlaybackPose {
+    fn get_pos(&self) -> &Vector3<f32> {
+        &self.pos
+    }
+
+    fn get_rot(&self) -> &Quaternion<f32> {
+        &self.rot
+    }
+
+    fn get_click(&self) -> bool {
+        false
+    }
+
+    fn get_scroll(&self) -> ScenePoseScroll {
+        (0.0, 0.0)
+    }
+
+    fn get_render(&self) -> bool {
+        true
+    }
+
+    fn apply_haptic(&self) {
+        // The recorded sabers have no haptics.
+    }
+}
@@ -0,0 +1,224 @@
+use std::rc::Rc;
+use std::sync::Arc;
+
+use cgmath::Vector3;
+
+use crate::asset::AssetManagerRc;
+use crate::audio::AudioEngineRc;
+use crate::mailbox::{self, Receiver, TryRecvError};
+use crate::model::*;
+use crate::net::NetManager;
+use crate::output::OutputInfoRc;
+use crate::scene::{GameParam, GameStatsInner, MenuParam, PracticeParam, Scene, SceneFactory, SceneInput, SceneManager, create_floor, create_pointer, create_saber, create_stats_window};
+use crate::songinfo::ColorScheme;
+use crate::ui::{ResultsWindow, UILoop};
+use crate::util::{SettingsRc, StatsRc};
+
+// Minimum percentage of the ranks, in descending order.
+const RANKS: [(f32, &str); 6] = [(90.0, "SS"), (80.0, "S"), (65.0, "A"), (50.0, "B"), (35.0, "C"), (20.0, "D")];
+const RANK_LOWEST: &str = "E";
+const RANK_FAILED: &str = "F";
+
+pub struct ResultsParam {
+    asset_mgr: AssetManagerRc,
+    beatmap_info_index: usize,
+    #[cfg(feature = "test")]
+    test: bool,
+    practice_opt: Option<PracticeParam>,
+    song_name: String,
+    game_stats: GameStatsInner,
+    cleared: bool,
+    replay_msg: String, // Outcome of saving the replay, empty if it is not recorded
+}
+
+impl ResultsParam {
+    pub fn new(asset_mgr: AssetManagerRc, beatmap_info_index: usize, #[cfg(feature = "test")] test: bool, practice_opt: Option<PracticeParam>, song_name: String, game_stats: GameStatsInner, cleared: bool) -> Self {
+        Self {
+            asset_mgr,
+            beatmap_info_index,
+            #[cfg(feature = "test")]
+            test,
+            practice_opt,
+            song_name,
+            game_stats,
+            cleared,
+            replay_msg: String::new(),
+        }
+    }
+
+    pub fn with_replay_msg(self, replay_msg: String) -> Self {
+        Self {
+            replay_msg,
+            ..self
+        }
+    }
+}
+
+impl SceneFactory for ResultsParam {
+    type Scene = Results;
+    type Error = ();
+
+    fn load(self, _asset_mgr: AssetManagerRc, model_reg: &mut ModelRegistry, _output_info: OutputInfoRc, stats: StatsRc, _settings: SettingsRc, _audio_engine: AudioEngineRc, ui_loop: &UILoop, _net_manager: &NetManager) -> Result<Self::Scene, Self::Error> {
+        Results::new(self, model_reg, stats, ui_loop)
+    }
+}
+
+pub struct Results {
+    asset_mgr: AssetManagerRc,
+    beatmap_info_index: usize, // For retrying the game
+    #[cfg(feature = "test")]
+    test: bool,
+    practice_opt: Option<PracticeParam>,
+    ui_loop: UILoop,
+    results_window: Rc<Window>,
+    results_window_rx: Receiver<ResultsMessage>,
+    saber_l: Rc<Saber>,
+    saber_r: Rc<Saber>,
+    pointer: Rc<Pointer>,
+}
+
+enum ResultsMessage {
+    Retry,
+    Menu,
+}
+
+fn calc_rank(percentage: f32, cleared: bool) -> &'static str {
+    if !cleared {
+        return RANK_FAILED;
+    }
+
+    RANKS.iter().find(|(min_percentage, _)| percentage >= *min_percentage).map_or(RANK_LOWEST, |(_, rank)| rank)
+}
+
+impl Results {
+    fn new(param: ResultsParam, model_reg: &mut ModelRegistry, stats: StatsRc, ui_loop: &UILoop) -> Result<Self, ()> {
+        // Setup results window, it is displayed in front of the player.
+
+        let (results_window_tx, results_window_rx) = mailbox::mailbox();
+
+        let game_stats = param.game_stats;
+        let cleared = param.cleared;
+        let song_name = param.song_name;
+        let replay_msg = param.replay_msg;
+        let practice = param.practice_opt.is_some();
+
+        let window_param = WindowParam::new(600, 400, move || {
+            let window = ResultsWindow::new().unwrap();
+            let percentage = game_stats.calc_percentage();
+
+            window.set_song_name(song_name.into());
+            window.set_cleared(cleared);
+            window.set_rank(calc_rank(percentage, cleared).into());
+            window.set_score(game_stats.get_score().try_into().unwrap());
+            window.set_max_score(game_stats.get_max_score().try_into().unwrap());
+            window.set_percentage(format!("{:.1}%", percentage).into());
+            window.set_max_combo(game_stats.get_max_combo().try_into().unwrap());
+            window.set_count(game_stats.get_count().try_into().unwrap());
+            window.set_total(game_stats.get_total().try_into().unwrap());
+            window.set_misses(game_stats.get_misses().try_into().unwrap());
+            window.set_bad_cuts_wrong_color(game_stats.get_bad_cuts_wrong_color().try_into().unwrap());
+            window.set_bad_cuts_wrong_direction(game_stats.get_bad_cuts_wrong_dir().try_into().unwrap());
+            window.set_bad_cuts_too_slow(game_stats.get_bad_cuts_too_slow().try_into().unwrap());
+            window.set_bomb_hits(game_stats.get_bomb_hits().try_into().unwrap());
+            window.set_wall_hits(game_stats.get_wall_hits().try_into().unwrap());
+            window.set_no_fail_used(game_stats.is_no_fail_used());
+            window.set_practice(practice);
+            window.set_replay_msg(replay_msg.into());
+
+            window.on_retry_game({
+                let results_window_tx = results_window_tx.clone();
+
+                move || {
+                    results_window_tx.send(ResultsMessage::Retry).unwrap();
+                }
+            });
+
+            window.on_open_menu(move || {
+                results_window_tx.send(ResultsMessage::Menu).unwrap();
+            });
+
+            window
+        });
+
+        let results_window = model_reg.create(window_param);
+        results_window.set_visible(true);
+        results_window.set_scale(3.0, 2.0);
+        results_window.set_pos(&Vector3::new(0.0, 4.0, 1.8));
+
+        // Setup floor.
+
+        create_floor(model_reg);
+        create_stats_window(model_reg, stats, ui_loop);
+
+        // Setup sabers.
+
+        let color_scheme = ColorScheme::default();
+        let color_l = color_scheme.get_color_l();
+        let color_r = color_scheme.get_color_r();
+
+        let (saber_l, saber_r) = create_saber(model_reg, color_l, color_r);
+
+        // Setup pointer.
+
+        let pointer = create_pointer(model_reg);
+
+        Ok(Self {
+            asset_mgr: param.asset_mgr,
+            beatmap_info_index: param.beatmap_info_index,
+            #[cfg(feature = "test")]
+            test: param.test,
+            practice_opt: param.practice_opt,
+            ui_loop: ui_loop.clone(),
+            results_window,
+            results_window_rx,
+            saber_l,
+            saber_r,
+            pointer,
+        })
+    }
+
+    fn retry(&self, scene_mgr: &SceneManager) {
+        // If the game can't be started again, then the results are kept with the error.
+
+        let result = GameParam::reload(Arc::clone(&self.asset_mgr), self.beatmap_info_index, #[cfg(feature = "test")] self.test).and_then(|param| {
+            match self.practice_opt {
+                Some(practice) => scene_mgr.load(param.with_practice(practice)),
+                None => scene_mgr.load(param),
+            }
+        });
+
+        if let Err(e) = result {
+            self.show_retry_error(format!("Unable to retry: {}", e));
+        }
+    }
+
+    fn show_retry_error(&self, e: String) {
+        self.ui_loop.add_callback({
+            let window_weak = self.results_window.as_weak::<ResultsWindow>();
+
+            move || {
+                window_weak.unwrap().set_error_msg(e.into());
+            }
+        });
+    }
+}
+
+impl Scene for Results {
+    fn update(&self, scene_mgr: &SceneManager, scene_input: &SceneInput) {
+        // Handle UI events.
+
+        scene_mgr.get_ui_subr().update(&self.saber_l, &self.saber_r, &self.pointer, &[&self.results_window], scene_input);
+
+        match self.results_window_rx.try_recv() {
+            Ok(ResultsMessage::Retry) => {
+                self.retry(scene_mgr);
+            },
+            Ok(ResultsMessage::Menu) => {
+                scene_mgr.load(MenuParam::new()).expect("Unable to load scene");
+            },
+            Err(e) => {
+                assert!(matches!(e, TryRecvError::Empty));
+            },
+        }
+    }
+}
@@ -12,14 +12,14 @@ use crate::model::{ModelRegistry, ModelRenderer};
 use crate::net::NetManager;
 use crate::output::OutputInfoRc;
 use crate::ui::{UILoop, UIManager, UIManagerRc, UISubr};
-use crate::util::StatsRc;
+use crate::util::{SettingsRc, StatsRc};
 
 pub trait SceneFactory {
     type Scene: Scene + 'static;
     type Error;
 
     #[allow(clippy::too_many_arguments)]
-    fn load(self, asset_mgr: AssetManagerRc, model_reg: &mut ModelRegistry, output_info: OutputInfoRc, stats: StatsRc, audio_engine: AudioEngineRc, ui_loop: &UILoop, net_manager: &NetManager) -> Result<Self::Scene, Self::Error>; // TODO: Put all these parameters into a struct?
+    fn load(self, asset_mgr: AssetManagerRc, model_reg: &mut ModelRegistry, output_info: OutputInfoRc, stats: StatsRc, settings: SettingsRc, audio_engine: AudioEngineRc, ui_loop: &UILoop, net_manager: &NetManager) -> Result<Self::Scene, Self::Error>; // TODO: Put all these parameters into a struct?
 }
 
 pub trait Scene { // TODO: add lifecycle methods?
@@ -32,6 +32,8 @@ pub struct SceneInput<'a> {
     
     pub pose_l_opt: Option<&'a dyn ScenePose>,
     pub pose_r_opt: Option<&'a dyn ScenePose>,
+    pub cam_pos: Vector3<f32>, // See Frame->get_cam_pos().
+    pub cam_rot: Quaternion<f32>, // See Frame->get_cam_rot().
 }
 
 pub trait ScenePose {
@@ -49,6 +51,7 @@ pub struct SceneManager {
     asset_mgr: AssetManagerRc,
     output_info: OutputInfoRc,
     stats: StatsRc,
+    settings: SettingsRc,
     uni_bg_layout: BindGroupLayout,
     audio_engine: AudioEngineRc,
     ui_manager: UIManagerRc,
@@ -60,7 +63,7 @@ pub struct SceneManager {
 }
 
 impl SceneManager {
-    pub fn new(asset_mgr: AssetManagerRc, output_info: OutputInfoRc, stats: StatsRc, uni_bg_layout: BindGroupLayout, audio_engine: AudioEngineRc) -> Self {
+    pub fn new(asset_mgr: AssetManagerRc, output_info: OutputInfoRc, stats: StatsRc, settings: SettingsRc, uni_bg_layout: BindGroupLayout, audio_engine: AudioEngineRc) -> Self {
         // Init UI subsystem.
 
         let ui_manager = Rc::new(UIManager::new(output_info.get_queue().clone()));
@@ -77,6 +80,7 @@ impl SceneManager {
             asset_mgr,
             output_info,
             stats,
+            settings,
             uni_bg_layout,
             audio_engine,
             ui_manager,
@@ -95,7 +99,7 @@ impl SceneManager {
 
             // TODO: Implement cache, since ModelRegistry/Obj is going to reload/compile assets on scene switch.
             let mut model_reg = ModelRegistry::new(Arc::clone(&self.asset_mgr), Rc::clone(&self.output_info), Rc::clone(&self.ui_manager));
-            let scene = Box::new(factory.load(Arc::clone(&self.asset_mgr), &mut model_reg, Rc::clone(&self.output_info), Arc::clone(&self.stats), Rc::clone(&self.audio_engine), self.ui_manager.get_ui_loop(), &self.net_manager)?); // TODO: Load next scene: this is going to block the renderloop. Do it on different thread?
+            let scene = Box::new(factory.load(Arc::clone(&self.asset_mgr), &mut model_reg, Rc::clone(&self.output_info), Arc::clone(&self.stats), Arc::clone(&self.settings), Rc::clone(&self.audio_engine), self.ui_manager.get_ui_loop(), &self.net_manager)?); // TODO: Load next scene: this is going to block the renderloop. Do it on different thread?
             let model_renderer = model_reg.build(Arc::clone(&self.stats), &self.uni_bg_layout);
 
             let next_scene_info = SceneInfo::new(scene, model_renderer);
@@ -0,0 +1,88 @@
+// Scoring of the cuts, see https://bsmg.wiki/beatsaber-guides/scoring.html .
+// The score of a cut is rated by the swing (pre-swing and post-swing) and by the accuracy
+// (distance of the cut plane from the center of the cube), it is multiplied by the multiplier.
+
+use crate::bsor::{self, ReplayNote, ReplayNoteEvent, ReplayNoteKind};
+
+pub const SCORE_PRE_SWING: u32 = 70; // Given for a swing of at least PRE_SWING_ANGLE before the cut
+pub const SCORE_POST_SWING: u32 = 30; // Given for a swing of at least POST_SWING_ANGLE after the cut
+pub const SCORE_ACCURACY: u32 = 15; // Given for cutting through the center of the cube
+pub const SCORE_NOTE: u32 = SCORE_PRE_SWING + SCORE_POST_SWING + SCORE_ACCURACY;
+pub const SCORE_CHAIN_HEAD: u32 = SCORE_PRE_SWING + SCORE_ACCURACY; // The post-swing is done on the links
+pub const SCORE_CHAIN_LINK: u32 = 20;
+
+pub const ACCURACY_DIST: f32 = 0.3; // No accuracy score is given, if the cut plane is this far from the center of the note [m]
+
+const MULTIPLIER_MAX: u32 = 8; // The multiplier is doubled after 2 * multiplier hits in a row (the last hit is scored with the doubled one)
+
+#[derive(Copy, Clone)]
+pub struct Multiplier {
+    value: u32,
+    hits: u32, // Since the last change of value
+}
+
+impl Multiplier {
+    pub fn new() -> Self {
+        Self {
+            value: 1,
+            hits: 0,
+        }
+    }
+
+    pub fn get(&self) -> u32 {
+        self.value
+    }
+
+    pub fn hit(&mut self) {
+        if self.value < MULTIPLIER_MAX {
+            self.hits += 1;
+
+            if self.hits >= 2 * self.value {
+                self.value *= 2;
+                self.hits = 0;
+            }
+        }
+    }
+
+    pub fn miss(&mut self) {
+        self.value = (self.value / 2).max(1);
+        self.hits = 0;
+    }
+}
+
+// The part of score given for the ratio (0..1) of a rating.
+
+pub fn calc_swing_score(ratio: f32, score: u32) -> u32 {
+    (ratio.clamp(0.0, 1.0) * score as f32).round() as u32
+}
+
+pub fn calc_max_score(mut note_scores: Vec<(f32, u32)>) -> u32 {
+    // Score of the notes (ts, maximum score of the cut), if all of them are cut perfectly.
+
+    note_scores.sort_by(|(ts1, _), (ts2, _)| ts1.total_cmp(ts2));
+
+    let mut multiplier = Multiplier::new();
+
+    note_scores.into_iter().map(|(_, score)| {
+        multiplier.hit();
+        score * multiplier.get()
+    }).sum()
+}
+
+// Score of a note event, without the multiplier.
+pub fn calc_note_score(note: &ReplayNote) -> u32 {
+    let Some(cut_info) = note.cut_info_opt.filter(|_| note.event_type == ReplayNoteEvent::Good) else {
+        return 0;
+    };
+
+    let pre_swing_score = calc_swing_score(cut_info.before_cut_rating, SCORE_PRE_SWING);
+    let post_swing_score = calc_swing_score(cut_info.after_cut_rating, SCORE_POST_SWING);
+    let accuracy_score = calc_swing_score(1.0 - cut_info.cut_distance_to_center / ACCURACY_DIST, SCORE_ACCURACY);
+
+    match bsor::calc_note_kind(note.note_id) {
+        ReplayNoteKind::Normal | ReplayNoteKind::ArcHead | ReplayNoteKind::ArcTail => pre_swing_score + post_swing_score + accuracy_score,
+        ReplayNoteKind::ChainHead => pre_swing_score + accuracy_score,
+        ReplayNoteKind::ChainLink => SCORE_CHAIN_LINK,
+        ReplayNoteKind::Bomb => 0,
+    }
+}
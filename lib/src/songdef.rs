@@ -1,13 +1,28 @@
 use num_enum::{IntoPrimitive, TryFromPrimitive};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-pub const CHAR_STANDARD: &str = "Standard";
+// We need to store SongCharacteristic and SongDifficulty in slint data structures,
+// therefore conversion from/to a primitive is needed.
 
-// We need to store SongDifficulty in slint data structures, therefore
-// conversion from/to a primitive is needed.
+#[repr(i32)]
+#[derive(Clone, Copy, Deserialize, Eq, IntoPrimitive, Ord, PartialEq, PartialOrd, Serialize, TryFromPrimitive)]
+pub enum SongCharacteristic {
+    Standard,
+    OneSaber,
+    NoArrows,
+    #[serde(rename = "90Degree")]
+    Degree90,
+    #[serde(rename = "360Degree")]
+    Degree360,
+    Lightshow,
+    Lawless,
+    Legacy,
+    #[serde(other)]
+    Unknown, // Not playable
+}
 
 #[repr(i32)]
-#[derive(Clone, Copy, Deserialize, Eq, IntoPrimitive, Ord, PartialEq, PartialOrd, TryFromPrimitive)]
+#[derive(Clone, Copy, Deserialize, Eq, IntoPrimitive, Ord, PartialEq, PartialOrd, Serialize, TryFromPrimitive)]
 pub enum SongDifficulty {
     Easy,
     Normal,
@@ -15,3 +30,22 @@ pub enum SongDifficulty {
     Expert,
     ExpertPlus,
 }
+
+// Names of the mods in the requirements of the beatmaps (see SongCore).
+
+pub const REQUIREMENT_CHROMA: &str = "Chroma";
+pub const REQUIREMENT_CINEMA: &str = "Cinema";
+pub const REQUIREMENT_MAPPING_EXTENSIONS: &str = "Mapping Extensions";
+pub const REQUIREMENT_NOODLE_EXTENSIONS: &str = "Noodle Extensions";
+
+// Chroma is mostly lighting and Cinema is a background video, the beatmap can be played
+// without them. Noodle Extensions is supported, if the custom data is applied (only the
+// note positions are, see NoteCustomData). Everything else (e.g. extended grid) is unsupported.
+
+pub fn is_requirement_supported(requirement: &str, custom_data: bool) -> bool {
+    match requirement {
+        REQUIREMENT_CHROMA | REQUIREMENT_CINEMA => true,
+        REQUIREMENT_NOODLE_EXTENSIONS => custom_data,
+        _ => false,
+    }
+}
@@ -0,0 +1,282 @@
+// Beatmap analysis: note density, swing parity and a simple difficulty estimate.
+// For parity, see https://bsmg.wiki/mapping/basic-mapping.html#parity .
+
+use crate::songdef::SongDifficulty;
+
+use super::{Beatmap, NoteCutDir, NoteType, Timing};
+
+const PEAK_WINDOW_T: f32 = 2.0; // [s]
+const DENSITY_BUCKET_COUNT: usize = 60;
+
+const SWING_GROUP_T: f32 = 0.06; // [s] Notes of a hand closer than this are cut by a single swing (stacks, sliders).
+const RESET_T: f32 = 0.5; // [s] Repeated parity with at least this much time is a reset, otherwise it's a parity error.
+
+// The difficulty rating is roughly the NPS expected for the difficulty, increased by the parity errors.
+const DIFFICULTY_NPS_WEIGHT: f32 = 0.7;
+const DIFFICULTY_PEAK_NPS_WEIGHT: f32 = 0.3;
+const DIFFICULTY_PARITY_ERROR_WEIGHT: f32 = 2.0; // Per ratio of the swings with parity error
+const DIFFICULTY_THRESHOLDS: [(f32, SongDifficulty); 4] = [ // Minimum rating of the difficulties
+    (1.5, SongDifficulty::Normal),
+    (2.5, SongDifficulty::Hard),
+    (3.5, SongDifficulty::Expert),
+    (5.0, SongDifficulty::ExpertPlus),
+];
+
+pub struct BeatmapAnalysis {
+    note_count: usize,
+    duration: f32, // [s] Between the first and the last note
+    nps: f32,
+    peak_nps: f32,
+    peak_ts: f32, // [s] Start of the densest window
+    density: Box<[f32]>, // NPS of the equal parts of the song (until the last note)
+    swings_l: Box<[Swing]>,
+    swings_r: Box<[Swing]>,
+    difficulty_rating: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    Forehand,
+    Backhand,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SwingStatus {
+    Ok,
+    Reset, // The hand has time to reset, the swing has the same parity as the previous one.
+    ParityError, // The swing has the same parity as the previous one, without time to reset.
+}
+
+pub struct Swing {
+    ts: f32, // [s] First note of the swing
+    parity: Parity,
+    status: SwingStatus,
+}
+
+struct SwingGroup {
+    ts: f32,
+    end_ts: f32,
+    parity_opt: Option<Parity>,
+}
+
+impl BeatmapAnalysis {
+    pub fn new(beatmap: &Beatmap, timing: &Timing) -> Self {
+        // Collect the notes to be cut, the chain links are cut by the swing of the chain head.
+        // Bombs are ignored.
+
+        let heads = beatmap.get_chains().iter().map(|chain| chain.get_head());
+
+        let mut notes: Vec<_> = beatmap.get_notes().iter().chain(heads).filter_map(|note| {
+            timing.get_ts(note.get_bpm_pos()).map(|ts| (ts, note.get_note_type(), note.get_cut_dir()))
+        }).collect();
+        notes.sort_by(|(ts_a, ..), (ts_b, ..)| ts_a.total_cmp(ts_b));
+
+        let tss: Box<_> = notes.iter().map(|(ts, ..)| *ts).collect();
+
+        let duration = match (tss.first(), tss.last()) {
+            (Some(first_ts), Some(last_ts)) => last_ts - first_ts,
+            _ => 0.0,
+        };
+
+        let nps = if duration > 0.0 { tss.len() as f32 / duration } else { 0.0 };
+        let (peak_nps, peak_ts) = Self::calc_peak(&tss);
+        let density = Self::calc_density(&tss);
+
+        let calc_swings = |note_type| {
+            Self::calc_swings(notes.iter().filter(|(_, note_type_other, _)| *note_type_other == note_type).map(|(ts, _, cut_dir)| (*ts, *cut_dir)))
+        };
+
+        let swings_l = calc_swings(NoteType::Left);
+        let swings_r = calc_swings(NoteType::Right);
+
+        // Estimate the difficulty.
+
+        let swing_count = swings_l.len() + swings_r.len();
+        let parity_error_count = swings_l.iter().chain(swings_r.iter()).filter(|swing| swing.status == SwingStatus::ParityError).count();
+        let parity_error_ratio = if swing_count > 0 { parity_error_count as f32 / swing_count as f32 } else { 0.0 };
+
+        let difficulty_rating = (DIFFICULTY_NPS_WEIGHT * nps + DIFFICULTY_PEAK_NPS_WEIGHT * peak_nps) * (1.0 + DIFFICULTY_PARITY_ERROR_WEIGHT * parity_error_ratio);
+
+        Self {
+            note_count: tss.len(),
+            duration,
+            nps,
+            peak_nps,
+            peak_ts,
+            density,
+            swings_l,
+            swings_r,
+            difficulty_rating,
+        }
+    }
+
+    pub fn get_note_count(&self) -> usize {
+        self.note_count
+    }
+
+    pub fn get_duration(&self) -> f32 {
+        self.duration
+    }
+
+    pub fn get_nps(&self) -> f32 {
+        self.nps
+    }
+
+    pub fn get_peak_nps(&self) -> f32 {
+        self.peak_nps
+    }
+
+    pub fn get_peak_ts(&self) -> f32 {
+        self.peak_ts
+    }
+
+    pub fn get_density(&self) -> &[f32] {
+        &self.density
+    }
+
+    pub fn get_swings(&self, note_type: NoteType) -> &[Swing] {
+        match note_type {
+            NoteType::Left => &self.swings_l,
+            NoteType::Right => &self.swings_r,
+        }
+    }
+
+    pub fn get_reset_count(&self) -> usize {
+        self.count_swings(SwingStatus::Reset)
+    }
+
+    pub fn get_parity_error_count(&self) -> usize {
+        self.count_swings(SwingStatus::ParityError)
+    }
+
+    pub fn get_difficulty_rating(&self) -> f32 {
+        self.difficulty_rating
+    }
+
+    pub fn get_difficulty(&self) -> SongDifficulty {
+        DIFFICULTY_THRESHOLDS.iter().rev().find(|(rating, _)| self.difficulty_rating >= *rating).map(|(_, difficulty)| *difficulty).unwrap_or(SongDifficulty::Easy)
+    }
+
+    fn count_swings(&self, status: SwingStatus) -> usize {
+        self.swings_l.iter().chain(self.swings_r.iter()).filter(|swing| swing.status == status).count()
+    }
+
+    fn calc_peak(tss: &[f32]) -> (f32, f32) {
+        // Count the notes in the window ending at each note.
+
+        let mut peak_count = 0;
+        let mut peak_ts = 0.0;
+        let mut start = 0;
+
+        for (end, ts) in tss.iter().enumerate() {
+            while tss[start] <= ts - PEAK_WINDOW_T {
+                start += 1;
+            }
+
+            let count = end - start + 1;
+            if count > peak_count {
+                peak_count = count;
+                peak_ts = tss[start];
+            }
+        }
+
+        (peak_count as f32 / PEAK_WINDOW_T, peak_ts)
+    }
+
+    fn calc_density(tss: &[f32]) -> Box<[f32]> {
+        let last_ts = tss.last().copied().unwrap_or(0.0);
+        if last_ts <= 0.0 {
+            return Box::default();
+        }
+
+        let bucket_t = last_ts / DENSITY_BUCKET_COUNT as f32;
+        let mut density = vec![0.0; DENSITY_BUCKET_COUNT];
+
+        for ts in tss {
+            let index = ((ts.max(0.0) / bucket_t) as usize).min(DENSITY_BUCKET_COUNT - 1);
+            density[index] += 1.0 / bucket_t;
+        }
+
+        density.into_boxed_slice()
+    }
+
+    fn calc_swings<I: Iterator<Item = (f32, NoteCutDir)>>(notes: I) -> Box<[Swing]> {
+        // Group the notes of the swings, the first note with direction determines the parity.
+
+        let mut groups: Vec<SwingGroup> = Vec::new();
+
+        for (ts, cut_dir) in notes {
+            if let Some(group) = groups.last_mut() && ts - group.end_ts < SWING_GROUP_T {
+                group.end_ts = ts;
+                group.parity_opt = group.parity_opt.or(Parity::from_cut_dir(cut_dir));
+            } else {
+                groups.push(SwingGroup {
+                    ts,
+                    end_ts: ts,
+                    parity_opt: Parity::from_cut_dir(cut_dir),
+                });
+            }
+        }
+
+        // Swings without parity (dot notes, horizontal swings) are assumed to alternate.
+
+        let mut prev_opt: Option<(f32, Parity)> = None;
+
+        Box::from_iter(groups.into_iter().map(|group| {
+            let (parity, status) = match (group.parity_opt, prev_opt) {
+                (None, None) => (Parity::Forehand, SwingStatus::Ok),
+                (None, Some((_, prev_parity))) => (prev_parity.flip(), SwingStatus::Ok),
+                (Some(parity), Some((prev_end_ts, prev_parity))) if parity == prev_parity => {
+                    if group.ts - prev_end_ts >= RESET_T {
+                        (parity, SwingStatus::Reset)
+                    } else {
+                        (parity, SwingStatus::ParityError)
+                    }
+                },
+                (Some(parity), _) => (parity, SwingStatus::Ok),
+            };
+
+            prev_opt = Some((group.end_ts, parity));
+
+            Swing {
+                ts: group.ts,
+                parity,
+                status,
+            }
+        }))
+    }
+}
+
+impl Parity {
+    // Downward swings are forehands, upward swings are backhands. The parity of horizontal swings
+    // depends on the previous swing.
+
+    fn from_cut_dir(cut_dir: NoteCutDir) -> Option<Self> {
+        match cut_dir {
+            NoteCutDir::Down | NoteCutDir::DownLeft | NoteCutDir::DownRight => Some(Parity::Forehand),
+            NoteCutDir::Up | NoteCutDir::UpLeft | NoteCutDir::UpRight => Some(Parity::Backhand),
+            NoteCutDir::Left | NoteCutDir::Right | NoteCutDir::Any => None,
+        }
+    }
+
+    fn flip(self) -> Self {
+        match self {
+            Parity::Forehand => Parity::Backhand,
+            Parity::Backhand => Parity::Forehand,
+        }
+    }
+}
+
+impl Swing {
+    pub fn get_ts(&self) -> f32 {
+        self.ts
+    }
+
+    pub fn get_parity(&self) -> Parity {
+        self.parity
+    }
+
+    pub fn get_status(&self) -> SwingStatus {
+        self.status
+    }
+}
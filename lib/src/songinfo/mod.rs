@@ -0,0 +1,4425 @@
+// For format description, see:
+// - https://bsmg.wiki/mapping/map-format.html
+// - https://github.com/Kylemc1413/SongCore/blob/master/README.md
+// TODO: use &refs in #[derive(Deserialize)] structs instead of owned types
+#![allow(non_camel_case_types)]
+
+mod analysis;
+pub use analysis::*;
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as fmt_Result};
+use std::io::Read;
+use std::ops::Range;
+use std::result::{Result as result_Result};
+use std::sync::Arc;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::de::{Error as de_Error, Visitor};
+use serde_json::{Error as json_Error, Value};
+use sha1::{Digest, Sha1};
+
+use crate::asset::{AssetError, AssetManagerRc};
+use crate::model::Color;
+use crate::songdef::{self, SongCharacteristic, SongDifficulty};
+
+type Result<T> = result_Result<T, Error>;
+
+const NOTE_TYPE_BOMB_V2: u8 = 3;
+
+const OBSTACLE_TYPE_FULL_V2: u32 = 0;
+const OBSTACLE_TYPE_CROUCH_V2: u32 = 1;
+const OBSTACLE_TYPE_FREE_V2: u32 = 2; // Since 2.6.0: _lineLayer and _height are specified.
+
+const BPM_POS_EPSILON: f32 = 0.001; // Used for matching notes at the same beat.
+
+const EVENT_TYPE_BPM_CHANGE_V2: i32 = 100;
+
+const EVENT_TYPE_BACK_LASERS: i32 = 0;
+const EVENT_TYPE_RING_LIGHTS: i32 = 1;
+const EVENT_TYPE_LEFT_LASERS: i32 = 2;
+const EVENT_TYPE_RIGHT_LASERS: i32 = 3;
+const EVENT_TYPE_CENTER_LIGHTS: i32 = 4;
+
+const EVENT_TYPE_EARLY_ROTATION_V2: i32 = 14;
+const EVENT_TYPE_LATE_ROTATION_V2: i32 = 15;
+const ROTATION_ANGLES_V2: [f32; 8] = [-60.0, -45.0, -30.0, -15.0, 15.0, 30.0, 45.0, 60.0]; // Indexed by _value
+
+// See https://bsmg.wiki/mapping/difficulty-jump-settings.html .
+const HALF_JUMP_DURATION: f32 = 4.0; // [beat]
+const HALF_JUMP_DURATION_MIN: f32 = 0.25; // [beat]
+const HALF_JUMP_DIST_MAX: f32 = 17.999; // [m]
+
+// BPM map created from BPM changes, when writing V4.
+const BPMMAP_FILENAME: &str = "AudioData.dat";
+const BPMMAP_SAMPLE_RATE: u32 = 44100;
+const BPM_EPSILON: f32 = 0.001; // Used for matching the BPMs of the BPM map ranges.
+
+// Noodle Extensions positions are relative to the center of the grid.
+const NOODLE_X_OFFSET: f32 = 2.0;
+
+#[derive(Debug)]
+pub enum Error {
+    Asset(AssetError),
+    Parse(json_Error),
+    Build(String),
+    Validation(Box<[Diagnostic]>), // Contains at least one diagnostic with Severity::Error
+}
+
+impl From<AssetError> for Error {
+    fn from(value: AssetError) -> Self {
+        Error::Asset(value)
+    }
+}
+
+impl From<json_Error> for Error {
+    fn from(value: json_Error) -> Self {
+        Error::Parse(value)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt_Result {
+        match self {
+            Error::Asset(AssetError::NotFound) => write!(f, "File not found"),
+            Error::Asset(AssetError::Decode) => write!(f, "File not decodable"),
+            Error::Parse(e) => write!(f, "{}", e),
+            Error::Build(msg) => write!(f, "{}", msg),
+            Error::Validation(diagnostics) => {
+                // Only the first error is shown, the rest is summarized.
+
+                let mut errors = diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error);
+                if let Some(first) = errors.next() {
+                    write!(f, "{}", first)?;
+                }
+
+                match errors.count() {
+                    0 => Ok(()),
+                    count => write!(f, " (and {} more)", count),
+                }
+            },
+        }
+    }
+}
+
+// Validation
+
+// The raw structs are validated before they are built. Errors are rejecting the file, warnings
+// are kept, since the affected values are either ignored or clamped by the build.
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    severity: Severity,
+    filename: String,
+    path: String, // JSON path, e.g. $._notes[3]._time
+    message: String,
+}
+
+impl Diagnostic {
+    pub fn get_severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn get_filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt_Result {
+        let severity = match self.severity {
+            Severity::Error => "Error",
+            Severity::Warning => "Warning",
+        };
+
+        write!(f, "{} in {} at {}: {}", severity, self.filename, self.path, self.message)
+    }
+}
+
+struct Validator<'a> {
+    filename: &'a str,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Validator<'a> {
+    fn run<T: Validate>(filename: &'a str, raw: &T) -> Result<Box<[Diagnostic]>> {
+        let mut validator = Self {
+            filename,
+            diagnostics: Vec::new(),
+        };
+
+        raw.validate(&mut validator, "$");
+
+        let diagnostics = validator.diagnostics.into_boxed_slice();
+        if diagnostics.iter().any(|diagnostic| diagnostic.severity == Severity::Error) {
+            return Err(Error::Validation(diagnostics));
+        }
+
+        Ok(diagnostics)
+    }
+
+    fn report(&mut self, severity: Severity, path: &str, field: &str, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            filename: self.filename.to_string(),
+            path: format!("{}.{}", path, field),
+            message,
+        });
+    }
+
+    fn validate<T: Validate>(&mut self, path: &str, field: &str, value: &T) {
+        value.validate(self, &format!("{}.{}", path, field));
+    }
+
+    fn check_finite(&mut self, path: &str, field: &str, value: f32) -> bool {
+        if !value.is_finite() {
+            self.report(Severity::Error, path, field, format!("Not a finite number: {}", value));
+            return false;
+        }
+
+        true
+    }
+
+    fn check_positive(&mut self, path: &str, field: &str, value: f32) {
+        if self.check_finite(path, field, value) && value <= 0.0 {
+            self.report(Severity::Error, path, field, format!("Not positive: {}", value));
+        }
+    }
+
+    fn check_non_negative(&mut self, path: &str, field: &str, value: f32) {
+        if self.check_finite(path, field, value) && value < 0.0 {
+            self.report(Severity::Warning, path, field, format!("Negative: {}", value));
+        }
+    }
+
+    fn check_bpm_pos(&mut self, path: &str, field: &str, bpm_pos: f32) {
+        if self.check_finite(path, field, bpm_pos) && bpm_pos < 0.0 {
+            self.report(Severity::Warning, path, field, format!("Negative beat position: {}", bpm_pos));
+        }
+    }
+
+    // Notes, bombs, arcs and chains have to be placed on the 4x3 grid.
+
+    fn check_grid(&mut self, path: &str, x_field: &str, y_field: &str, x: u8, y: u8) {
+        if x > 3 {
+            self.report(Severity::Error, path, x_field, format!("Column outside of the grid: {}", x));
+        }
+
+        if y > 2 {
+            self.report(Severity::Error, path, y_field, format!("Row outside of the grid: {}", y));
+        }
+    }
+
+    fn check_size(&mut self, path: &str, field: &str, size: i32) {
+        if size < 0 {
+            self.report(Severity::Error, path, field, format!("Negative size: {}", size));
+        }
+    }
+
+    // Objects of V4 are referring to their data by index, objects with invalid index are ignored.
+
+    fn check_indexes<T, F: Fn(&T) -> u32>(&mut self, path: &str, field: &str, index_field: &str, objs: &[T], get_index: F, len: usize) {
+        for (i, obj) in objs.iter().enumerate() {
+            let index = get_index(obj);
+
+            if index as usize >= len {
+                self.report(Severity::Warning, &format!("{}.{}[{}]", path, field, i), index_field, format!("Index out of range: {}", index));
+            }
+        }
+    }
+}
+
+trait Validate {
+    fn validate(&self, validator: &mut Validator, path: &str);
+}
+
+impl<T: Validate> Validate for Option<T> {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        if let Some(value) = self {
+            value.validate(validator, path);
+        }
+    }
+}
+
+impl<T: Validate> Validate for Vec<T> {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        for (index, value) in self.iter().enumerate() {
+            value.validate(validator, &format!("{}[{}]", path, index));
+        }
+    }
+}
+
+// SongInfo
+
+pub struct SongInfo {
+    asset_mgr: AssetManagerRc,
+    author: String,
+    title: String,
+    sub_title: String,
+    full_title: String, // Title and sub-title
+    song_filename: String,
+    bpm_selector: BPMSelector,
+    metadata: SongMetadata,
+    color_schemes: Box<[ColorScheme]>,
+    beatmap_infos: Box<[BeatmapInfo]>,
+    diagnostics: Box<[Diagnostic]>,
+}
+
+impl SongInfo {
+    pub fn load(asset_mgr: AssetManagerRc) -> Result<Self> {
+        // From https://docs.rs/serde_json/latest/serde_json/fn.from_reader.html :
+        // Note that counter to intuition, this function is usually slower than reading a file completely into memory and then applying from_str or from_slice on it.
+
+        let asset_file = asset_mgr.open("/Info.dat")?;
+        let buf = asset_file.read_str()?;
+
+        let (mut song_info, diagnostics) = match get_version(&buf)?.as_str() {
+            "2.0.0" | "2.1.0" => {
+                let info: SongInfo_V2 = serde_json::from_str(&buf)?;
+                let diagnostics = Validator::run("Info.dat", &info)?;
+                (info.build(asset_mgr)?, diagnostics)
+            },
+            "4.0.0" | "4.0.1" => {
+                let info: SongInfo_V4 = serde_json::from_str(&buf)?;
+                let diagnostics = Validator::run("Info.dat", &info)?;
+                (info.build(asset_mgr)?, diagnostics)
+            },
+            version => return Err(Error::Build(format!("Unsupported info version: {}", version)))
+        };
+
+        song_info.diagnostics = diagnostics;
+
+        Ok(song_info)
+    }
+
+    #[cfg(feature = "test")]
+    pub fn test(asset_mgr: AssetManagerRc) -> Self {
+        let beatmap_info = BeatmapInfo::test(Arc::clone(&asset_mgr));
+
+        Self {
+            asset_mgr,
+            author: "author".to_string(),
+            title: "title".to_string(),
+            sub_title: String::new(),
+            full_title: "title".to_string(),
+            song_filename: "song_filename".to_string(),
+            bpm_selector: BPMSelector::Fixed(1.0),
+            metadata: SongMetadata::default(),
+            color_schemes: Box::from([]),
+            beatmap_infos: Box::from([beatmap_info]),
+            diagnostics: Box::from([]),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new(asset_mgr: AssetManagerRc, author: String, title: String, sub_title: String, song_filename: String, bpm_selector: BPMSelector, metadata: SongMetadata, color_schemes: Vec<ColorScheme>, beatmap_infos: Vec<BeatmapInfo>) -> Self {
+        let space = if sub_title.is_empty() {
+            ""
+        } else {
+            " "
+        };
+
+        Self {
+            asset_mgr,
+            author,
+            full_title: format!("{}{}{}", title, space, sub_title),
+            title,
+            sub_title,
+            song_filename: format!("/{}", song_filename),
+            bpm_selector,
+            metadata,
+            color_schemes: color_schemes.into_boxed_slice(),
+            beatmap_infos: beatmap_infos.into_boxed_slice(),
+            diagnostics: Box::from([]),
+        }
+    }
+
+    pub fn get_author(&self) -> &str {
+        &self.author
+    }
+
+    pub fn get_title(&self) -> &str {
+        &self.full_title
+    }
+
+    pub fn get_song_filename(&self) -> &str {
+        &self.song_filename
+    }
+
+    pub fn get_metadata(&self) -> &SongMetadata {
+        &self.metadata
+    }
+
+    // The cover image is not decoded (None, if the song doesn't have it).
+
+    pub fn load_cover(&self) -> Result<Option<Vec<u8>>> {
+        let Some(cover_filename) = &self.metadata.cover_filename_opt else {
+            return Ok(None);
+        };
+
+        Ok(Some(read_file(&self.asset_mgr, cover_filename)?))
+    }
+
+    // Identifies the song the same way as SongCore and BeatSaver: SHA-1 of Info.dat followed by the
+    // beatmap files in the order of the beatmaps. In V4, Info.dat is followed by the audio data file
+    // (the BPM map) and each beatmap file is followed by its lightshow file. The hash is lowercase hex.
+
+    pub fn calc_hash(&self) -> Result<String> {
+        let mut hasher = Sha1::new();
+        hasher.update(read_file(&self.asset_mgr, "Info.dat")?);
+
+        if let BPMSelector::Mapped(filename) = &self.bpm_selector {
+            hasher.update(read_file(&self.asset_mgr, filename)?);
+        }
+
+        for beatmap_info in &self.beatmap_infos {
+            hasher.update(read_file(&self.asset_mgr, &beatmap_info.filename)?);
+
+            if let Some(lightshow_filename) = &beatmap_info.lightshow_filename_opt {
+                hasher.update(read_file(&self.asset_mgr, lightshow_filename)?);
+            }
+        }
+
+        Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+
+    // Rotating characteristics may have their own environment (only in V2).
+
+    pub fn get_environment_name(&self, beatmap_info: &BeatmapInfo) -> Option<&str> {
+        let rotating = matches!(beatmap_info.characteristic, SongCharacteristic::Degree90 | SongCharacteristic::Degree360);
+
+        if rotating && let Some(name) = &self.metadata.all_directions_environment_name_opt {
+            return Some(name);
+        }
+
+        self.metadata.environment_names.get(beatmap_info.environment_name_index as usize).map(String::as_str)
+    }
+
+    pub fn get_bpm_info(&self) -> Result<BPMInfo> {
+        Ok(match &self.bpm_selector {
+            BPMSelector::Fixed(bpm) => BPMInfo::Fixed(*bpm),
+            BPMSelector::Mapped(filename) => BPMInfo::Mapped(BPMMap::load(Arc::clone(&self.asset_mgr), filename)?),
+        })
+    }
+
+    pub fn get_color_scheme(&self, index: u32) -> Option<&ColorScheme> {
+        self.color_schemes.get(index as usize)
+    }
+
+    pub fn get_beatmap_infos(&self) -> &[BeatmapInfo] {
+        &self.beatmap_infos
+    }
+
+    // Warnings of Info.dat (errors are rejecting the song).
+
+    pub fn get_diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    // Writes Info.dat and the beatmaps (for V4 also the BPM map and the lightshows) in the given
+    // version, converting them if needed. The song file itself is not written.
+
+    pub fn write(&self, version: FormatVersion) -> Result<Box<[SongFile]>> {
+        // The names of unknown characteristics are not kept, so they would be lost.
+
+        if self.beatmap_infos.iter().any(|beatmap_info| beatmap_info.characteristic == SongCharacteristic::Unknown) {
+            return Err(Error::Build("Unknown characteristic can't be written".to_string()));
+        }
+
+        let beatmaps = self.beatmap_infos.iter().map(|beatmap_info| beatmap_info.load()).collect::<Result<Vec<_>>>()?;
+
+        let mut files = Vec::new();
+
+        let info = match version {
+            FormatVersion::V2 | FormatVersion::V3 => {
+                // The BPM map is converted to BPM changes of the beatmaps.
+
+                let (bpm, map_bpm_changes_opt) = match &self.bpm_selector {
+                    BPMSelector::Fixed(bpm) => (*bpm, None),
+                    BPMSelector::Mapped(filename) => {
+                        let bpm_map = BPMMap::load(Arc::clone(&self.asset_mgr), filename)?;
+                        let (bpm, bpm_changes) = bpm_map.to_bpm_changes()?;
+                        (bpm, Some(bpm_changes))
+                    },
+                };
+
+                for (beatmap_info, beatmap) in self.beatmap_infos.iter().zip(&beatmaps) {
+                    let bpm_changes = map_bpm_changes_opt.as_deref().unwrap_or(&beatmap.bpm_changes);
+                    files.push(SongFile::new(beatmap_info.filename.clone(), beatmap.write_with(version, bpm_changes)?));
+                }
+
+                to_json(&SongInfo_V2::from_song_info(self, bpm), "_version", "2.1.0")?
+            },
+            FormatVersion::V4 => {
+                if self.metadata.song_time_offset != 0.0 {
+                    return Err(Error::Build("Song time offset is not supported by info version 4".to_string()));
+                }
+
+                // The BPM changes of the beatmaps are converted to a BPM map, they have to be
+                // the same for all of the beatmaps.
+
+                let (bpm, bpmmap_filename_opt) = match &self.bpm_selector {
+                    BPMSelector::Mapped(filename) => {
+                        let bpm_map = BPMMap::load(Arc::clone(&self.asset_mgr), filename)?;
+
+                        files.push(SongFile::new(filename.clone(), to_json(&BPMMap_V4::from_bpm_map(&bpm_map), "version", "4.0.0")?));
+                        (bpm_map.to_bpm_changes()?.0, Some(filename.clone()))
+                    },
+                    BPMSelector::Fixed(bpm) => {
+                        let bpm = *bpm;
+                        let bpm_changes = beatmaps.first().map(|beatmap| &beatmap.bpm_changes[..]).unwrap_or_default();
+                        if beatmaps.iter().any(|beatmap| !BPMChange::is_same_list(&beatmap.bpm_changes, bpm_changes)) {
+                            return Err(Error::Build("Beatmaps have different BPM changes".to_string()));
+                        }
+
+                        if bpm_changes.is_empty() {
+                            (bpm, None)
+                        } else {
+                            let end_bpm_pos = beatmaps.iter().map(|beatmap| beatmap.get_end_bpm_pos()).fold(0.0, f32::max);
+                            let bpm_map = BPMMap::from_bpm_changes(bpm, bpm_changes, end_bpm_pos);
+
+                            files.push(SongFile::new(BPMMAP_FILENAME.to_string(), to_json(&BPMMap_V4::from_bpm_map(&bpm_map), "version", "4.0.0")?));
+                            (bpm, Some(BPMMAP_FILENAME.to_string()))
+                        }
+                    },
+                };
+
+                // The lighting events are moved to separate files (shared lightshows are written once).
+
+                let mut lightshow_filename_opts = Vec::new();
+
+                for (beatmap_info, beatmap) in self.beatmap_infos.iter().zip(&beatmaps) {
+                    files.push(SongFile::new(beatmap_info.filename.clone(), beatmap.write(version)?));
+
+                    let lightshow_filename_opt = beatmap_info.lightshow_filename_opt.clone().or_else(|| {
+                        (!beatmap.light_events.is_empty()).then(|| format!("{}.lightshow.dat", beatmap_info.filename.strip_suffix(".dat").unwrap_or(&beatmap_info.filename)))
+                    });
+
+                    if let Some(lightshow_filename) = &lightshow_filename_opt && !files.iter().any(|file| file.filename == *lightshow_filename) {
+                        files.push(SongFile::new(lightshow_filename.clone(), beatmap.write_lightshow()?));
+                    }
+
+                    lightshow_filename_opts.push(lightshow_filename_opt);
+                }
+
+                to_json(&SongInfo_V4::from_song_info(self, bpm, bpmmap_filename_opt, lightshow_filename_opts), "version", "4.0.1")?
+            },
+        };
+
+        files.push(SongFile::new("Info.dat".to_string(), info));
+
+        Ok(files.into_boxed_slice())
+    }
+}
+
+// Info.dat fields, which are not needed for playing the song.
+
+#[derive(Default)]
+pub struct SongMetadata {
+    level_author: String, // Mappers of the song (in V4, they are collected from the beatmaps)
+    cover_filename_opt: Option<String>,
+    preview_start: f32, // [s]
+    preview_duration: f32, // [s]
+    song_time_offset: f32, // Audio ts of the beatmap start, only in V2 [s]
+    shuffle: f32, // Only in V2, unused
+    shuffle_period: f32, // Only in V2, unused
+    environment_names: Box<[String]>, // Indexed by the beatmaps
+    all_directions_environment_name_opt: Option<String>, // Only in V2
+}
+
+impl SongMetadata {
+    pub fn get_level_author(&self) -> &str {
+        &self.level_author
+    }
+
+    pub fn get_cover_filename(&self) -> Option<&str> {
+        self.cover_filename_opt.as_deref()
+    }
+
+    pub fn get_preview_start(&self) -> f32 {
+        self.preview_start
+    }
+
+    pub fn get_preview_duration(&self) -> f32 {
+        self.preview_duration
+    }
+
+    pub fn get_song_time_offset(&self) -> f32 {
+        self.song_time_offset
+    }
+
+    pub fn get_shuffle(&self) -> f32 {
+        self.shuffle
+    }
+
+    pub fn get_shuffle_period(&self) -> f32 {
+        self.shuffle_period
+    }
+
+    pub fn get_environment_names(&self) -> &[String] {
+        &self.environment_names
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FormatVersion {
+    V2,
+    V3, // Info.dat is written in V2
+    V4,
+}
+
+pub struct SongFile {
+    filename: String,
+    content: String,
+}
+
+impl SongFile {
+    fn new(filename: String, content: String) -> Self {
+        Self {
+            filename,
+            content,
+        }
+    }
+
+    pub fn get_filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn get_content(&self) -> &str {
+        &self.content
+    }
+}
+
+enum BPMSelector {
+    Fixed(f32),
+    Mapped(String),
+}
+
+pub enum BPMInfo {
+    Fixed(f32),
+    Mapped(BPMMap),
+}
+
+pub struct ColorScheme {
+    color_l: Color,
+    color_r: Color,
+    env_color_l: Color,
+    env_color_r: Color,
+}
+
+impl ColorScheme {
+    fn new(color_l: Color, color_r: Color, env_color_l: Color, env_color_r: Color) -> Self {
+        Self {
+            color_l,
+            color_r,
+            env_color_l,
+            env_color_r,
+        }
+    }
+
+    pub fn get_color_l(&self) -> &Color {
+        &self.color_l
+    }
+
+    pub fn get_color_r(&self) -> &Color {
+        &self.color_r
+    }
+
+    pub fn get_env_color_l(&self) -> &Color {
+        &self.env_color_l
+    }
+
+    pub fn get_env_color_r(&self) -> &Color {
+        &self.env_color_r
+    }
+
+    fn is_same(&self, other: &ColorScheme) -> bool {
+        self.color_l.0 == other.color_l.0 && self.color_r.0 == other.color_r.0 && self.env_color_l.0 == other.env_color_l.0 && self.env_color_r.0 == other.env_color_r.0
+    }
+}
+
+impl Default for ColorScheme {
+    fn default() -> Self {
+        let color_l = Color::from_srgb_float(0.7843137, 0.07843138, 0.07843138); // See https://bsmg.wiki/mapping/lighting-defaults.html#_1-19-0-colors .
+        let color_r = Color::from_srgb_float(0.1568627, 0.5568627, 0.8235294);
+        let env_color_l = Color::from_srgb_float(0.85, 0.08499997, 0.08499997);
+        let env_color_r = Color::from_srgb_float(0.1882353, 0.675294, 1.0);
+
+        ColorScheme::new(color_l, color_r, env_color_l, env_color_r)
+    }
+}
+
+pub struct BeatmapInfo {
+    asset_mgr: AssetManagerRc,
+    characteristic: SongCharacteristic,
+    difficulty: SongDifficulty,
+    color_scheme_index_opt: Option<u32>,
+    def_color_scheme: ColorScheme,
+    filename: String,
+    lightshow_filename_opt: Option<String>,
+    notejump_speed: f32,
+    notejump_beatoffset: f32,
+    environment_name_index: u32,
+    mappers: Box<[String]>, // Only in V4
+    lighters: Box<[String]>, // Only in V4
+    songcore_info: SongCoreInfo,
+    #[cfg(feature = "test")]
+    test: bool,
+}
+
+impl BeatmapInfo {
+    #[allow(clippy::too_many_arguments)]
+    fn new(asset_mgr: AssetManagerRc, characteristic: SongCharacteristic, difficulty: SongDifficulty, mut color_scheme_index_opt: Option<i32>, def_color_scheme: ColorScheme, filename: String, lightshow_filename_opt: Option<String>, notejump_speed: f32, notejump_beatoffset: f32, environment_name_index_opt: Option<i32>, mappers: Vec<String>, lighters: Vec<String>, songcore_info: SongCoreInfo) -> Self {
+        Self {
+            asset_mgr,
+            characteristic,
+            difficulty,
+            color_scheme_index_opt: color_scheme_index_opt.take_if(|color_scheme_index| *color_scheme_index >= 0).map(|color_scheme_index| color_scheme_index.try_into().unwrap()), // color_scheme_index can be negative, which is the same as not specified.
+            def_color_scheme,
+            filename,
+            lightshow_filename_opt,
+            notejump_speed,
+            notejump_beatoffset,
+            environment_name_index: environment_name_index_opt.and_then(|environment_name_index| environment_name_index.try_into().ok()).unwrap_or(0), // Negative is the same as not specified.
+            mappers: mappers.into_boxed_slice(),
+            lighters: lighters.into_boxed_slice(),
+            songcore_info,
+            #[cfg(feature = "test")]
+            test: false,
+        }
+    }
+
+    #[cfg(feature = "test")]
+    fn test(asset_mgr: AssetManagerRc) -> Self {
+        Self {
+            asset_mgr,
+            characteristic: SongCharacteristic::Standard,
+            difficulty: SongDifficulty::Easy,
+            color_scheme_index_opt: None,
+            def_color_scheme: ColorScheme::default(),
+            filename: "filename".to_string(),
+            lightshow_filename_opt: None,
+            notejump_speed: 1.0,
+            notejump_beatoffset: 0.0,
+            environment_name_index: 0,
+            mappers: Box::from([]),
+            lighters: Box::from([]),
+            songcore_info: SongCoreInfo::default(),
+            test: true,
+        }
+    }
+
+    pub fn load(&self) -> Result<Beatmap> {
+        #[cfg(feature = "test")]
+        if self.test {
+            return Beatmap::test();
+        }
+
+        let mut beatmap = Beatmap::load(Arc::clone(&self.asset_mgr), &self.filename)?;
+
+        // Since V4, the lighting events are stored in a separate file.
+
+        if let Some(lightshow_filename) = &self.lightshow_filename_opt {
+            beatmap.load_lightshow(Arc::clone(&self.asset_mgr), lightshow_filename)?;
+        }
+
+        Ok(beatmap)
+    }
+
+    pub fn get_characteristic(&self) -> SongCharacteristic {
+        self.characteristic
+    }
+
+    pub fn get_difficulty(&self) -> SongDifficulty {
+        self.difficulty
+    }
+
+    pub fn get_color_scheme_index_opt(&self) -> Option<u32> {
+        self.color_scheme_index_opt
+    }
+
+    pub fn get_def_color_scheme(&self) -> &ColorScheme {
+        &self.def_color_scheme
+    }
+
+    pub fn get_notejump_speed(&self) -> f32 {
+        self.notejump_speed
+    }
+
+    pub fn get_notejump_beatoffset(&self) -> f32 {
+        self.notejump_beatoffset
+    }
+
+    pub fn get_environment_name_index(&self) -> u32 {
+        self.environment_name_index
+    }
+
+    pub fn get_mappers(&self) -> &[String] {
+        &self.mappers
+    }
+
+    pub fn get_lighters(&self) -> &[String] {
+        &self.lighters
+    }
+
+    pub fn get_songcore_info(&self) -> &SongCoreInfo {
+        &self.songcore_info
+    }
+
+    // The beatmap is played incorrectly without the mods of these requirements, some of them
+    // are supported only with the per-note custom data applied.
+
+    pub fn get_unsupported_requirements(&self, custom_data: bool) -> Vec<&str> {
+        self.songcore_info.requirements.iter().map(String::as_str).filter(|requirement| !songdef::is_requirement_supported(requirement, custom_data)).collect()
+    }
+
+    // Calculate the jump of the notes with the given seconds per beat, the reaction time
+    // of the beatmap can be overridden.
+
+    pub fn calc_jump_info(&self, spb: f32, reaction_time_opt: Option<f32>) -> JumpInfo {
+        // If not specified, then the default of the difficulty is used.
+
+        let notejump_speed = if self.notejump_speed > 0.0 {
+            self.notejump_speed
+        } else {
+            match self.difficulty {
+                SongDifficulty::Easy | SongDifficulty::Normal | SongDifficulty::Hard => 10.0,
+                SongDifficulty::Expert => 12.0,
+                SongDifficulty::ExpertPlus => 16.0,
+            }
+        };
+
+        let half_jump_duration = if let Some(reaction_time) = reaction_time_opt {
+            reaction_time
+        } else {
+            // The half jump duration is halved until the half jump distance is short enough.
+
+            let mut half_jump_duration = HALF_JUMP_DURATION;
+
+            while notejump_speed * spb * half_jump_duration > HALF_JUMP_DIST_MAX {
+                half_jump_duration /= 2.0;
+            }
+
+            (half_jump_duration + self.notejump_beatoffset).max(HALF_JUMP_DURATION_MIN) * spb
+        };
+
+        JumpInfo {
+            notejump_speed,
+            half_jump_duration,
+        }
+    }
+}
+
+// Additional info of the beatmap, which is defined by the SongCore mod. The requirements
+// are the mods, which are needed for playing the beatmap.
+
+#[derive(Default)]
+pub struct SongCoreInfo {
+    label_opt: Option<String>,
+    requirements: Box<[String]>,
+    suggestions: Box<[String]>,
+    warnings: Box<[String]>,
+    information: Box<[String]>,
+}
+
+impl SongCoreInfo {
+    pub fn new(label_opt: Option<String>, requirements: Box<[String]>, suggestions: Box<[String]>, warnings: Box<[String]>, information: Box<[String]>) -> Self {
+        Self {
+            label_opt,
+            requirements,
+            suggestions,
+            warnings,
+            information,
+        }
+    }
+
+    pub fn get_label(&self) -> Option<&str> {
+        self.label_opt.as_deref()
+    }
+
+    pub fn get_requirements(&self) -> &[String] {
+        &self.requirements
+    }
+
+    pub fn get_suggestions(&self) -> &[String] {
+        &self.suggestions
+    }
+
+    pub fn get_warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn get_information(&self) -> &[String] {
+        &self.information
+    }
+
+    fn is_empty(&self) -> bool {
+        self.label_opt.is_none() && self.requirements.is_empty() && self.suggestions.is_empty() && self.warnings.is_empty() && self.information.is_empty()
+    }
+
+    // Raw lists are in the order of: requirements, suggestions, warnings, information.
+
+    fn from_raw(label_opt: Option<String>, lists: [Option<Vec<String>>; 4]) -> Self {
+        let [requirements, suggestions, warnings, information] = lists.map(|list_opt| list_opt.unwrap_or_default().into_boxed_slice());
+        Self::new(label_opt, requirements, suggestions, warnings, information)
+    }
+
+    fn to_raw(&self) -> (Option<String>, [Option<Vec<String>>; 4]) {
+        let lists = [&self.requirements, &self.suggestions, &self.warnings, &self.information].map(|list| (!list.is_empty()).then(|| list.to_vec()));
+        (self.label_opt.clone(), lists)
+    }
+}
+
+// The notes are spawned half_jump_duration before they reach the player, which is
+// the same as the reaction time.
+
+pub struct JumpInfo {
+    notejump_speed: f32, // [m/s]
+    half_jump_duration: f32, // [s]
+}
+
+impl JumpInfo {
+    pub fn get_notejump_speed(&self) -> f32 {
+        self.notejump_speed
+    }
+
+    pub fn get_half_jump_duration(&self) -> f32 {
+        self.half_jump_duration
+    }
+
+    pub fn get_half_jump_dist(&self) -> f32 {
+        self.notejump_speed * self.half_jump_duration
+    }
+
+    pub fn get_jump_dist(&self) -> f32 {
+        2.0 * self.get_half_jump_dist()
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct SongInfo_V2 {
+    #[serde(rename = "_songAuthorName")]
+    author: String,
+    #[serde(rename = "_songName")]
+    title: String,
+    #[serde(rename = "_songSubName")]
+    sub_title: String,
+    #[serde(rename = "_levelAuthorName", skip_serializing_if = "Option::is_none")]
+    level_author: Option<String>,
+
+    #[serde(rename = "_songFilename")]
+    song_filename: String,
+    #[serde(rename = "_coverImageFilename", skip_serializing_if = "Option::is_none")]
+    cover_filename: Option<String>,
+    #[serde(rename = "_beatsPerMinute")]
+    bpm: f32,
+    #[serde(rename = "_previewStartTime", skip_serializing_if = "Option::is_none")]
+    preview_start: Option<f32>,
+    #[serde(rename = "_previewDuration", skip_serializing_if = "Option::is_none")]
+    preview_duration: Option<f32>,
+    #[serde(rename = "_songTimeOffset", skip_serializing_if = "Option::is_none")]
+    song_time_offset: Option<f32>,
+    #[serde(rename = "_shuffle", skip_serializing_if = "Option::is_none")]
+    shuffle: Option<f32>,
+    #[serde(rename = "_shufflePeriod", skip_serializing_if = "Option::is_none")]
+    shuffle_period: Option<f32>,
+
+    #[serde(rename = "_environmentName", skip_serializing_if = "Option::is_none")]
+    environment_name: Option<String>,
+    #[serde(rename = "_allDirectionsEnvironmentName", skip_serializing_if = "Option::is_none")]
+    all_directions_environment_name: Option<String>,
+    #[serde(rename = "_environmentNames", skip_serializing_if = "Option::is_none")]
+    environment_names: Option<Vec<String>>, // Since 2.1.0
+
+    #[serde(rename = "_colorSchemes", skip_serializing_if = "Option::is_none")]
+    color_schemes: Option<Vec<SongInfo_V2_ColorScheme>>,
+
+    #[serde(rename = "_difficultyBeatmapSets")]
+    beatmap_info_sets: Vec<SongInfo_V2_BeatmapInfoSet>,
+}
+
+impl Validate for SongInfo_V2 {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_positive(path, "_beatsPerMinute", self.bpm);
+
+        for (field, value_opt) in [("_previewStartTime", self.preview_start), ("_previewDuration", self.preview_duration), ("_songTimeOffset", self.song_time_offset), ("_shuffle", self.shuffle), ("_shufflePeriod", self.shuffle_period)] {
+            if let Some(value) = value_opt {
+                validator.check_finite(path, field, value);
+            }
+        }
+
+        validator.validate(path, "_colorSchemes", &self.color_schemes);
+        validator.validate(path, "_difficultyBeatmapSets", &self.beatmap_info_sets);
+    }
+}
+
+impl SongInfo_V2 {
+    fn build(self, asset_mgr: AssetManagerRc) -> Result<SongInfo> {
+        let mut color_schemes = Vec::new();
+        if let Some(raw_color_schemes) = self.color_schemes {
+            for raw_color_scheme in raw_color_schemes {
+                let inner = raw_color_scheme.inner;
+                let color_l = inner.color_l;
+                let color_r = inner.color_r;
+                let mut color_scheme = ColorScheme {
+                    color_l: Color::from_srgb_float(color_l.r, color_l.g, color_l.b),
+                    color_r: Color::from_srgb_float(color_r.r, color_r.g, color_r.b),
+                    ..ColorScheme::default()
+                };
+
+                if let Some(color) = inner.env_color_l {
+                    color_scheme.env_color_l = Color::from_srgb_float(color.r, color.g, color.b);
+                }
+
+                if let Some(color) = inner.env_color_r {
+                    color_scheme.env_color_r = Color::from_srgb_float(color.r, color.g, color.b);
+                }
+
+                color_schemes.push(color_scheme);
+            }
+        }
+
+        let mut beatmap_infos = Vec::new();
+        for raw_beatmap_info_set in self.beatmap_info_sets {
+            let characteristic = raw_beatmap_info_set.characteristic;
+
+            for raw_beatmap_info in raw_beatmap_info_set.beatmap_infos {
+                let mut def_color_scheme = ColorScheme::default();
+                let mut songcore_info = SongCoreInfo::default();
+
+                if let Some(custom_data) = raw_beatmap_info.custom_data {
+                    if let Some(color) = custom_data.color_l {
+                        def_color_scheme.color_l = Color::from_srgb_float(color.r, color.g, color.b);
+                    }
+                    
+                    if let Some(color) = custom_data.color_r {
+                        def_color_scheme.color_r = Color::from_srgb_float(color.r, color.g, color.b);
+                    }
+
+                    if let Some(color) = custom_data.env_color_l {
+                        def_color_scheme.env_color_l = Color::from_srgb_float(color.r, color.g, color.b);
+                    }
+
+                    if let Some(color) = custom_data.env_color_r {
+                        def_color_scheme.env_color_r = Color::from_srgb_float(color.r, color.g, color.b);
+                    }
+
+                    songcore_info = SongCoreInfo::from_raw(custom_data.label, [custom_data.requirements, custom_data.suggestions, custom_data.warnings, custom_data.information]);
+                }
+
+                let beatmap_info = BeatmapInfo::new(Arc::clone(&asset_mgr), characteristic, raw_beatmap_info.difficulty, raw_beatmap_info.color_scheme_index_opt, def_color_scheme, raw_beatmap_info.filename, None, raw_beatmap_info.notejump_speed, raw_beatmap_info.notejump_beatoffset, raw_beatmap_info.environment_name_index_opt, Vec::new(), Vec::new(), songcore_info);
+                beatmap_infos.push(beatmap_info);
+            }
+        }
+
+        // Before 2.1.0, there is a single environment.
+
+        let environment_names = self.environment_names.or_else(|| self.environment_name.map(|environment_name| vec![environment_name])).unwrap_or_default();
+
+        let metadata = SongMetadata {
+            level_author: self.level_author.unwrap_or_default(),
+            cover_filename_opt: self.cover_filename,
+            preview_start: self.preview_start.unwrap_or(0.0),
+            preview_duration: self.preview_duration.unwrap_or(0.0),
+            song_time_offset: self.song_time_offset.unwrap_or(0.0),
+            shuffle: self.shuffle.unwrap_or(0.0),
+            shuffle_period: self.shuffle_period.unwrap_or(0.0),
+            environment_names: environment_names.into_boxed_slice(),
+            all_directions_environment_name_opt: self.all_directions_environment_name,
+        };
+
+        Ok(SongInfo::new(asset_mgr, self.author, self.title, self.sub_title, self.song_filename, BPMSelector::Fixed(self.bpm), metadata, color_schemes, beatmap_infos))
+    }
+
+    fn from_song_info(song_info: &SongInfo, bpm: f32) -> Self {
+        let color_schemes = Vec::from_iter(song_info.color_schemes.iter().map(|color_scheme| SongInfo_V2_ColorScheme {
+            inner: SongInfo_V2_ColorScheme_Inner {
+                color_l: FloatColor::from_color(&color_scheme.color_l),
+                color_r: FloatColor::from_color(&color_scheme.color_r),
+                env_color_l: Some(FloatColor::from_color(&color_scheme.env_color_l)),
+                env_color_r: Some(FloatColor::from_color(&color_scheme.env_color_r)),
+            },
+        }));
+
+        // The beatmaps are grouped by their characteristic, in the order of their appearance.
+
+        let mut beatmap_info_sets: Vec<SongInfo_V2_BeatmapInfoSet> = Vec::new();
+
+        for beatmap_info in &song_info.beatmap_infos {
+            let raw_beatmap_info = SongInfo_V2_BeatmapInfo {
+                difficulty: beatmap_info.difficulty,
+                color_scheme_index_opt: beatmap_info.color_scheme_index_opt.map(|color_scheme_index| color_scheme_index as i32),
+                filename: beatmap_info.filename.clone(),
+                notejump_speed: beatmap_info.notejump_speed,
+                notejump_beatoffset: beatmap_info.notejump_beatoffset,
+                environment_name_index_opt: Some(beatmap_info.environment_name_index as i32),
+                custom_data: SongInfo_V2_BeatmapInfo_CustomData::from_beatmap_info(beatmap_info),
+            };
+
+            if let Some(beatmap_info_set) = beatmap_info_sets.iter_mut().find(|beatmap_info_set| beatmap_info_set.characteristic == beatmap_info.characteristic) {
+                beatmap_info_set.beatmap_infos.push(raw_beatmap_info);
+            } else {
+                beatmap_info_sets.push(SongInfo_V2_BeatmapInfoSet {
+                    characteristic: beatmap_info.characteristic,
+                    beatmap_infos: vec![raw_beatmap_info],
+                });
+            }
+        }
+
+        let metadata = &song_info.metadata;
+        let environment_names = &metadata.environment_names;
+
+        Self {
+            author: song_info.author.clone(),
+            title: song_info.title.clone(),
+            sub_title: song_info.sub_title.clone(),
+            level_author: Some(metadata.level_author.clone()),
+            song_filename: song_info.song_filename[1..].to_string(),
+            cover_filename: metadata.cover_filename_opt.clone(),
+            bpm,
+            preview_start: Some(metadata.preview_start),
+            preview_duration: Some(metadata.preview_duration),
+            song_time_offset: Some(metadata.song_time_offset),
+            shuffle: Some(metadata.shuffle),
+            shuffle_period: Some(metadata.shuffle_period),
+            environment_name: environment_names.first().cloned(),
+            all_directions_environment_name: metadata.all_directions_environment_name_opt.clone(),
+            environment_names: (!environment_names.is_empty()).then(|| environment_names.to_vec()),
+            color_schemes: Some(color_schemes),
+            beatmap_info_sets,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct SongInfo_V2_ColorScheme {
+    #[serde(rename = "colorScheme")]
+    inner: SongInfo_V2_ColorScheme_Inner,
+}
+
+impl Validate for SongInfo_V2_ColorScheme {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.validate(path, "colorScheme", &self.inner);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct SongInfo_V2_ColorScheme_Inner {
+    #[serde(rename = "saberAColor")]
+    color_l: FloatColor,
+    #[serde(rename = "saberBColor")]
+    color_r: FloatColor,
+    #[serde(rename = "environmentColor0", skip_serializing_if = "Option::is_none")]
+    env_color_l: Option<FloatColor>,
+    #[serde(rename = "environmentColor1", skip_serializing_if = "Option::is_none")]
+    env_color_r: Option<FloatColor>,
+}
+
+impl Validate for SongInfo_V2_ColorScheme_Inner {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.validate(path, "saberAColor", &self.color_l);
+        validator.validate(path, "saberBColor", &self.color_r);
+        validator.validate(path, "environmentColor0", &self.env_color_l);
+        validator.validate(path, "environmentColor1", &self.env_color_r);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct SongInfo_V2_BeatmapInfoSet {
+    #[serde(rename = "_beatmapCharacteristicName")]
+    characteristic: SongCharacteristic,
+    #[serde(rename = "_difficultyBeatmaps")]
+    beatmap_infos: Vec<SongInfo_V2_BeatmapInfo>,
+}
+
+impl Validate for SongInfo_V2_BeatmapInfoSet {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.validate(path, "_difficultyBeatmaps", &self.beatmap_infos);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct SongInfo_V2_BeatmapInfo {
+    #[serde(rename = "_difficulty")]
+    difficulty: SongDifficulty,
+    #[serde(rename = "_beatmapColorSchemeIdx", skip_serializing_if = "Option::is_none")]
+    color_scheme_index_opt: Option<i32>,
+    #[serde(rename = "_beatmapFilename")]
+    filename: String,
+    #[serde(rename = "_noteJumpMovementSpeed")]
+    notejump_speed: f32,
+    #[serde(rename = "_noteJumpStartBeatOffset")]
+    notejump_beatoffset: f32,
+    #[serde(rename = "_environmentNameIdx", skip_serializing_if = "Option::is_none")]
+    environment_name_index_opt: Option<i32>, // Since 2.1.0
+    #[serde(rename = "_customData", skip_serializing_if = "Option::is_none")]
+    custom_data: Option<SongInfo_V2_BeatmapInfo_CustomData>,
+}
+
+impl Validate for SongInfo_V2_BeatmapInfo {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        // Note jump speed of 0 (or less) is replaced by the default of the difficulty.
+
+        validator.check_non_negative(path, "_noteJumpMovementSpeed", self.notejump_speed);
+        validator.check_finite(path, "_noteJumpStartBeatOffset", self.notejump_beatoffset);
+        validator.validate(path, "_customData", &self.custom_data);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct SongInfo_V2_BeatmapInfo_CustomData {
+    #[serde(rename = "_colorLeft", skip_serializing_if = "Option::is_none")]
+    color_l: Option<FloatColor>,
+    #[serde(rename = "_colorRight", skip_serializing_if = "Option::is_none")]
+    color_r: Option<FloatColor>,
+    #[serde(rename = "_envColorLeft", skip_serializing_if = "Option::is_none")]
+    env_color_l: Option<FloatColor>,
+    #[serde(rename = "_envColorRight", skip_serializing_if = "Option::is_none")]
+    env_color_r: Option<FloatColor>,
+    #[serde(rename = "_difficultyLabel", skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(rename = "_requirements", skip_serializing_if = "Option::is_none")]
+    requirements: Option<Vec<String>>,
+    #[serde(rename = "_suggestions", skip_serializing_if = "Option::is_none")]
+    suggestions: Option<Vec<String>>,
+    #[serde(rename = "_warnings", skip_serializing_if = "Option::is_none")]
+    warnings: Option<Vec<String>>,
+    #[serde(rename = "_information", skip_serializing_if = "Option::is_none")]
+    information: Option<Vec<String>>,
+}
+
+impl SongInfo_V2_BeatmapInfo_CustomData {
+    // Only the colors, which are different from the default, are written.
+
+    fn from_beatmap_info(beatmap_info: &BeatmapInfo) -> Option<Self> {
+        let color_scheme = &beatmap_info.def_color_scheme;
+        let def_color_scheme = ColorScheme::default();
+        let get_color = |color: &Color, def_color: &Color| (color.0 != def_color.0).then(|| FloatColor::from_color(color));
+
+        let songcore_info = &beatmap_info.songcore_info;
+        let (label, [requirements, suggestions, warnings, information]) = songcore_info.to_raw();
+
+        let custom_data = Self {
+            color_l: get_color(&color_scheme.color_l, &def_color_scheme.color_l),
+            color_r: get_color(&color_scheme.color_r, &def_color_scheme.color_r),
+            env_color_l: get_color(&color_scheme.env_color_l, &def_color_scheme.env_color_l),
+            env_color_r: get_color(&color_scheme.env_color_r, &def_color_scheme.env_color_r),
+            label,
+            requirements,
+            suggestions,
+            warnings,
+            information,
+        };
+
+        (!color_scheme.is_same(&def_color_scheme) || !songcore_info.is_empty()).then_some(custom_data)
+    }
+}
+
+impl Validate for SongInfo_V2_BeatmapInfo_CustomData {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.validate(path, "_colorLeft", &self.color_l);
+        validator.validate(path, "_colorRight", &self.color_r);
+        validator.validate(path, "_envColorLeft", &self.env_color_l);
+        validator.validate(path, "_envColorRight", &self.env_color_r);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct SongInfo_V4 {
+    song: SongInfo_V4_Song,
+    audio: SongInfo_V4_Audio,
+    #[serde(rename = "coverImageFilename", skip_serializing_if = "Option::is_none")]
+    cover_filename: Option<String>,
+    #[serde(rename = "environmentNames", skip_serializing_if = "Option::is_none")]
+    environment_names: Option<Vec<String>>,
+    #[serde(rename = "colorSchemes", skip_serializing_if = "Option::is_none")]
+    color_schemes: Option<Vec<SongInfo_V4_ColorScheme>>,
+    #[serde(rename = "difficultyBeatmaps")]
+    beatmap_infos: Vec<SongInfo_V4_BeatmapInfo>,
+}
+
+impl Validate for SongInfo_V4 {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.validate(path, "audio", &self.audio);
+        validator.validate(path, "difficultyBeatmaps", &self.beatmap_infos);
+    }
+}
+
+impl SongInfo_V4 {
+    fn build(self, asset_mgr: AssetManagerRc) -> Result<SongInfo> {
+        let bpm_selector = if let Some(filename) = self.audio.bpmmap_filename {
+            BPMSelector::Mapped(filename)
+        } else if let Some(bpm) = self.audio.bpm {
+            BPMSelector::Fixed(bpm)
+        } else {
+            return Err(Error::Build("Either bpm or audioDataFilename is required".to_string()));
+        };
+
+        let mut color_schemes = Vec::new();
+        if let Some(raw_color_schemes) = self.color_schemes {
+            for raw_color_scheme in raw_color_schemes {
+                let mut color_scheme = ColorScheme {
+                    color_l: raw_color_scheme.color_l,
+                    color_r: raw_color_scheme.color_r,
+                    ..ColorScheme::default()
+                };
+
+                if let Some(color) = raw_color_scheme.env_color_l {
+                    color_scheme.env_color_l = color;
+                }
+
+                if let Some(color) = raw_color_scheme.env_color_r {
+                    color_scheme.env_color_r = color;
+                }
+
+                color_schemes.push(color_scheme);
+            }
+        }
+
+        // The mappers of the song are collected from the beatmaps.
+
+        let mut level_authors: Vec<&str> = Vec::new();
+        for mapper in self.beatmap_infos.iter().filter_map(|raw_beatmap_info| raw_beatmap_info.authors.as_ref()).flat_map(|authors| &authors.mappers) {
+            if !level_authors.contains(&mapper.as_str()) {
+                level_authors.push(mapper);
+            }
+        }
+
+        let metadata = SongMetadata {
+            level_author: level_authors.join(", "),
+            cover_filename_opt: self.cover_filename,
+            preview_start: self.audio.preview_start.unwrap_or(0.0),
+            preview_duration: self.audio.preview_duration.unwrap_or(0.0),
+            environment_names: self.environment_names.unwrap_or_default().into_boxed_slice(),
+            ..SongMetadata::default()
+        };
+
+        let mut beatmap_infos = Vec::new();
+        for raw_beatmap_info in self.beatmap_infos {
+            let (mappers, lighters) = raw_beatmap_info.authors.map(|authors| (authors.mappers, authors.lighters)).unwrap_or_default();
+            let songcore_info = raw_beatmap_info.custom_data.map(|custom_data| {
+                SongCoreInfo::from_raw(custom_data.label, [custom_data.requirements, custom_data.suggestions, custom_data.warnings, custom_data.information])
+            }).unwrap_or_default();
+
+            let beatmap_info = BeatmapInfo::new(Arc::clone(&asset_mgr), raw_beatmap_info.characteristic, raw_beatmap_info.difficulty, raw_beatmap_info.color_scheme_index_opt, ColorScheme::default(), raw_beatmap_info.filename, raw_beatmap_info.lightshow_filename_opt, raw_beatmap_info.notejump_speed, raw_beatmap_info.notejump_beatoffset, raw_beatmap_info.environment_name_index_opt, mappers, lighters, songcore_info);
+            beatmap_infos.push(beatmap_info);
+        }
+
+        Ok(SongInfo::new(asset_mgr, self.song.author, self.song.title, self.song.sub_title, self.audio.song_filename, bpm_selector, metadata, color_schemes, beatmap_infos))
+    }
+
+    fn from_song_info(song_info: &SongInfo, bpm: f32, bpmmap_filename_opt: Option<String>, lightshow_filename_opts: Vec<Option<String>>) -> Self {
+        let metadata = &song_info.metadata;
+        let mut color_schemes = Vec::from_iter(song_info.color_schemes.iter().map(SongInfo_V4_ColorScheme::from_color_scheme));
+
+        // There are no default colors of the beatmaps in V4, they are added as color schemes.
+
+        let beatmap_infos = Vec::from_iter(song_info.beatmap_infos.iter().zip(lightshow_filename_opts).map(|(beatmap_info, lightshow_filename_opt)| {
+            let color_scheme_index_opt = beatmap_info.color_scheme_index_opt.map(|color_scheme_index| color_scheme_index as i32).or_else(|| {
+                (!beatmap_info.def_color_scheme.is_same(&ColorScheme::default())).then(|| {
+                    color_schemes.push(SongInfo_V4_ColorScheme::from_color_scheme(&beatmap_info.def_color_scheme));
+                    color_schemes.len() as i32 - 1
+                })
+            });
+
+            // Without mappers of the beatmap, the mappers of the song are used.
+
+            let mappers = if beatmap_info.mappers.is_empty() && !metadata.level_author.is_empty() {
+                vec![metadata.level_author.clone()]
+            } else {
+                beatmap_info.mappers.to_vec()
+            };
+
+            SongInfo_V4_BeatmapInfo {
+                characteristic: beatmap_info.characteristic,
+                difficulty: beatmap_info.difficulty,
+                authors: Some(SongInfo_V4_BeatmapAuthors {
+                    mappers,
+                    lighters: beatmap_info.lighters.to_vec(),
+                }),
+                environment_name_index_opt: Some(beatmap_info.environment_name_index as i32),
+                color_scheme_index_opt,
+                filename: beatmap_info.filename.clone(),
+                lightshow_filename_opt,
+                notejump_speed: beatmap_info.notejump_speed,
+                notejump_beatoffset: beatmap_info.notejump_beatoffset,
+                custom_data: SongInfo_V4_BeatmapInfo_CustomData::from_songcore_info(&beatmap_info.songcore_info),
+            }
+        }));
+
+        Self {
+            song: SongInfo_V4_Song {
+                author: song_info.author.clone(),
+                title: song_info.title.clone(),
+                sub_title: song_info.sub_title.clone(),
+            },
+            audio: SongInfo_V4_Audio {
+                song_filename: song_info.song_filename[1..].to_string(),
+                bpm: Some(bpm),
+                bpmmap_filename: bpmmap_filename_opt,
+                preview_start: Some(metadata.preview_start),
+                preview_duration: Some(metadata.preview_duration),
+            },
+            cover_filename: metadata.cover_filename_opt.clone(),
+            environment_names: Some(metadata.environment_names.to_vec()),
+            color_schemes: Some(color_schemes),
+            beatmap_infos,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct SongInfo_V4_Song {
+    author: String,
+    title: String,
+    #[serde(rename = "subTitle")]
+    sub_title: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct SongInfo_V4_Audio {
+    #[serde(rename = "songFilename")]
+    song_filename: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bpm: Option<f32>,
+    #[serde(rename = "audioDataFilename", skip_serializing_if = "Option::is_none")]
+    bpmmap_filename: Option<String>,
+    #[serde(rename = "previewStartTime", skip_serializing_if = "Option::is_none")]
+    preview_start: Option<f32>,
+    #[serde(rename = "previewDuration", skip_serializing_if = "Option::is_none")]
+    preview_duration: Option<f32>,
+}
+
+impl Validate for SongInfo_V4_Audio {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        if let Some(bpm) = self.bpm {
+            validator.check_positive(path, "bpm", bpm);
+        }
+
+        for (field, value_opt) in [("previewStartTime", self.preview_start), ("previewDuration", self.preview_duration)] {
+            if let Some(value) = value_opt {
+                validator.check_finite(path, field, value);
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct SongInfo_V4_ColorScheme {
+    #[serde(rename = "saberAColor")]
+    color_l: Color,
+    #[serde(rename = "saberBColor")]
+    color_r: Color,
+    #[serde(rename = "environmentColor0", skip_serializing_if = "Option::is_none")]
+    env_color_l: Option<Color>,
+    #[serde(rename = "environmentColor1", skip_serializing_if = "Option::is_none")]
+    env_color_r: Option<Color>,
+}
+
+impl SongInfo_V4_ColorScheme {
+    fn from_color_scheme(color_scheme: &ColorScheme) -> Self {
+        Self {
+            color_l: color_scheme.color_l,
+            color_r: color_scheme.color_r,
+            env_color_l: Some(color_scheme.env_color_l),
+            env_color_r: Some(color_scheme.env_color_r),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct SongInfo_V4_BeatmapInfo {
+    characteristic: SongCharacteristic,
+    difficulty: SongDifficulty,
+    #[serde(rename = "beatmapAuthors", skip_serializing_if = "Option::is_none")]
+    authors: Option<SongInfo_V4_BeatmapAuthors>,
+    #[serde(rename = "environmentNameIdx", skip_serializing_if = "Option::is_none")]
+    environment_name_index_opt: Option<i32>,
+    #[serde(rename = "beatmapColorSchemeIdx", skip_serializing_if = "Option::is_none")]
+    color_scheme_index_opt: Option<i32>,
+    #[serde(rename = "beatmapDataFilename")]
+    filename: String,
+    #[serde(rename = "lightshowDataFilename", skip_serializing_if = "Option::is_none")]
+    lightshow_filename_opt: Option<String>,
+    #[serde(rename = "noteJumpMovementSpeed")]
+    notejump_speed: f32,
+    #[serde(rename = "noteJumpStartBeatOffset")]
+    notejump_beatoffset: f32,
+    #[serde(rename = "customData", skip_serializing_if = "Option::is_none")]
+    custom_data: Option<SongInfo_V4_BeatmapInfo_CustomData>,
+}
+
+impl Validate for SongInfo_V4_BeatmapInfo {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_non_negative(path, "noteJumpMovementSpeed", self.notejump_speed);
+        validator.check_finite(path, "noteJumpStartBeatOffset", self.notejump_beatoffset);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct SongInfo_V4_BeatmapAuthors {
+    #[serde(default)]
+    mappers: Vec<String>,
+    #[serde(default)]
+    lighters: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct SongInfo_V4_BeatmapInfo_CustomData {
+    #[serde(rename = "difficultyLabel", skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    requirements: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suggestions: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warnings: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    information: Option<Vec<String>>,
+}
+
+impl SongInfo_V4_BeatmapInfo_CustomData {
+    fn from_songcore_info(songcore_info: &SongCoreInfo) -> Option<Self> {
+        let (label, [requirements, suggestions, warnings, information]) = songcore_info.to_raw();
+
+        let custom_data = Self {
+            label,
+            requirements,
+            suggestions,
+            warnings,
+            information,
+        };
+
+        (!songcore_info.is_empty()).then_some(custom_data)
+    }
+}
+
+// BPMMap
+
+pub struct BPMMap {
+    sample_rate: u32,
+    ranges: Box<[BPMRange]>,
+    diagnostics: Box<[Diagnostic]>,
+}
+
+impl BPMMap {
+    fn load<S: AsRef<str>>(asset_mgr: AssetManagerRc, filename: S) -> Result<Self> {
+        let filename = filename.as_ref();
+        let asset_file = asset_mgr.open(&format!("/{}", filename))?;
+        let buf = asset_file.read_str()?;
+
+        let (mut bpm_map, diagnostics) = match get_version(&buf)?.as_str() {
+            "2.0.0" => {
+                let bpmmap: BPMMap_V2 = serde_json::from_str(&buf)?;
+                let diagnostics = Validator::run(filename, &bpmmap)?;
+                (bpmmap.build(), diagnostics)
+            },
+            "4.0.0" => {
+                let bpmmap: BPMMap_V4 = serde_json::from_str(&buf)?;
+                let diagnostics = Validator::run(filename, &bpmmap)?;
+                (bpmmap.build(), diagnostics)
+            },
+            version => return Err(Error::Build(format!("Unsupported bpmmap version: {}", version)))
+        };
+
+        bpm_map.diagnostics = diagnostics;
+
+        Ok(bpm_map)
+    }
+
+    fn new(sample_rate: u32, mut ranges: Vec<BPMRange>) -> Self {
+        ranges.sort_by(|range1, range2| range1.bpm.start.total_cmp(&range2.bpm.start));
+
+        Self {
+            sample_rate,
+            ranges: ranges.into_boxed_slice(),
+            diagnostics: Box::from([]),
+        }
+    }
+
+    pub fn get_diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    // Creates a BPM map, which is covering the beat positions 0..end_bpm_pos.
+
+    fn from_bpm_changes(bpm: f32, bpm_changes: &[BPMChange], end_bpm_pos: f32) -> Self {
+        let get_sample_pos = |ts: f32| (ts * BPMMAP_SAMPLE_RATE as f32).round() as u32;
+
+        let mut ranges = Vec::new();
+        let mut start = 0.0;
+        let mut ts = 0.0;
+        let mut spb = 60.0 / bpm;
+
+        for (end, next_bpm_opt) in bpm_changes.iter().map(|bpm_change| (bpm_change.bpm_pos, Some(bpm_change.bpm))).chain([(end_bpm_pos, None)]) {
+            if end > start {
+                let end_ts = ts + (end - start) * spb;
+                ranges.push(BPMRange::new(get_sample_pos(ts)..get_sample_pos(end_ts), BPMMAP_SAMPLE_RATE, start..end));
+
+                start = end;
+                ts = end_ts;
+            }
+
+            if let Some(next_bpm) = next_bpm_opt {
+                spb = 60.0 / next_bpm;
+            }
+        }
+
+        Self::new(BPMMAP_SAMPLE_RATE, ranges)
+    }
+
+    // Returns the initial BPM and the BPM changes of the ranges (gaps between the ranges are lost).
+
+    fn to_bpm_changes(&self) -> Result<(f32, Vec<BPMChange>)> {
+        let mut bpm_opt: Option<f32> = None;
+        let mut last_bpm = 0.0;
+        let mut bpm_changes = Vec::new();
+
+        for range in self.ranges.iter().filter(|range| range.bpm.end > range.bpm.start && range.ts.end > range.ts.start) {
+            let bpm = 60.0 * (range.bpm.end - range.bpm.start) / (range.ts.end - range.ts.start);
+
+            if bpm_opt.is_none() {
+                bpm_opt = Some(bpm);
+            } else if (bpm - last_bpm).abs() > BPM_EPSILON {
+                bpm_changes.push(BPMChange::new(range.bpm.start, bpm)?);
+            }
+
+            last_bpm = bpm;
+        }
+
+        let bpm = bpm_opt.ok_or(Error::Build("BPM map without ranges".to_string()))?;
+
+        Ok((bpm, bpm_changes))
+    }
+}
+
+struct BPMRange {
+    sample_pos: Range<u32>,
+    ts: Range<f32>,
+    bpm: Range<f32>,
+}
+
+impl BPMRange {
+    fn new(sample_pos: Range<u32>, sample_rate: u32, bpm: Range<f32>) -> Self {
+        Self {
+            ts: sample_pos.start as f32 / sample_rate as f32..sample_pos.end as f32 / sample_rate as f32,
+            sample_pos,
+            bpm,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct BPMMap_V2 {
+    #[serde(rename = "_songFrequency")]
+    sample_rate: u32,
+    #[serde(rename = "_regions")]
+    ranges: Vec<BPMMap_V2_Range>,
+}
+
+impl Validate for BPMMap_V2 {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        if self.sample_rate == 0 {
+            validator.report(Severity::Error, path, "_songFrequency", "Sample rate is zero".to_string());
+        }
+
+        validator.validate(path, "_regions", &self.ranges);
+    }
+}
+
+impl BPMMap_V2 {
+    fn build(self) -> BPMMap {
+        let ranges = Vec::from_iter(self.ranges.into_iter().map(|range| BPMRange::new(range.start_sample_pos..range.end_sample_pos, self.sample_rate, range.start_bpm..range.end_bpm)));
+        BPMMap::new(self.sample_rate, ranges)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct BPMMap_V2_Range {
+    #[serde(rename = "_startSampleIndex")]
+    start_sample_pos: u32,
+    #[serde(rename = "_endSampleIndex")]
+    end_sample_pos: u32,
+    #[serde(rename = "_startBeat")]
+    start_bpm: f32,
+    #[serde(rename = "_endBeat")]
+    end_bpm: f32,
+}
+
+impl Validate for BPMMap_V2_Range {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validate_bpm_range(validator, path, ["_startSampleIndex", "_endSampleIndex", "_startBeat", "_endBeat"], self.start_sample_pos..self.end_sample_pos, self.start_bpm..self.end_bpm);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct BPMMap_V4 {
+    #[serde(rename = "songFrequency")]
+    sample_rate: u32,
+    #[serde(rename = "bpmData")]
+    ranges: Vec<BPMMap_V4_Range>,
+}
+
+impl BPMMap_V4 {
+    fn from_bpm_map(bpm_map: &BPMMap) -> Self {
+        Self {
+            sample_rate: bpm_map.sample_rate,
+            ranges: Vec::from_iter(bpm_map.ranges.iter().map(|range| BPMMap_V4_Range {
+                start_sample_pos: range.sample_pos.start,
+                end_sample_pos: range.sample_pos.end,
+                start_bpm: range.bpm.start,
+                end_bpm: range.bpm.end,
+            })),
+        }
+    }
+}
+
+impl Validate for BPMMap_V4 {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        if self.sample_rate == 0 {
+            validator.report(Severity::Error, path, "songFrequency", "Sample rate is zero".to_string());
+        }
+
+        validator.validate(path, "bpmData", &self.ranges);
+    }
+}
+
+impl BPMMap_V4 {
+    fn build(self) -> BPMMap {
+        let ranges = Vec::from_iter(self.ranges.into_iter().map(|range| BPMRange::new(range.start_sample_pos..range.end_sample_pos, self.sample_rate, range.start_bpm..range.end_bpm)));
+        BPMMap::new(self.sample_rate, ranges)
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct BPMMap_V4_Range {
+    #[serde(rename = "si")]
+    start_sample_pos: u32,
+    #[serde(rename = "ei")]
+    end_sample_pos: u32,
+    #[serde(rename = "sb")]
+    start_bpm: f32,
+    #[serde(rename = "eb")]
+    end_bpm: f32,
+}
+
+impl Validate for BPMMap_V4_Range {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validate_bpm_range(validator, path, ["si", "ei", "sb", "eb"], self.start_sample_pos..self.end_sample_pos, self.start_bpm..self.end_bpm);
+    }
+}
+
+// Ranges going backwards are ignored by the timing.
+
+fn validate_bpm_range(validator: &mut Validator, path: &str, fields: [&str; 4], sample_pos: Range<u32>, bpm: Range<f32>) {
+    let [start_sample_pos_field, end_sample_pos_field, start_bpm_field, end_bpm_field] = fields;
+
+    if sample_pos.end < sample_pos.start {
+        validator.report(Severity::Warning, path, end_sample_pos_field, format!("Before {}: {}", start_sample_pos_field, sample_pos.end));
+    }
+
+    let start_finite = validator.check_finite(path, start_bpm_field, bpm.start);
+    let end_finite = validator.check_finite(path, end_bpm_field, bpm.end);
+
+    if start_finite && end_finite && bpm.end < bpm.start {
+        validator.report(Severity::Warning, path, end_bpm_field, format!("Before {}: {}", start_bpm_field, bpm.end));
+    }
+}
+
+// Timing
+
+// Converts beat positions to timestamps (piecewise linear). If there is a BPM map, then it is
+// used, otherwise the fixed BPM is changed by the BPM changes of the beatmap.
+
+pub struct Timing {
+    segments: Box<[TimingSegment]>,
+}
+
+struct TimingSegment {
+    bpm_pos: Range<f32>,
+    spb: f32, // Seconds per beat
+    offset: f32, // ts = bpm_pos * spb + offset
+}
+
+impl Timing {
+    pub fn new(bpm_info: &BPMInfo, bpm_changes: &[BPMChange]) -> Self {
+        let segments = match bpm_info {
+            BPMInfo::Fixed(bpm) => {
+                // The first segment is open at both ends, it reaches ts == 0 at bpm_pos == 0.
+
+                let mut segments = Vec::new();
+                let mut start = f32::NEG_INFINITY;
+                let mut spb = 60.0 / bpm;
+                let mut offset = 0.0;
+
+                for bpm_change in bpm_changes {
+                    let bpm_pos = bpm_change.bpm_pos;
+                    let ts = bpm_pos * spb + offset;
+
+                    segments.push(TimingSegment {
+                        bpm_pos: start..bpm_pos,
+                        spb,
+                        offset,
+                    });
+
+                    start = bpm_pos;
+                    spb = 60.0 / bpm_change.bpm;
+                    offset = ts - bpm_pos * spb;
+                }
+
+                segments.push(TimingSegment {
+                    bpm_pos: start..f32::INFINITY,
+                    spb,
+                    offset,
+                });
+
+                segments
+            },
+            BPMInfo::Mapped(bpm_map) => {
+                Vec::from_iter(bpm_map.ranges.iter().filter(|range| range.bpm.end > range.bpm.start).map(|range| {
+                    let spb = (range.ts.end - range.ts.start) / (range.bpm.end - range.bpm.start);
+
+                    TimingSegment {
+                        bpm_pos: range.bpm.clone(),
+                        spb,
+                        offset: range.ts.start - range.bpm.start * spb,
+                    }
+                }))
+            },
+        };
+
+        Self {
+            segments: segments.into_boxed_slice(),
+        }
+    }
+
+    // Shifts the timestamps, e.g. by the song time offset.
+
+    pub fn with_offset(self, offset: f32) -> Self {
+        let segments = self.segments.into_iter().map(|segment| TimingSegment {
+            offset: segment.offset + offset,
+            ..segment
+        }).collect();
+
+        Self {
+            segments,
+        }
+    }
+
+    pub fn get_ts(&self, bpm_pos: f32) -> Option<f32> {
+        self.get_segment(bpm_pos).map(|segment| bpm_pos * segment.spb + segment.offset)
+    }
+
+    pub fn get_spb(&self, bpm_pos: f32) -> Option<f32> {
+        self.get_segment(bpm_pos).map(|segment| segment.spb)
+    }
+
+    fn get_segment(&self, bpm_pos: f32) -> Option<&TimingSegment> {
+        let index = self.segments.partition_point(|segment| segment.bpm_pos.start <= bpm_pos); // First index, where segment.bpm_pos.start > bpm_pos
+        if index == 0 {
+            return None;
+        }
+
+        let segment = &self.segments[index - 1];
+        if bpm_pos >= segment.bpm_pos.end {
+            return None;
+        }
+
+        Some(segment)
+    }
+}
+
+// Beatmap
+
+pub struct Beatmap {
+    notes: Box<[Note]>,
+    bombs: Box<[Bomb]>,
+    obstacles: Box<[Obstacle]>,
+    arcs: Box<[SongArc]>,
+    chains: Box<[Chain]>,
+    bpm_changes: Box<[BPMChange]>,
+    light_events: Box<[LightEvent]>,
+    rotation_events: Box<[RotationEvent]>,
+    rotation_steps: Box<[RotationStep]>,
+    diagnostics: Box<[Diagnostic]>,
+}
+
+// The accumulated rotation from the given beat position.
+
+struct RotationStep {
+    bpm_pos: f32,
+    rotation: f32,
+}
+
+impl Beatmap {
+    fn load<S: AsRef<str>>(asset_mgr: AssetManagerRc, filename: S) -> Result<Self> {
+        let filename = filename.as_ref();
+        let asset_file = asset_mgr.open(&format!("/{}", filename))?;
+        let buf = asset_file.read_str()?;
+
+        let (mut beatmap, diagnostics) = match get_version(&buf)?.as_str() {
+            "2.0.0" | "2.2.0" => {
+                let beatmap: Beatmap_V2 = serde_json::from_str(&buf)?;
+                let diagnostics = Validator::run(filename, &beatmap)?;
+                (beatmap.build()?, diagnostics)
+            },
+            "3.0.0" | "3.2.0" | "3.3.0" => {
+                let beatmap: Beatmap_V3 = serde_json::from_str(&buf)?;
+                let diagnostics = Validator::run(filename, &beatmap)?;
+                (beatmap.build()?, diagnostics)
+            },
+            "4.0.0" | "4.1.0" => {
+                let beatmap: Beatmap_V4 = serde_json::from_str(&buf)?;
+                let diagnostics = Validator::run(filename, &beatmap)?;
+                (beatmap.build()?, diagnostics)
+            },
+            version => return Err(Error::Build(format!("Unsupported beatmap version: {}", version)))
+        };
+
+        beatmap.diagnostics = diagnostics;
+
+        Ok(beatmap)
+    }
+
+    #[cfg(feature = "test")]
+    fn test() -> Result<Self> {
+        let mut cut_dir_it = [
+            NoteCutDir::Up,
+            NoteCutDir::Down,
+            NoteCutDir::Left,
+            NoteCutDir::Right,
+            NoteCutDir::UpLeft,
+            NoteCutDir::UpRight,
+            NoteCutDir::DownLeft,
+            NoteCutDir::DownRight,
+            NoteCutDir::Any,
+        ].into_iter().cycle();
+
+        let mut notes = Vec::new();
+
+        for i in 0..100 {
+            let note = Note::new(i as f32, 2, 1, NoteType::Right, cut_dir_it.next().unwrap()).unwrap();
+            notes.push(note);
+        }
+        
+        Ok(Self {
+            notes: notes.into_boxed_slice(),
+            bombs: Box::from([]),
+            obstacles: Box::from([]),
+            arcs: Box::from([]),
+            chains: Box::from([]),
+            bpm_changes: Box::from([]),
+            light_events: Box::from([]),
+            rotation_events: Box::from([]),
+            rotation_steps: Box::from([]),
+            diagnostics: Box::from([]),
+        })
+    }
+
+    // Beatmap of notes only, for the tests.
+    #[cfg(test)]
+    pub fn from_notes(notes: Vec<Note>) -> Self {
+        Self::new(notes, Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(mut notes: Vec<Note>, mut bombs: Vec<Bomb>, mut obstacles: Vec<Obstacle>, mut arcs: Vec<SongArc>, mut chains: Vec<Chain>, mut bpm_changes: Vec<BPMChange>, mut light_events: Vec<LightEvent>, mut rotation_events: Vec<RotationEvent>) -> Self {
+        // Chain heads are usually present as color notes as well, these notes are replaced by the chains.
+        // The chains are sorted first, so that the heads around a note are found by binary search.
+
+        chains.sort_by(|chain1, chain2| chain1.head.bpm_pos.total_cmp(&chain2.head.bpm_pos));
+
+        notes.retain(|note| {
+            let start = chains.partition_point(|chain| chain.head.bpm_pos <= note.bpm_pos - BPM_POS_EPSILON);
+            !chains[start..].iter().take_while(|chain| chain.head.bpm_pos < note.bpm_pos + BPM_POS_EPSILON).any(|chain| chain.head.is_same(note))
+        });
+
+        notes.sort_by(|note1, note2| note1.bpm_pos.total_cmp(&note2.bpm_pos));
+        bombs.sort_by(|bomb1, bomb2| bomb1.bpm_pos.total_cmp(&bomb2.bpm_pos));
+        obstacles.sort_by(|obstacle1, obstacle2| obstacle1.bpm_pos.total_cmp(&obstacle2.bpm_pos));
+        arcs.sort_by(|arc1, arc2| arc1.head.bpm_pos.total_cmp(&arc2.head.bpm_pos));
+        bpm_changes.sort_by(|bpm_change1, bpm_change2| bpm_change1.bpm_pos.total_cmp(&bpm_change2.bpm_pos));
+        light_events.sort_by(|light_event1, light_event2| light_event1.bpm_pos.total_cmp(&light_event2.bpm_pos));
+        rotation_events.sort_by(|rotation_event1, rotation_event2| rotation_event1.bpm_pos.total_cmp(&rotation_event2.bpm_pos));
+
+        // Early rotations are applied to the objects at the same beat position, late rotations are
+        // applied only to the objects after it.
+
+        let mut rotation_step_infos = Vec::from_iter(rotation_events.iter().map(|rotation_event| {
+            let bpm_pos = match rotation_event.timing {
+                RotationTiming::Early => rotation_event.bpm_pos - BPM_POS_EPSILON,
+                RotationTiming::Late => rotation_event.bpm_pos + BPM_POS_EPSILON,
+            };
+
+            (bpm_pos, rotation_event.rotation)
+        }));
+
+        rotation_step_infos.sort_by(|(bpm_pos1, _), (bpm_pos2, _)| bpm_pos1.total_cmp(bpm_pos2));
+
+        let mut rotation = 0.0;
+
+        let rotation_steps = Box::from_iter(rotation_step_infos.into_iter().map(|(bpm_pos, rotation_diff)| {
+            rotation += rotation_diff;
+
+            RotationStep {
+                bpm_pos,
+                rotation,
+            }
+        }));
+
+        Self {
+            notes: notes.into_boxed_slice(),
+            bombs: bombs.into_boxed_slice(),
+            obstacles: obstacles.into_boxed_slice(),
+            arcs: arcs.into_boxed_slice(),
+            chains: chains.into_boxed_slice(),
+            bpm_changes: bpm_changes.into_boxed_slice(),
+            light_events: light_events.into_boxed_slice(),
+            rotation_events: rotation_events.into_boxed_slice(),
+            rotation_steps,
+            diagnostics: Box::from([]),
+        }
+    }
+
+    fn load_lightshow<S: AsRef<str>>(&mut self, asset_mgr: AssetManagerRc, filename: S) -> Result<()> {
+        let filename = filename.as_ref();
+        let asset_file = asset_mgr.open(&format!("/{}", filename))?;
+        let buf = asset_file.read_str()?;
+
+        let (mut light_events, diagnostics) = match get_version(&buf)?.as_str() {
+            "4.0.0" => {
+                let lightshow: Lightshow_V4 = serde_json::from_str(&buf)?;
+                let diagnostics = Validator::run(filename, &lightshow)?;
+                (lightshow.build(), diagnostics)
+            },
+            version => return Err(Error::Build(format!("Unsupported lightshow version: {}", version)))
+        };
+
+        light_events.sort_by(|light_event1, light_event2| light_event1.bpm_pos.total_cmp(&light_event2.bpm_pos));
+        self.light_events = light_events.into_boxed_slice();
+        self.diagnostics = self.diagnostics.iter().cloned().chain(diagnostics).collect();
+
+        Ok(())
+    }
+
+    pub fn get_notes(&self) -> &[Note] {
+        &self.notes
+    }
+
+    pub fn get_bombs(&self) -> &[Bomb] {
+        &self.bombs
+    }
+
+    pub fn get_obstacles(&self) -> &[Obstacle] {
+        &self.obstacles
+    }
+
+    pub fn get_arcs(&self) -> &[SongArc] {
+        &self.arcs
+    }
+
+    pub fn get_chains(&self) -> &[Chain] {
+        &self.chains
+    }
+
+    pub fn get_bpm_changes(&self) -> &[BPMChange] {
+        &self.bpm_changes
+    }
+
+    pub fn get_light_events(&self) -> &[LightEvent] {
+        &self.light_events
+    }
+
+    pub fn get_rotation_events(&self) -> &[RotationEvent] {
+        &self.rotation_events
+    }
+
+    // Lane rotation of the objects at the given beat position [deg], positive is clockwise (seen from above).
+
+    pub fn get_rotation(&self, bpm_pos: f32) -> f32 {
+        let index = self.rotation_steps.partition_point(|rotation_step| rotation_step.bpm_pos <= bpm_pos); // First index, where rotation_step.bpm_pos > bpm_pos
+        if index == 0 {
+            return 0.0;
+        }
+
+        self.rotation_steps[index - 1].rotation
+    }
+
+    // Warnings of the beatmap and its lightshow (errors are rejecting the beatmap).
+
+    pub fn get_diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    // Writes the beatmap file in the given version. In V4, the BPM changes are part of the BPM map
+    // (see SongInfo::write()) and the lighting events are part of the lightshow (see write_lightshow()).
+
+    pub fn write(&self, version: FormatVersion) -> Result<String> {
+        self.write_with(version, &self.bpm_changes)
+    }
+
+    pub fn write_lightshow(&self) -> Result<String> {
+        to_json(&Lightshow_V4::from_light_events(&self.light_events)?, "version", "4.0.0")
+    }
+
+    fn write_with(&self, version: FormatVersion, bpm_changes: &[BPMChange]) -> Result<String> {
+        match version {
+            FormatVersion::V2 => to_json(&Beatmap_V2::from_beatmap(self, bpm_changes)?, "_version", "2.2.0"),
+            FormatVersion::V3 => to_json(&Beatmap_V3::from_beatmap(self, bpm_changes), "version", "3.3.0"),
+            FormatVersion::V4 => to_json(&Beatmap_V4::from_beatmap(self)?, "version", "4.1.0"),
+        }
+    }
+
+    // Chain heads are written as notes as well (they are replaced by the chains, when loaded).
+
+    fn get_notes_with_chain_heads(&self) -> Vec<&Note> {
+        let mut notes = Vec::from_iter(self.notes.iter().chain(self.chains.iter().map(|chain| &chain.head)));
+        notes.sort_by(|note1, note2| note1.bpm_pos.total_cmp(&note2.bpm_pos));
+
+        notes
+    }
+
+    // The first whole beat after the last object or event.
+
+    fn get_end_bpm_pos(&self) -> f32 {
+        let bpm_poses = self.notes.iter().map(|note| note.bpm_pos)
+            .chain(self.bombs.iter().map(|bomb| bomb.bpm_pos))
+            .chain(self.obstacles.iter().map(|obstacle| obstacle.bpm_pos + obstacle.duration.max(0.0)))
+            .chain(self.arcs.iter().map(|arc| arc.head.bpm_pos.max(arc.tail.bpm_pos)))
+            .chain(self.chains.iter().map(|chain| chain.head.bpm_pos.max(chain.tail_bpm_pos)))
+            .chain(self.bpm_changes.iter().map(|bpm_change| bpm_change.bpm_pos))
+            .chain(self.light_events.iter().map(|light_event| light_event.bpm_pos))
+            .chain(self.rotation_events.iter().map(|rotation_event| rotation_event.bpm_pos));
+
+        bpm_poses.fold(0.0, f32::max).floor() + 1.0
+    }
+}
+
+pub struct Note {
+    bpm_pos: f32,
+    x: u8,
+    y: u8,
+    note_type: NoteType,
+    cut_dir: NoteCutDir,
+    custom_data_opt: Option<NoteCustomData>,
+}
+
+// Per-note overrides of the Chroma and Noodle Extensions mods, only a small subset of them is supported.
+
+#[derive(Clone, Copy, Default)]
+pub struct NoteCustomData {
+    color_opt: Option<Color>,
+    pos_opt: Option<(f32, f32)>, // Grid position, overrides x and y of the note (not necessarily integers)
+    disable_spawn_effect: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NoteType {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy)]
+pub enum NoteCutDir {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+    Any,
+}
+
+impl Note {
+    pub fn new(bpm_pos: f32, x: u8, y: u8, note_type: NoteType, cut_dir: NoteCutDir) -> Result<Self> {
+        if x > 3 || y > 2 {
+            return Err(Error::Build("Either note x or y invalid".to_string()));
+        }
+
+        Ok(Self {
+            bpm_pos,
+            x,
+            y,
+            note_type,
+            cut_dir,
+            custom_data_opt: None,
+        })
+    }
+
+    pub fn with_custom_data(self, custom_data_opt: Option<NoteCustomData>) -> Self {
+        Self {
+            custom_data_opt,
+            ..self
+        }
+    }
+
+    pub fn get_bpm_pos(&self) -> f32 {
+        self.bpm_pos
+    }
+
+    pub fn get_x(&self) -> u8 {
+        self.x
+    }
+
+    pub fn get_y(&self) -> u8 {
+        self.y
+    }
+
+    pub fn get_note_type(&self) -> NoteType {
+        self.note_type
+    }
+
+    pub fn get_cut_dir(&self) -> NoteCutDir {
+        self.cut_dir
+    }
+
+    pub fn get_custom_data(&self) -> Option<&NoteCustomData> {
+        self.custom_data_opt.as_ref()
+    }
+
+    fn is_same(&self, other: &Note) -> bool {
+        (self.bpm_pos - other.bpm_pos).abs() < BPM_POS_EPSILON && self.x == other.x && self.y == other.y && self.note_type == other.note_type
+    }
+}
+
+impl NoteCustomData {
+    pub fn new(color_opt: Option<Color>, pos_opt: Option<(f32, f32)>, disable_spawn_effect: bool) -> Self {
+        Self {
+            color_opt,
+            pos_opt,
+            disable_spawn_effect,
+        }
+    }
+
+    pub fn get_color(&self) -> Option<&Color> {
+        self.color_opt.as_ref()
+    }
+
+    pub fn get_pos(&self) -> Option<(f32, f32)> {
+        self.pos_opt
+    }
+
+    pub fn is_spawn_effect_disabled(&self) -> bool {
+        self.disable_spawn_effect
+    }
+
+    // Arrays with unexpected length are ignored (they were reported by the validator). Returns None,
+    // if nothing is overridden.
+
+    fn from_raw(color_opt: Option<&[f32]>, pos_opt: Option<&[f32]>, disable_spawn_effect: bool) -> Option<Self> {
+        let color_opt = color_opt.and_then(|color| match *color {
+            [r, g, b] | [r, g, b, _] => Some(Color::from_srgb_float(r, g, b)),
+            _ => None,
+        });
+
+        let pos_opt = pos_opt.and_then(|pos| match *pos {
+            [x, y] => Some((x + NOODLE_X_OFFSET, y)),
+            _ => None,
+        });
+
+        if color_opt.is_none() && pos_opt.is_none() && !disable_spawn_effect {
+            return None;
+        }
+
+        Some(Self::new(color_opt, pos_opt, disable_spawn_effect))
+    }
+
+    fn to_raw(self) -> (Option<Vec<f32>>, Option<Vec<f32>>, bool) {
+        let color_opt = self.color_opt.map(|color| to_srgb_float(color).to_vec());
+        let pos_opt = self.pos_opt.map(|(x, y)| vec![x - NOODLE_X_OFFSET, y]);
+
+        (color_opt, pos_opt, self.disable_spawn_effect)
+    }
+}
+
+impl NoteCutDir {
+    // Direction of the swing on the grid (x: right, y: up), normalized.
+
+    pub fn get_dir(&self) -> (f32, f32) {
+        let (x, y): (f32, f32) = match self {
+            NoteCutDir::Up => (0.0, 1.0),
+            NoteCutDir::Down => (0.0, -1.0),
+            NoteCutDir::Left => (-1.0, 0.0),
+            NoteCutDir::Right => (1.0, 0.0),
+            NoteCutDir::UpLeft => (-1.0, 1.0),
+            NoteCutDir::UpRight => (1.0, 1.0),
+            NoteCutDir::DownLeft => (-1.0, -1.0),
+            NoteCutDir::DownRight => (1.0, -1.0),
+            NoteCutDir::Any => (0.0, 0.0),
+        };
+
+        let len = x.hypot(y);
+
+        if len > 0.0 {
+            (x / len, y / len)
+        } else {
+            (x, y)
+        }
+    }
+}
+
+pub struct Bomb {
+    bpm_pos: f32,
+    x: u8,
+    y: u8,
+}
+
+impl Bomb {
+    pub fn new(bpm_pos: f32, x: u8, y: u8) -> Result<Self> {
+        if x > 3 || y > 2 {
+            return Err(Error::Build("Either bomb x or y invalid".to_string()));
+        }
+
+        Ok(Self {
+            bpm_pos,
+            x,
+            y,
+        })
+    }
+
+    pub fn get_bpm_pos(&self) -> f32 {
+        self.bpm_pos
+    }
+
+    pub fn get_x(&self) -> u8 {
+        self.x
+    }
+
+    pub fn get_y(&self) -> u8 {
+        self.y
+    }
+}
+
+// Obstacles can be placed outside of the 4x3 grid, therefore x and y are signed.
+
+pub struct Obstacle {
+    bpm_pos: f32,
+    duration: f32,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+impl Obstacle {
+    pub fn new(bpm_pos: f32, duration: f32, x: i32, y: i32, width: i32, height: i32) -> Result<Self> {
+        if width < 0 || height < 0 {
+            return Err(Error::Build("Either obstacle width or height invalid".to_string()));
+        }
+
+        Ok(Self {
+            bpm_pos,
+            duration,
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    pub fn get_bpm_pos(&self) -> f32 {
+        self.bpm_pos
+    }
+
+    pub fn get_duration(&self) -> f32 {
+        self.duration
+    }
+
+    pub fn get_x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn get_y(&self) -> i32 {
+        self.y
+    }
+
+    pub fn get_width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn get_height(&self) -> i32 {
+        self.height
+    }
+}
+
+// Arcs are connecting a head and a tail note, the curve is defined by the
+// cut directions and the multipliers (control point distances).
+
+pub struct SongArc {
+    note_type: NoteType,
+    head: ArcPoint,
+    tail: ArcPoint,
+    mid_anchor: ArcMidAnchor,
+}
+
+impl SongArc {
+    pub fn new(note_type: NoteType, head: ArcPoint, tail: ArcPoint, mid_anchor: ArcMidAnchor) -> Self {
+        Self {
+            note_type,
+            head,
+            tail,
+            mid_anchor,
+        }
+    }
+
+    pub fn get_note_type(&self) -> NoteType {
+        self.note_type
+    }
+
+    pub fn get_head(&self) -> &ArcPoint {
+        &self.head
+    }
+
+    pub fn get_tail(&self) -> &ArcPoint {
+        &self.tail
+    }
+
+    pub fn get_mid_anchor(&self) -> ArcMidAnchor {
+        self.mid_anchor
+    }
+}
+
+pub struct ArcPoint {
+    bpm_pos: f32,
+    x: u8,
+    y: u8,
+    cut_dir: NoteCutDir,
+    multiplier: f32,
+}
+
+impl ArcPoint {
+    pub fn new(bpm_pos: f32, x: u8, y: u8, cut_dir: NoteCutDir, multiplier: f32) -> Result<Self> {
+        if x > 3 || y > 2 {
+            return Err(Error::Build("Either arc x or y invalid".to_string()));
+        }
+
+        Ok(Self {
+            bpm_pos,
+            x,
+            y,
+            cut_dir,
+            multiplier,
+        })
+    }
+
+    pub fn get_bpm_pos(&self) -> f32 {
+        self.bpm_pos
+    }
+
+    pub fn get_x(&self) -> u8 {
+        self.x
+    }
+
+    pub fn get_y(&self) -> u8 {
+        self.y
+    }
+
+    pub fn get_cut_dir(&self) -> NoteCutDir {
+        self.cut_dir
+    }
+
+    pub fn get_multiplier(&self) -> f32 {
+        self.multiplier
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum ArcMidAnchor {
+    Straight,
+    Clockwise,
+    CounterClockwise,
+}
+
+// Chains are consisting of a head note and link segments. The links are laid out on
+// a quadratic bezier curve, which leaves the head in its cut direction and ends at
+// the tail. The squish factor scales the curve (1: the last link is at the tail).
+
+pub struct Chain {
+    head: Note,
+    links: Box<[ChainLink]>,
+    tail_bpm_pos: f32,
+    tail_x: u8,
+    tail_y: u8,
+    slice_count: u8,
+    squish: f32,
+}
+
+impl Chain {
+    pub fn new(head: Note, tail_bpm_pos: f32, tail_x: u8, tail_y: u8, slice_count: u8, squish: f32) -> Result<Self> {
+        if tail_x > 3 || tail_y > 2 {
+            return Err(Error::Build("Either chain tail x or y invalid".to_string()));
+        }
+
+        if slice_count == 0 {
+            return Err(Error::Build("Chain slice count invalid".to_string()));
+        }
+
+        let (dir_x, dir_y) = head.cut_dir.get_dir();
+
+        let (x0, y0) = (head.x as f32, head.y as f32);
+        let (x2, y2) = (tail_x as f32, tail_y as f32);
+
+        let dist = (x2 - x0).hypot(y2 - y0);
+        let (x1, y1) = (x0 + dir_x * dist / 2.0, y0 + dir_y * dist / 2.0);
+
+        // The head is the first slice, the links are the rest.
+
+        let links = Box::from_iter((1..slice_count).map(|i| {
+            let factor = i as f32 / (slice_count - 1) as f32;
+            let t = factor * squish;
+
+            let x = (1.0 - t) * (1.0 - t) * x0 + 2.0 * (1.0 - t) * t * x1 + t * t * x2;
+            let y = (1.0 - t) * (1.0 - t) * y0 + 2.0 * (1.0 - t) * t * y1 + t * t * y2;
+
+            let tangent_x = 2.0 * (1.0 - t) * (x1 - x0) + 2.0 * t * (x2 - x1);
+            let tangent_y = 2.0 * (1.0 - t) * (y1 - y0) + 2.0 * t * (y2 - y1);
+            let len = tangent_x.hypot(tangent_y);
+
+            let (dir_x, dir_y) = if len > 0.0 { (tangent_x / len, tangent_y / len) } else { (0.0, -1.0) };
+
+            ChainLink {
+                bpm_pos: head.bpm_pos + (tail_bpm_pos - head.bpm_pos) * factor,
+                x,
+                y,
+                dir_x,
+                dir_y,
+            }
+        }));
+
+        Ok(Self {
+            head,
+            links,
+            tail_bpm_pos,
+            tail_x,
+            tail_y,
+            slice_count,
+            squish,
+        })
+    }
+
+    pub fn get_head(&self) -> &Note {
+        &self.head
+    }
+
+    pub fn get_links(&self) -> &[ChainLink] {
+        &self.links
+    }
+
+    pub fn get_tail_bpm_pos(&self) -> f32 {
+        self.tail_bpm_pos
+    }
+
+    pub fn get_tail_x(&self) -> u8 {
+        self.tail_x
+    }
+
+    pub fn get_tail_y(&self) -> u8 {
+        self.tail_y
+    }
+
+    pub fn get_slice_count(&self) -> u8 {
+        self.slice_count
+    }
+
+    pub fn get_squish(&self) -> f32 {
+        self.squish
+    }
+}
+
+pub struct ChainLink {
+    bpm_pos: f32,
+    x: f32,
+    y: f32,
+    dir_x: f32,
+    dir_y: f32,
+}
+
+impl ChainLink {
+    pub fn get_bpm_pos(&self) -> f32 {
+        self.bpm_pos
+    }
+
+    // The position is on the grid, but it is not necessarily at an integer position.
+
+    pub fn get_x(&self) -> f32 {
+        self.x
+    }
+
+    pub fn get_y(&self) -> f32 {
+        self.y
+    }
+
+    // Direction of the curve at the link on the grid (x: right, y: up), normalized.
+
+    pub fn get_dir(&self) -> (f32, f32) {
+        (self.dir_x, self.dir_y)
+    }
+}
+
+// The BPM is changed at the beat position, the beat positions afterwards are using the new BPM.
+
+pub struct BPMChange {
+    bpm_pos: f32,
+    bpm: f32,
+}
+
+impl BPMChange {
+    pub fn new(bpm_pos: f32, bpm: f32) -> Result<Self> {
+        if bpm <= 0.0 {
+            return Err(Error::Build("BPM change invalid".to_string()));
+        }
+
+        Ok(Self {
+            bpm_pos,
+            bpm,
+        })
+    }
+
+    pub fn get_bpm_pos(&self) -> f32 {
+        self.bpm_pos
+    }
+
+    pub fn get_bpm(&self) -> f32 {
+        self.bpm
+    }
+
+    fn is_same_list(bpm_changes1: &[BPMChange], bpm_changes2: &[BPMChange]) -> bool {
+        bpm_changes1.len() == bpm_changes2.len() && bpm_changes1.iter().zip(bpm_changes2).all(|(bpm_change1, bpm_change2)| bpm_change1.bpm_pos == bpm_change2.bpm_pos && bpm_change1.bpm == bpm_change2.bpm)
+    }
+}
+
+// Lighting events are controlling the light groups of the environment, only the basic
+// events are supported (no event boxes, Chroma, etc.).
+
+pub struct LightEvent {
+    bpm_pos: f32,
+    group: LightGroup,
+    value: LightValue,
+    brightness: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LightGroup {
+    BackLasers,
+    RingLights,
+    LeftLasers,
+    RightLasers,
+    CenterLights,
+}
+
+#[derive(Clone, Copy)]
+pub enum LightValue {
+    Off,
+    On(LightColor),
+    Flash(LightColor),
+    Fade(LightColor),
+    Transition(LightColor), // Transition from the previous event to this one
+}
+
+#[derive(Clone, Copy)]
+pub enum LightColor {
+    Left,
+    Right,
+    White,
+}
+
+impl LightEvent {
+    // Returns None, if the event is not a (supported) lighting event.
+
+    fn new(bpm_pos: f32, event_type: i32, value: i32, brightness: f32) -> Option<Self> {
+        let group = match event_type {
+            EVENT_TYPE_BACK_LASERS => LightGroup::BackLasers,
+            EVENT_TYPE_RING_LIGHTS => LightGroup::RingLights,
+            EVENT_TYPE_LEFT_LASERS => LightGroup::LeftLasers,
+            EVENT_TYPE_RIGHT_LASERS => LightGroup::RightLasers,
+            EVENT_TYPE_CENTER_LIGHTS => LightGroup::CenterLights,
+            _ => return None,
+        };
+
+        let value = match value {
+            0 => LightValue::Off,
+            1 => LightValue::On(LightColor::Right),
+            2 => LightValue::Flash(LightColor::Right),
+            3 => LightValue::Fade(LightColor::Right),
+            4 => LightValue::Transition(LightColor::Right),
+            5 => LightValue::On(LightColor::Left),
+            6 => LightValue::Flash(LightColor::Left),
+            7 => LightValue::Fade(LightColor::Left),
+            8 => LightValue::Transition(LightColor::Left),
+            9 => LightValue::On(LightColor::White),
+            10 => LightValue::Flash(LightColor::White),
+            11 => LightValue::Fade(LightColor::White),
+            12 => LightValue::Transition(LightColor::White),
+            _ => return None,
+        };
+
+        Some(Self {
+            bpm_pos,
+            group,
+            value,
+            brightness: brightness.max(0.0),
+        })
+    }
+
+    pub fn get_bpm_pos(&self) -> f32 {
+        self.bpm_pos
+    }
+
+    pub fn get_group(&self) -> LightGroup {
+        self.group
+    }
+
+    pub fn get_value(&self) -> LightValue {
+        self.value
+    }
+
+    pub fn get_brightness(&self) -> f32 {
+        self.brightness
+    }
+
+    // The inverse of new(): event type and value.
+
+    fn get_raw(&self) -> (i32, i32) {
+        let event_type = match self.group {
+            LightGroup::BackLasers => EVENT_TYPE_BACK_LASERS,
+            LightGroup::RingLights => EVENT_TYPE_RING_LIGHTS,
+            LightGroup::LeftLasers => EVENT_TYPE_LEFT_LASERS,
+            LightGroup::RightLasers => EVENT_TYPE_RIGHT_LASERS,
+            LightGroup::CenterLights => EVENT_TYPE_CENTER_LIGHTS,
+        };
+
+        let get_offset = |light_color| match light_color {
+            LightColor::Right => 0,
+            LightColor::Left => 4,
+            LightColor::White => 8,
+        };
+
+        let value = match self.value {
+            LightValue::Off => 0,
+            LightValue::On(light_color) => 1 + get_offset(light_color),
+            LightValue::Flash(light_color) => 2 + get_offset(light_color),
+            LightValue::Fade(light_color) => 3 + get_offset(light_color),
+            LightValue::Transition(light_color) => 4 + get_offset(light_color),
+        };
+
+        (event_type, value)
+    }
+}
+
+// Rotation events are rotating the lane (used by 90Degree and 360Degree characteristics).
+
+pub struct RotationEvent {
+    bpm_pos: f32,
+    timing: RotationTiming,
+    rotation: f32,
+}
+
+#[derive(Clone, Copy)]
+pub enum RotationTiming {
+    Early,
+    Late,
+}
+
+impl RotationEvent {
+    pub fn new(bpm_pos: f32, timing: RotationTiming, rotation: f32) -> Self {
+        Self {
+            bpm_pos,
+            timing,
+            rotation,
+        }
+    }
+
+    pub fn get_bpm_pos(&self) -> f32 {
+        self.bpm_pos
+    }
+
+    pub fn get_timing(&self) -> RotationTiming {
+        self.timing
+    }
+
+    pub fn get_rotation(&self) -> f32 {
+        self.rotation
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V2 {
+    #[serde(rename = "_notes")]
+    notes: Vec<Beatmap_V2_Note>,
+    #[serde(rename = "_obstacles", skip_serializing_if = "Option::is_none")]
+    obstacles: Option<Vec<Beatmap_V2_Obstacle>>,
+    #[serde(rename = "_events", skip_serializing_if = "Option::is_none")]
+    events: Option<Vec<Beatmap_V2_Event>>,
+    #[serde(rename = "_BPMChanges", skip_serializing_if = "Option::is_none")]
+    bpm_changes: Option<Vec<Beatmap_V2_BPMChange>>,
+    #[serde(rename = "_customData", skip_serializing_if = "Option::is_none")]
+    custom_data: Option<Beatmap_V2_CustomData>,
+}
+
+impl Validate for Beatmap_V2 {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.validate(path, "_notes", &self.notes);
+        validator.validate(path, "_obstacles", &self.obstacles);
+        validator.validate(path, "_events", &self.events);
+        validator.validate(path, "_BPMChanges", &self.bpm_changes);
+        validator.validate(path, "_customData", &self.custom_data);
+    }
+}
+
+impl Beatmap_V2 {
+    fn build(self) -> Result<Beatmap> {
+        let mut notes = Vec::new();
+        let mut bombs = Vec::new();
+
+        for raw_note in self.notes {
+            if let Some(note_type) = get_note_type(raw_note.note_type) {
+                let note = Note::new(raw_note.bpm_pos, raw_note.x, raw_note.y, note_type, raw_note.cut_dir)?
+                    .with_custom_data(raw_note.custom_data.as_ref().and_then(Beatmap_V2_NoteCustomData::build));
+                notes.push(note);
+            } else if raw_note.note_type == NOTE_TYPE_BOMB_V2 {
+                let bomb = Bomb::new(raw_note.bpm_pos, raw_note.x, raw_note.y)?;
+                bombs.push(bomb);
+            }
+        }
+
+        let mut obstacles = Vec::new();
+
+        for raw_obstacle in self.obstacles.into_iter().flatten() {
+            // Other types are used by mapping extensions, which are not supported.
+
+            let y_height_opt = match raw_obstacle.obstacle_type {
+                OBSTACLE_TYPE_FULL_V2 => Some((0, 5)),
+                OBSTACLE_TYPE_CROUCH_V2 => Some((2, 3)),
+                OBSTACLE_TYPE_FREE_V2 => raw_obstacle.y_opt.zip(raw_obstacle.height_opt),
+                _ => None,
+            };
+
+            if let Some((y, height)) = y_height_opt {
+                let obstacle = Obstacle::new(raw_obstacle.bpm_pos, raw_obstacle.duration, raw_obstacle.x, y, raw_obstacle.width, height)?;
+                obstacles.push(obstacle);
+            }
+        }
+
+        // BPM changes are either events or they are listed separately (also in custom data, see MediocreMapper).
+
+        let mut bpm_changes = Vec::new();
+        let mut light_events = Vec::new();
+        let mut rotation_events = Vec::new();
+
+        for raw_event in self.events.into_iter().flatten() {
+            if raw_event.event_type == EVENT_TYPE_BPM_CHANGE_V2 {
+                if let Some(bpm) = raw_event.float_value_opt {
+                    let bpm_change = BPMChange::new(raw_event.bpm_pos, bpm)?;
+                    bpm_changes.push(bpm_change);
+                }
+            } else if raw_event.event_type == EVENT_TYPE_EARLY_ROTATION_V2 || raw_event.event_type == EVENT_TYPE_LATE_ROTATION_V2 {
+                // Other values are used by mapping extensions, which are not supported.
+
+                if let Some(rotation) = usize::try_from(raw_event.value).ok().and_then(|value| ROTATION_ANGLES_V2.get(value)) {
+                    let timing = if raw_event.event_type == EVENT_TYPE_EARLY_ROTATION_V2 { RotationTiming::Early } else { RotationTiming::Late };
+
+                    let rotation_event = RotationEvent::new(raw_event.bpm_pos, timing, *rotation);
+                    rotation_events.push(rotation_event);
+                }
+            } else if let Some(light_event) = LightEvent::new(raw_event.bpm_pos, raw_event.event_type, raw_event.value, raw_event.float_value_opt.unwrap_or(1.0)) {
+                light_events.push(light_event);
+            }
+        }
+
+        let custom_bpm_changes = self.custom_data.and_then(|custom_data| custom_data.bpm_changes);
+
+        for raw_bpm_change in self.bpm_changes.into_iter().chain(custom_bpm_changes).flatten() {
+            let bpm_change = BPMChange::new(raw_bpm_change.bpm_pos, raw_bpm_change.bpm)?;
+            bpm_changes.push(bpm_change);
+        }
+
+        Ok(Beatmap::new(notes, bombs, obstacles, Vec::new(), Vec::new(), bpm_changes, light_events, rotation_events))
+    }
+
+    fn from_beatmap(beatmap: &Beatmap, bpm_changes: &[BPMChange]) -> Result<Self> {
+        if !beatmap.arcs.is_empty() || !beatmap.chains.is_empty() {
+            return Err(Error::Build("Arcs and chains are not supported by beatmap version 2".to_string()));
+        }
+
+        // Bombs are notes of a special type.
+
+        let mut notes = Vec::from_iter(beatmap.notes.iter().map(|note| Beatmap_V2_Note {
+            bpm_pos: note.bpm_pos,
+            x: note.x,
+            y: note.y,
+            note_type: get_raw_note_type(note.note_type),
+            cut_dir: note.cut_dir,
+            custom_data: note.custom_data_opt.as_ref().map(Beatmap_V2_NoteCustomData::from_custom_data),
+        }));
+
+        notes.extend(beatmap.bombs.iter().map(|bomb| Beatmap_V2_Note {
+            bpm_pos: bomb.bpm_pos,
+            x: bomb.x,
+            y: bomb.y,
+            note_type: NOTE_TYPE_BOMB_V2,
+            cut_dir: NoteCutDir::Any,
+            custom_data: None,
+        }));
+
+        notes.sort_by(|note1, note2| note1.bpm_pos.total_cmp(&note2.bpm_pos));
+
+        let obstacles = Vec::from_iter(beatmap.obstacles.iter().map(|obstacle| {
+            let (obstacle_type, y_opt, height_opt) = match (obstacle.y, obstacle.height) {
+                (0, 5) => (OBSTACLE_TYPE_FULL_V2, None, None),
+                (2, 3) => (OBSTACLE_TYPE_CROUCH_V2, None, None),
+                (y, height) => (OBSTACLE_TYPE_FREE_V2, Some(y), Some(height)),
+            };
+
+            Beatmap_V2_Obstacle {
+                bpm_pos: obstacle.bpm_pos,
+                duration: obstacle.duration,
+                x: obstacle.x,
+                y_opt,
+                obstacle_type,
+                width: obstacle.width,
+                height_opt,
+            }
+        }));
+
+        // BPM changes, lighting events and rotation events are all events.
+
+        let mut events = Vec::from_iter(beatmap.light_events.iter().map(|light_event| {
+            let (event_type, value) = light_event.get_raw();
+
+            Beatmap_V2_Event {
+                bpm_pos: light_event.bpm_pos,
+                event_type,
+                value,
+                float_value_opt: Some(light_event.brightness),
+            }
+        }));
+
+        events.extend(bpm_changes.iter().map(|bpm_change| Beatmap_V2_Event {
+            bpm_pos: bpm_change.bpm_pos,
+            event_type: EVENT_TYPE_BPM_CHANGE_V2,
+            value: 0,
+            float_value_opt: Some(bpm_change.bpm),
+        }));
+
+        for rotation_event in &beatmap.rotation_events {
+            let value = ROTATION_ANGLES_V2.iter().position(|rotation| *rotation == rotation_event.rotation)
+                .ok_or_else(|| Error::Build(format!("Rotation not supported by beatmap version 2: {}", rotation_event.rotation)))?;
+
+            events.push(Beatmap_V2_Event {
+                bpm_pos: rotation_event.bpm_pos,
+                event_type: match rotation_event.timing {
+                    RotationTiming::Early => EVENT_TYPE_EARLY_ROTATION_V2,
+                    RotationTiming::Late => EVENT_TYPE_LATE_ROTATION_V2,
+                },
+                value: value as i32,
+                float_value_opt: None,
+            });
+        }
+
+        events.sort_by(|event1, event2| event1.bpm_pos.total_cmp(&event2.bpm_pos));
+
+        Ok(Self {
+            notes,
+            obstacles: Some(obstacles),
+            events: Some(events),
+            bpm_changes: None,
+            custom_data: None,
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V2_Note {
+    #[serde(rename = "_time")]
+    bpm_pos: f32,
+    #[serde(rename = "_lineIndex")]
+    x: u8,
+    #[serde(rename = "_lineLayer")]
+    y: u8,
+    #[serde(rename = "_type")]
+    note_type: u8,
+    #[serde(rename = "_cutDirection")]
+    cut_dir: NoteCutDir,
+    #[serde(rename = "_customData", skip_serializing_if = "Option::is_none")]
+    custom_data: Option<Beatmap_V2_NoteCustomData>,
+}
+
+impl Validate for Beatmap_V2_Note {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "_time", self.bpm_pos);
+
+        // Other types are ignored.
+
+        if get_note_type(self.note_type).is_some() || self.note_type == NOTE_TYPE_BOMB_V2 {
+            validator.check_grid(path, "_lineIndex", "_lineLayer", self.x, self.y);
+        }
+
+        validator.validate(path, "_customData", &self.custom_data);
+    }
+}
+
+// Unknown fields of the custom data are ignored.
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V2_NoteCustomData {
+    #[serde(rename = "_color", skip_serializing_if = "Option::is_none")]
+    color: Option<Vec<f32>>,
+    #[serde(rename = "_position", skip_serializing_if = "Option::is_none")]
+    pos: Option<Vec<f32>>,
+    #[serde(rename = "_disableSpawnEffect", skip_serializing_if = "Option::is_none")]
+    disable_spawn_effect: Option<bool>,
+}
+
+impl Validate for Beatmap_V2_NoteCustomData {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validate_custom_array(validator, path, "_color", &self.color, &[3, 4]);
+        validate_custom_array(validator, path, "_position", &self.pos, &[2]);
+    }
+}
+
+impl Beatmap_V2_NoteCustomData {
+    fn build(&self) -> Option<NoteCustomData> {
+        NoteCustomData::from_raw(self.color.as_deref(), self.pos.as_deref(), self.disable_spawn_effect.unwrap_or(false))
+    }
+
+    fn from_custom_data(custom_data: &NoteCustomData) -> Self {
+        let (color, pos, disable_spawn_effect) = custom_data.to_raw();
+
+        Self {
+            color,
+            pos,
+            disable_spawn_effect: disable_spawn_effect.then_some(true),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V2_Obstacle {
+    #[serde(rename = "_time")]
+    bpm_pos: f32,
+    #[serde(rename = "_duration")]
+    duration: f32,
+    #[serde(rename = "_lineIndex")]
+    x: i32,
+    #[serde(rename = "_lineLayer", skip_serializing_if = "Option::is_none")]
+    y_opt: Option<i32>,
+    #[serde(rename = "_type")]
+    obstacle_type: u32,
+    #[serde(rename = "_width")]
+    width: i32,
+    #[serde(rename = "_height", skip_serializing_if = "Option::is_none")]
+    height_opt: Option<i32>,
+}
+
+impl Validate for Beatmap_V2_Obstacle {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "_time", self.bpm_pos);
+        validator.check_non_negative(path, "_duration", self.duration);
+        validator.check_size(path, "_width", self.width);
+
+        if let Some(height) = self.height_opt {
+            validator.check_size(path, "_height", height);
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V2_Event {
+    #[serde(rename = "_time")]
+    bpm_pos: f32,
+    #[serde(rename = "_type")]
+    event_type: i32,
+    #[serde(rename = "_value")]
+    value: i32,
+    #[serde(rename = "_floatValue", skip_serializing_if = "Option::is_none")]
+    float_value_opt: Option<f32>,
+}
+
+impl Validate for Beatmap_V2_Event {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "_time", self.bpm_pos);
+
+        if let Some(float_value) = self.float_value_opt {
+            if self.event_type == EVENT_TYPE_BPM_CHANGE_V2 {
+                validator.check_positive(path, "_floatValue", float_value);
+            } else {
+                validator.check_finite(path, "_floatValue", float_value);
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V2_BPMChange {
+    #[serde(rename = "_time")]
+    bpm_pos: f32,
+    #[serde(rename = "_BPM")]
+    bpm: f32,
+}
+
+impl Validate for Beatmap_V2_BPMChange {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "_time", self.bpm_pos);
+        validator.check_positive(path, "_BPM", self.bpm);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V2_CustomData {
+    #[serde(rename = "_BPMChanges", skip_serializing_if = "Option::is_none")]
+    bpm_changes: Option<Vec<Beatmap_V2_BPMChange>>,
+}
+
+impl Validate for Beatmap_V2_CustomData {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.validate(path, "_BPMChanges", &self.bpm_changes);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V3 {
+    #[serde(rename = "colorNotes")]
+    notes: Vec<Beatmap_V3_Note>,
+    #[serde(rename = "bombNotes", skip_serializing_if = "Option::is_none")]
+    bombs: Option<Vec<Beatmap_V3_Bomb>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    obstacles: Option<Vec<Beatmap_V3_Obstacle>>,
+    #[serde(rename = "sliders", skip_serializing_if = "Option::is_none")]
+    arcs: Option<Vec<Beatmap_V3_Arc>>,
+    #[serde(rename = "burstSliders", skip_serializing_if = "Option::is_none")]
+    chains: Option<Vec<Beatmap_V3_Chain>>,
+    #[serde(rename = "bpmEvents", skip_serializing_if = "Option::is_none")]
+    bpm_changes: Option<Vec<Beatmap_V3_BPMChange>>,
+    #[serde(rename = "basicBeatmapEvents", skip_serializing_if = "Option::is_none")]
+    events: Option<Vec<Beatmap_V3_Event>>,
+    #[serde(rename = "rotationEvents", skip_serializing_if = "Option::is_none")]
+    rotation_events: Option<Vec<Beatmap_V3_RotationEvent>>,
+}
+
+impl Validate for Beatmap_V3 {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.validate(path, "colorNotes", &self.notes);
+        validator.validate(path, "bombNotes", &self.bombs);
+        validator.validate(path, "obstacles", &self.obstacles);
+        validator.validate(path, "sliders", &self.arcs);
+        validator.validate(path, "burstSliders", &self.chains);
+        validator.validate(path, "bpmEvents", &self.bpm_changes);
+        validator.validate(path, "basicBeatmapEvents", &self.events);
+        validator.validate(path, "rotationEvents", &self.rotation_events);
+    }
+}
+
+impl Beatmap_V3 {
+    fn build(self) -> Result<Beatmap> {
+        let mut notes = Vec::new();
+
+        for raw_note in self.notes {
+            if let Some(note_type) = get_note_type(raw_note.note_type) {
+                let note = Note::new(raw_note.bpm_pos, raw_note.x, raw_note.y, note_type, raw_note.cut_dir)?
+                    .with_custom_data(raw_note.custom_data.as_ref().and_then(Beatmap_V3_NoteCustomData::build));
+                notes.push(note);
+            }
+        }
+
+        let mut bombs = Vec::new();
+
+        for raw_bomb in self.bombs.into_iter().flatten() {
+            let bomb = Bomb::new(raw_bomb.bpm_pos, raw_bomb.x, raw_bomb.y)?;
+            bombs.push(bomb);
+        }
+
+        let mut obstacles = Vec::new();
+
+        for raw_obstacle in self.obstacles.into_iter().flatten() {
+            let obstacle = Obstacle::new(raw_obstacle.bpm_pos, raw_obstacle.duration, raw_obstacle.x, raw_obstacle.y, raw_obstacle.width, raw_obstacle.height)?;
+            obstacles.push(obstacle);
+        }
+
+        let mut arcs = Vec::new();
+
+        for raw_arc in self.arcs.into_iter().flatten() {
+            if let Some(note_type) = get_note_type(raw_arc.note_type) {
+                let head = ArcPoint::new(raw_arc.bpm_pos, raw_arc.x, raw_arc.y, raw_arc.cut_dir, raw_arc.multiplier)?;
+                let tail = ArcPoint::new(raw_arc.tail_bpm_pos, raw_arc.tail_x, raw_arc.tail_y, raw_arc.tail_cut_dir, raw_arc.tail_multiplier)?;
+
+                let arc = SongArc::new(note_type, head, tail, raw_arc.mid_anchor);
+                arcs.push(arc);
+            }
+        }
+
+        let mut chains = Vec::new();
+
+        for raw_chain in self.chains.into_iter().flatten() {
+            if let Some(note_type) = get_note_type(raw_chain.note_type) {
+                let head = Note::new(raw_chain.bpm_pos, raw_chain.x, raw_chain.y, note_type, raw_chain.cut_dir)?;
+
+                let chain = Chain::new(head, raw_chain.tail_bpm_pos, raw_chain.tail_x, raw_chain.tail_y, raw_chain.slice_count, raw_chain.squish)?;
+                chains.push(chain);
+            }
+        }
+
+        let mut bpm_changes = Vec::new();
+
+        for raw_bpm_change in self.bpm_changes.into_iter().flatten() {
+            let bpm_change = BPMChange::new(raw_bpm_change.bpm_pos, raw_bpm_change.bpm)?;
+            bpm_changes.push(bpm_change);
+        }
+
+        let mut light_events = Vec::new();
+
+        for raw_event in self.events.into_iter().flatten() {
+            if let Some(light_event) = LightEvent::new(raw_event.bpm_pos, raw_event.event_type, raw_event.value, raw_event.float_value_opt.unwrap_or(1.0)) {
+                light_events.push(light_event);
+            }
+        }
+
+        let mut rotation_events = Vec::new();
+
+        for raw_rotation_event in self.rotation_events.into_iter().flatten() {
+            let rotation_event = RotationEvent::new(raw_rotation_event.bpm_pos, raw_rotation_event.timing, raw_rotation_event.rotation);
+            rotation_events.push(rotation_event);
+        }
+
+        Ok(Beatmap::new(notes, bombs, obstacles, arcs, chains, bpm_changes, light_events, rotation_events))
+    }
+
+    fn from_beatmap(beatmap: &Beatmap, bpm_changes: &[BPMChange]) -> Self {
+        let notes = Vec::from_iter(beatmap.get_notes_with_chain_heads().into_iter().map(|note| Beatmap_V3_Note {
+            bpm_pos: note.bpm_pos,
+            x: note.x,
+            y: note.y,
+            note_type: get_raw_note_type(note.note_type),
+            cut_dir: note.cut_dir,
+            custom_data: note.custom_data_opt.as_ref().map(Beatmap_V3_NoteCustomData::from_custom_data),
+        }));
+
+        let bombs = Vec::from_iter(beatmap.bombs.iter().map(|bomb| Beatmap_V3_Bomb {
+            bpm_pos: bomb.bpm_pos,
+            x: bomb.x,
+            y: bomb.y,
+        }));
+
+        let obstacles = Vec::from_iter(beatmap.obstacles.iter().map(|obstacle| Beatmap_V3_Obstacle {
+            bpm_pos: obstacle.bpm_pos,
+            duration: obstacle.duration,
+            x: obstacle.x,
+            y: obstacle.y,
+            width: obstacle.width,
+            height: obstacle.height,
+        }));
+
+        let arcs = Vec::from_iter(beatmap.arcs.iter().map(|arc| Beatmap_V3_Arc {
+            bpm_pos: arc.head.bpm_pos,
+            note_type: get_raw_note_type(arc.note_type),
+            x: arc.head.x,
+            y: arc.head.y,
+            cut_dir: arc.head.cut_dir,
+            multiplier: arc.head.multiplier,
+            tail_bpm_pos: arc.tail.bpm_pos,
+            tail_x: arc.tail.x,
+            tail_y: arc.tail.y,
+            tail_cut_dir: arc.tail.cut_dir,
+            tail_multiplier: arc.tail.multiplier,
+            mid_anchor: arc.mid_anchor,
+        }));
+
+        let chains = Vec::from_iter(beatmap.chains.iter().map(|chain| Beatmap_V3_Chain {
+            bpm_pos: chain.head.bpm_pos,
+            note_type: get_raw_note_type(chain.head.note_type),
+            x: chain.head.x,
+            y: chain.head.y,
+            cut_dir: chain.head.cut_dir,
+            tail_bpm_pos: chain.tail_bpm_pos,
+            tail_x: chain.tail_x,
+            tail_y: chain.tail_y,
+            slice_count: chain.slice_count,
+            squish: chain.squish,
+        }));
+
+        let bpm_changes = Vec::from_iter(bpm_changes.iter().map(|bpm_change| Beatmap_V3_BPMChange {
+            bpm_pos: bpm_change.bpm_pos,
+            bpm: bpm_change.bpm,
+        }));
+
+        let events = Vec::from_iter(beatmap.light_events.iter().map(|light_event| {
+            let (event_type, value) = light_event.get_raw();
+
+            Beatmap_V3_Event {
+                bpm_pos: light_event.bpm_pos,
+                event_type,
+                value,
+                float_value_opt: Some(light_event.brightness),
+            }
+        }));
+
+        let rotation_events = Vec::from_iter(beatmap.rotation_events.iter().map(|rotation_event| Beatmap_V3_RotationEvent {
+            bpm_pos: rotation_event.bpm_pos,
+            timing: rotation_event.timing,
+            rotation: rotation_event.rotation,
+        }));
+
+        Self {
+            notes,
+            bombs: Some(bombs),
+            obstacles: Some(obstacles),
+            arcs: Some(arcs),
+            chains: Some(chains),
+            bpm_changes: Some(bpm_changes),
+            events: Some(events),
+            rotation_events: Some(rotation_events),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V3_Note {
+    #[serde(rename = "b")]
+    bpm_pos: f32,
+    x: u8,
+    y: u8,
+    #[serde(rename = "c")]
+    note_type: u8,
+    #[serde(rename = "d")]
+    cut_dir: NoteCutDir,
+    #[serde(rename = "customData", skip_serializing_if = "Option::is_none")]
+    custom_data: Option<Beatmap_V3_NoteCustomData>,
+}
+
+impl Validate for Beatmap_V3_Note {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "b", self.bpm_pos);
+
+        if get_note_type(self.note_type).is_some() {
+            validator.check_grid(path, "x", "y", self.x, self.y);
+        }
+
+        validator.validate(path, "customData", &self.custom_data);
+    }
+}
+
+// Unknown fields of the custom data are ignored. It is used by version 4 as well.
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V3_NoteCustomData {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<Vec<f32>>,
+    #[serde(rename = "coordinates", skip_serializing_if = "Option::is_none")]
+    pos: Option<Vec<f32>>,
+    #[serde(rename = "spawnEffect", skip_serializing_if = "Option::is_none")]
+    spawn_effect: Option<bool>,
+}
+
+impl Validate for Beatmap_V3_NoteCustomData {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validate_custom_array(validator, path, "color", &self.color, &[3, 4]);
+        validate_custom_array(validator, path, "coordinates", &self.pos, &[2]);
+    }
+}
+
+impl Beatmap_V3_NoteCustomData {
+    fn build(&self) -> Option<NoteCustomData> {
+        NoteCustomData::from_raw(self.color.as_deref(), self.pos.as_deref(), !self.spawn_effect.unwrap_or(true))
+    }
+
+    fn from_custom_data(custom_data: &NoteCustomData) -> Self {
+        let (color, pos, disable_spawn_effect) = custom_data.to_raw();
+
+        Self {
+            color,
+            pos,
+            spawn_effect: disable_spawn_effect.then_some(false),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V3_Bomb {
+    #[serde(rename = "b")]
+    bpm_pos: f32,
+    x: u8,
+    y: u8,
+}
+
+impl Validate for Beatmap_V3_Bomb {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "b", self.bpm_pos);
+        validator.check_grid(path, "x", "y", self.x, self.y);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V3_Arc {
+    #[serde(rename = "b")]
+    bpm_pos: f32,
+    #[serde(rename = "c")]
+    note_type: u8,
+    x: u8,
+    y: u8,
+    #[serde(rename = "d")]
+    cut_dir: NoteCutDir,
+    #[serde(rename = "mu")]
+    multiplier: f32,
+    #[serde(rename = "tb")]
+    tail_bpm_pos: f32,
+    #[serde(rename = "tx")]
+    tail_x: u8,
+    #[serde(rename = "ty")]
+    tail_y: u8,
+    #[serde(rename = "tc")]
+    tail_cut_dir: NoteCutDir,
+    #[serde(rename = "tmu")]
+    tail_multiplier: f32,
+    #[serde(rename = "m")]
+    mid_anchor: ArcMidAnchor,
+}
+
+impl Validate for Beatmap_V3_Arc {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "b", self.bpm_pos);
+        validator.check_bpm_pos(path, "tb", self.tail_bpm_pos);
+        validator.check_finite(path, "mu", self.multiplier);
+        validator.check_finite(path, "tmu", self.tail_multiplier);
+
+        if get_note_type(self.note_type).is_some() {
+            validator.check_grid(path, "x", "y", self.x, self.y);
+            validator.check_grid(path, "tx", "ty", self.tail_x, self.tail_y);
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V3_Chain {
+    #[serde(rename = "b")]
+    bpm_pos: f32,
+    #[serde(rename = "c")]
+    note_type: u8,
+    x: u8,
+    y: u8,
+    #[serde(rename = "d")]
+    cut_dir: NoteCutDir,
+    #[serde(rename = "tb")]
+    tail_bpm_pos: f32,
+    #[serde(rename = "tx")]
+    tail_x: u8,
+    #[serde(rename = "ty")]
+    tail_y: u8,
+    #[serde(rename = "sc")]
+    slice_count: u8,
+    #[serde(rename = "s")]
+    squish: f32,
+}
+
+impl Validate for Beatmap_V3_Chain {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "b", self.bpm_pos);
+        validator.check_bpm_pos(path, "tb", self.tail_bpm_pos);
+        validate_chain(validator, path, ["sc", "s"], self.slice_count, self.squish);
+
+        if get_note_type(self.note_type).is_some() {
+            validator.check_grid(path, "x", "y", self.x, self.y);
+            validator.check_grid(path, "tx", "ty", self.tail_x, self.tail_y);
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V3_BPMChange {
+    #[serde(rename = "b")]
+    bpm_pos: f32,
+    #[serde(rename = "m")]
+    bpm: f32,
+}
+
+impl Validate for Beatmap_V3_BPMChange {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "b", self.bpm_pos);
+        validator.check_positive(path, "m", self.bpm);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V3_Event {
+    #[serde(rename = "b")]
+    bpm_pos: f32,
+    #[serde(rename = "et")]
+    event_type: i32,
+    #[serde(rename = "i")]
+    value: i32,
+    #[serde(rename = "f", skip_serializing_if = "Option::is_none")]
+    float_value_opt: Option<f32>,
+}
+
+impl Validate for Beatmap_V3_Event {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "b", self.bpm_pos);
+
+        if let Some(float_value) = self.float_value_opt {
+            validator.check_finite(path, "f", float_value);
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V3_RotationEvent {
+    #[serde(rename = "b")]
+    bpm_pos: f32,
+    #[serde(rename = "e")]
+    timing: RotationTiming,
+    #[serde(rename = "r")]
+    rotation: f32,
+}
+
+impl Validate for Beatmap_V3_RotationEvent {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "b", self.bpm_pos);
+        validator.check_finite(path, "r", self.rotation);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V3_Obstacle {
+    #[serde(rename = "b")]
+    bpm_pos: f32,
+    #[serde(rename = "d")]
+    duration: f32,
+    x: i32,
+    y: i32,
+    #[serde(rename = "w")]
+    width: i32,
+    #[serde(rename = "h")]
+    height: i32,
+}
+
+impl Validate for Beatmap_V3_Obstacle {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "b", self.bpm_pos);
+        validator.check_non_negative(path, "d", self.duration);
+        validator.check_size(path, "w", self.width);
+        validator.check_size(path, "h", self.height);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V4 {
+    #[serde(rename = "colorNotes")]
+    notes: Vec<Beatmap_V4_Note>,
+    #[serde(rename = "colorNotesData")]
+    note_datas: Vec<Beatmap_V4_NoteData>,
+    #[serde(rename = "bombNotes", skip_serializing_if = "Option::is_none")]
+    bombs: Option<Vec<Beatmap_V4_Bomb>>,
+    #[serde(rename = "bombNotesData", skip_serializing_if = "Option::is_none")]
+    bomb_datas: Option<Vec<Beatmap_V4_BombData>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    obstacles: Option<Vec<Beatmap_V4_Obstacle>>,
+    #[serde(rename = "obstaclesData", skip_serializing_if = "Option::is_none")]
+    obstacle_datas: Option<Vec<Beatmap_V4_ObstacleData>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    arcs: Option<Vec<Beatmap_V4_Arc>>,
+    #[serde(rename = "arcsData", skip_serializing_if = "Option::is_none")]
+    arc_datas: Option<Vec<Beatmap_V4_ArcData>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chains: Option<Vec<Beatmap_V4_Chain>>,
+    #[serde(rename = "chainsData", skip_serializing_if = "Option::is_none")]
+    chain_datas: Option<Vec<Beatmap_V4_ChainData>>,
+    #[serde(rename = "spawnRotations", skip_serializing_if = "Option::is_none")]
+    rotation_events: Option<Vec<Beatmap_V4_RotationEvent>>,
+    #[serde(rename = "spawnRotationsData", skip_serializing_if = "Option::is_none")]
+    rotation_event_datas: Option<Vec<Beatmap_V4_RotationEventData>>,
+}
+
+impl Validate for Beatmap_V4 {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.validate(path, "colorNotes", &self.notes);
+        validator.validate(path, "colorNotesData", &self.note_datas);
+        validator.validate(path, "bombNotes", &self.bombs);
+        validator.validate(path, "bombNotesData", &self.bomb_datas);
+        validator.validate(path, "obstacles", &self.obstacles);
+        validator.validate(path, "obstaclesData", &self.obstacle_datas);
+        validator.validate(path, "arcs", &self.arcs);
+        validator.validate(path, "arcsData", &self.arc_datas);
+        validator.validate(path, "chains", &self.chains);
+        validator.validate(path, "chainsData", &self.chain_datas);
+        validator.validate(path, "spawnRotations", &self.rotation_events);
+        validator.validate(path, "spawnRotationsData", &self.rotation_event_datas);
+
+        // Check the references to the data.
+
+        let note_data_count = self.note_datas.len();
+        validator.check_indexes(path, "colorNotes", "i", &self.notes, |note| note.data_index, note_data_count);
+
+        if let Some(bombs) = &self.bombs {
+            validator.check_indexes(path, "bombNotes", "i", bombs, |bomb| bomb.data_index, self.bomb_datas.as_ref().map_or(0, Vec::len));
+        }
+
+        if let Some(obstacles) = &self.obstacles {
+            validator.check_indexes(path, "obstacles", "i", obstacles, |obstacle| obstacle.data_index, self.obstacle_datas.as_ref().map_or(0, Vec::len));
+        }
+
+        if let Some(arcs) = &self.arcs {
+            validator.check_indexes(path, "arcs", "hi", arcs, |arc| arc.head_data_index, note_data_count);
+            validator.check_indexes(path, "arcs", "ti", arcs, |arc| arc.tail_data_index, note_data_count);
+            validator.check_indexes(path, "arcs", "ai", arcs, |arc| arc.data_index, self.arc_datas.as_ref().map_or(0, Vec::len));
+        }
+
+        if let Some(chains) = &self.chains {
+            validator.check_indexes(path, "chains", "i", chains, |chain| chain.head_data_index, note_data_count);
+            validator.check_indexes(path, "chains", "ci", chains, |chain| chain.data_index, self.chain_datas.as_ref().map_or(0, Vec::len));
+        }
+
+        if let Some(rotation_events) = &self.rotation_events {
+            validator.check_indexes(path, "spawnRotations", "i", rotation_events, |rotation_event| rotation_event.data_index, self.rotation_event_datas.as_ref().map_or(0, Vec::len));
+        }
+    }
+}
+
+impl Beatmap_V4 {
+    fn build(self) -> Result<Beatmap> {
+        let mut notes = Vec::new();
+
+        for raw_note in self.notes {
+            if let Some(raw_note_data) = self.note_datas.get(raw_note.data_index as usize) &&
+               let Some(note_type) = get_note_type(raw_note_data.note_type) {
+                let note = Note::new(raw_note.bpm_pos, raw_note_data.x, raw_note_data.y, note_type, raw_note_data.cut_dir)?
+                    .with_custom_data(raw_note_data.custom_data.as_ref().and_then(Beatmap_V3_NoteCustomData::build));
+                notes.push(note);
+            }
+        }
+
+        let mut bombs = Vec::new();
+
+        if let Some(raw_bombs) = self.bombs && let Some(raw_bomb_datas) = self.bomb_datas {
+            for raw_bomb in raw_bombs {
+                if let Some(raw_bomb_data) = raw_bomb_datas.get(raw_bomb.data_index as usize) {
+                    let bomb = Bomb::new(raw_bomb.bpm_pos, raw_bomb_data.x, raw_bomb_data.y)?;
+                    bombs.push(bomb);
+                }
+            }
+        }
+
+        let mut obstacles = Vec::new();
+
+        if let Some(raw_obstacles) = self.obstacles && let Some(raw_obstacle_datas) = self.obstacle_datas {
+            for raw_obstacle in raw_obstacles {
+                if let Some(raw_obstacle_data) = raw_obstacle_datas.get(raw_obstacle.data_index as usize) {
+                    let obstacle = Obstacle::new(raw_obstacle.bpm_pos, raw_obstacle_data.duration, raw_obstacle_data.x, raw_obstacle_data.y, raw_obstacle_data.width, raw_obstacle_data.height)?;
+                    obstacles.push(obstacle);
+                }
+            }
+        }
+
+        // Arcs are referring to colorNotesData for the head and the tail.
+
+        let mut arcs = Vec::new();
+
+        if let Some(raw_arcs) = self.arcs && let Some(raw_arc_datas) = self.arc_datas {
+            for raw_arc in raw_arcs {
+                if let Some(raw_head_data) = self.note_datas.get(raw_arc.head_data_index as usize) &&
+                   let Some(raw_tail_data) = self.note_datas.get(raw_arc.tail_data_index as usize) &&
+                   let Some(raw_arc_data) = raw_arc_datas.get(raw_arc.data_index as usize) &&
+                   let Some(note_type) = get_note_type(raw_head_data.note_type) {
+                    let head = ArcPoint::new(raw_arc.bpm_pos, raw_head_data.x, raw_head_data.y, raw_head_data.cut_dir, raw_arc_data.multiplier)?;
+                    let tail = ArcPoint::new(raw_arc.tail_bpm_pos, raw_tail_data.x, raw_tail_data.y, raw_tail_data.cut_dir, raw_arc_data.tail_multiplier)?;
+
+                    let arc = SongArc::new(note_type, head, tail, raw_arc_data.mid_anchor);
+                    arcs.push(arc);
+                }
+            }
+        }
+
+        // Chains are referring to colorNotesData for the head.
+
+        let mut chains = Vec::new();
+
+        if let Some(raw_chains) = self.chains && let Some(raw_chain_datas) = self.chain_datas {
+            for raw_chain in raw_chains {
+                if let Some(raw_head_data) = self.note_datas.get(raw_chain.head_data_index as usize) &&
+                   let Some(raw_chain_data) = raw_chain_datas.get(raw_chain.data_index as usize) &&
+                   let Some(note_type) = get_note_type(raw_head_data.note_type) {
+                    let head = Note::new(raw_chain.bpm_pos, raw_head_data.x, raw_head_data.y, note_type, raw_head_data.cut_dir)?;
+
+                    let chain = Chain::new(head, raw_chain.tail_bpm_pos, raw_chain_data.tail_x, raw_chain_data.tail_y, raw_chain_data.slice_count, raw_chain_data.squish)?;
+                    chains.push(chain);
+                }
+            }
+        }
+
+        let mut rotation_events = Vec::new();
+
+        if let Some(raw_rotation_events) = self.rotation_events && let Some(raw_rotation_event_datas) = self.rotation_event_datas {
+            for raw_rotation_event in raw_rotation_events {
+                if let Some(raw_rotation_event_data) = raw_rotation_event_datas.get(raw_rotation_event.data_index as usize) {
+                    let rotation_event = RotationEvent::new(raw_rotation_event.bpm_pos, raw_rotation_event_data.timing, raw_rotation_event_data.rotation);
+                    rotation_events.push(rotation_event);
+                }
+            }
+        }
+
+        // BPM changes are part of the BPM map (see audioDataFilename in Info.dat) and
+        // lighting events are part of the lightshow (see lightshowDataFilename in Info.dat).
+
+        Ok(Beatmap::new(notes, bombs, obstacles, arcs, chains, Vec::new(), Vec::new(), rotation_events))
+    }
+
+    // The objects are referring to deduplicated data (e.g. notes at the same place with the
+    // same cut direction are sharing their data).
+
+    fn from_beatmap(beatmap: &Beatmap) -> Result<Self> {
+        let mut note_datas = DataPool::new();
+        let mut get_note_data_index = |x, y, note_type, cut_dir, custom_data_opt: Option<&NoteCustomData>| note_datas.add(Beatmap_V4_NoteData {
+            x,
+            y,
+            note_type: get_raw_note_type(note_type),
+            cut_dir,
+            custom_data: custom_data_opt.map(Beatmap_V3_NoteCustomData::from_custom_data),
+        });
+
+        let mut notes = Vec::new();
+
+        for note in beatmap.get_notes_with_chain_heads() {
+            notes.push(Beatmap_V4_Note {
+                bpm_pos: note.bpm_pos,
+                data_index: get_note_data_index(note.x, note.y, note.note_type, note.cut_dir, note.get_custom_data())?,
+            });
+        }
+
+        let mut arcs = Vec::new();
+        let mut arc_datas = DataPool::new();
+
+        for arc in &beatmap.arcs {
+            arcs.push(Beatmap_V4_Arc {
+                bpm_pos: arc.head.bpm_pos,
+                tail_bpm_pos: arc.tail.bpm_pos,
+                head_data_index: get_note_data_index(arc.head.x, arc.head.y, arc.note_type, arc.head.cut_dir, None)?,
+                tail_data_index: get_note_data_index(arc.tail.x, arc.tail.y, arc.note_type, arc.tail.cut_dir, None)?,
+                data_index: arc_datas.add(Beatmap_V4_ArcData {
+                    multiplier: arc.head.multiplier,
+                    tail_multiplier: arc.tail.multiplier,
+                    mid_anchor: arc.mid_anchor,
+                })?,
+            });
+        }
+
+        let mut chains = Vec::new();
+        let mut chain_datas = DataPool::new();
+
+        for chain in &beatmap.chains {
+            chains.push(Beatmap_V4_Chain {
+                bpm_pos: chain.head.bpm_pos,
+                tail_bpm_pos: chain.tail_bpm_pos,
+                head_data_index: get_note_data_index(chain.head.x, chain.head.y, chain.head.note_type, chain.head.cut_dir, chain.head.get_custom_data())?,
+                data_index: chain_datas.add(Beatmap_V4_ChainData {
+                    tail_x: chain.tail_x,
+                    tail_y: chain.tail_y,
+                    slice_count: chain.slice_count,
+                    squish: chain.squish,
+                })?,
+            });
+        }
+
+        let mut bombs = Vec::new();
+        let mut bomb_datas = DataPool::new();
+
+        for bomb in &beatmap.bombs {
+            bombs.push(Beatmap_V4_Bomb {
+                bpm_pos: bomb.bpm_pos,
+                data_index: bomb_datas.add(Beatmap_V4_BombData {
+                    x: bomb.x,
+                    y: bomb.y,
+                })?,
+            });
+        }
+
+        let mut obstacles = Vec::new();
+        let mut obstacle_datas = DataPool::new();
+
+        for obstacle in &beatmap.obstacles {
+            obstacles.push(Beatmap_V4_Obstacle {
+                bpm_pos: obstacle.bpm_pos,
+                data_index: obstacle_datas.add(Beatmap_V4_ObstacleData {
+                    duration: obstacle.duration,
+                    x: obstacle.x,
+                    y: obstacle.y,
+                    width: obstacle.width,
+                    height: obstacle.height,
+                })?,
+            });
+        }
+
+        let mut rotation_events = Vec::new();
+        let mut rotation_event_datas = DataPool::new();
+
+        for rotation_event in &beatmap.rotation_events {
+            rotation_events.push(Beatmap_V4_RotationEvent {
+                bpm_pos: rotation_event.bpm_pos,
+                data_index: rotation_event_datas.add(Beatmap_V4_RotationEventData {
+                    timing: rotation_event.timing,
+                    rotation: rotation_event.rotation,
+                })?,
+            });
+        }
+
+        Ok(Self {
+            notes,
+            note_datas: note_datas.into_datas(),
+            bombs: Some(bombs),
+            bomb_datas: Some(bomb_datas.into_datas()),
+            obstacles: Some(obstacles),
+            obstacle_datas: Some(obstacle_datas.into_datas()),
+            arcs: Some(arcs),
+            arc_datas: Some(arc_datas.into_datas()),
+            chains: Some(chains),
+            chain_datas: Some(chain_datas.into_datas()),
+            rotation_events: Some(rotation_events),
+            rotation_event_datas: Some(rotation_event_datas.into_datas()),
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V4_Note {
+    #[serde(rename = "b")]
+    bpm_pos: f32,
+    #[serde(rename = "i")]
+    data_index: u32,
+}
+
+impl Validate for Beatmap_V4_Note {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "b", self.bpm_pos);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V4_NoteData {
+    x: u8,
+    y: u8,
+    #[serde(rename = "c")]
+    note_type: u8,
+    #[serde(rename = "d")]
+    cut_dir: NoteCutDir,
+    #[serde(rename = "customData", skip_serializing_if = "Option::is_none")]
+    custom_data: Option<Beatmap_V3_NoteCustomData>,
+}
+
+impl Validate for Beatmap_V4_NoteData {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        if get_note_type(self.note_type).is_some() {
+            validator.check_grid(path, "x", "y", self.x, self.y);
+        }
+
+        validator.validate(path, "customData", &self.custom_data);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V4_Bomb {
+    #[serde(rename = "b")]
+    bpm_pos: f32,
+    #[serde(rename = "i")]
+    data_index: u32,
+}
+
+impl Validate for Beatmap_V4_Bomb {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "b", self.bpm_pos);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V4_BombData {
+    x: u8,
+    y: u8,
+}
+
+impl Validate for Beatmap_V4_BombData {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_grid(path, "x", "y", self.x, self.y);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V4_Arc {
+    #[serde(rename = "hb")]
+    bpm_pos: f32,
+    #[serde(rename = "tb")]
+    tail_bpm_pos: f32,
+    #[serde(rename = "hi")]
+    head_data_index: u32,
+    #[serde(rename = "ti")]
+    tail_data_index: u32,
+    #[serde(rename = "ai")]
+    data_index: u32,
+}
+
+impl Validate for Beatmap_V4_Arc {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "hb", self.bpm_pos);
+        validator.check_bpm_pos(path, "tb", self.tail_bpm_pos);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V4_ArcData {
+    #[serde(rename = "m")]
+    multiplier: f32,
+    #[serde(rename = "tm")]
+    tail_multiplier: f32,
+    #[serde(rename = "a")]
+    mid_anchor: ArcMidAnchor,
+}
+
+impl Validate for Beatmap_V4_ArcData {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_finite(path, "m", self.multiplier);
+        validator.check_finite(path, "tm", self.tail_multiplier);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V4_Chain {
+    #[serde(rename = "hb")]
+    bpm_pos: f32,
+    #[serde(rename = "tb")]
+    tail_bpm_pos: f32,
+    #[serde(rename = "i")]
+    head_data_index: u32,
+    #[serde(rename = "ci")]
+    data_index: u32,
+}
+
+impl Validate for Beatmap_V4_Chain {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "hb", self.bpm_pos);
+        validator.check_bpm_pos(path, "tb", self.tail_bpm_pos);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V4_ChainData {
+    #[serde(rename = "tx")]
+    tail_x: u8,
+    #[serde(rename = "ty")]
+    tail_y: u8,
+    #[serde(rename = "c")]
+    slice_count: u8,
+    #[serde(rename = "s")]
+    squish: f32,
+}
+
+impl Validate for Beatmap_V4_ChainData {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_grid(path, "tx", "ty", self.tail_x, self.tail_y);
+        validate_chain(validator, path, ["c", "s"], self.slice_count, self.squish);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V4_RotationEvent {
+    #[serde(rename = "b")]
+    bpm_pos: f32,
+    #[serde(rename = "i")]
+    data_index: u32,
+}
+
+impl Validate for Beatmap_V4_RotationEvent {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "b", self.bpm_pos);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V4_RotationEventData {
+    #[serde(rename = "t")]
+    timing: RotationTiming,
+    #[serde(rename = "r")]
+    rotation: f32,
+}
+
+impl Validate for Beatmap_V4_RotationEventData {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_finite(path, "r", self.rotation);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V4_Obstacle {
+    #[serde(rename = "b")]
+    bpm_pos: f32,
+    #[serde(rename = "i")]
+    data_index: u32,
+}
+
+impl Validate for Beatmap_V4_Obstacle {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "b", self.bpm_pos);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Beatmap_V4_ObstacleData {
+    #[serde(rename = "d")]
+    duration: f32,
+    x: i32,
+    y: i32,
+    #[serde(rename = "w")]
+    width: i32,
+    #[serde(rename = "h")]
+    height: i32,
+}
+
+impl Validate for Beatmap_V4_ObstacleData {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_non_negative(path, "d", self.duration);
+        validator.check_size(path, "w", self.width);
+        validator.check_size(path, "h", self.height);
+    }
+}
+
+// Squish is the fraction of the curve, where the links are placed (0 places all of them at the head).
+
+fn validate_chain(validator: &mut Validator, path: &str, fields: [&str; 2], slice_count: u8, squish: f32) {
+    let [slice_count_field, squish_field] = fields;
+
+    if slice_count == 0 {
+        validator.report(Severity::Error, path, slice_count_field, "Slice count is zero".to_string());
+    }
+
+    validator.check_finite(path, squish_field, squish);
+}
+
+// Custom data arrays with unexpected length are ignored, that's the reason for the warning.
+
+fn validate_custom_array(validator: &mut Validator, path: &str, field: &str, values_opt: &Option<Vec<f32>>, lens: &[usize]) {
+    if let Some(values) = values_opt {
+        if !lens.contains(&values.len()) {
+            validator.report(Severity::Warning, path, field, format!("Unexpected length, ignored: {}", values.len()));
+        }
+
+        for (index, value) in values.iter().enumerate() {
+            validator.check_finite(path, &format!("{}[{}]", field, index), *value);
+        }
+    }
+}
+
+// Lightshow
+
+#[derive(Deserialize, Serialize)]
+struct Lightshow_V4 {
+    #[serde(rename = "basicEvents", skip_serializing_if = "Option::is_none")]
+    events: Option<Vec<Lightshow_V4_Event>>,
+    #[serde(rename = "basicEventsData", skip_serializing_if = "Option::is_none")]
+    event_datas: Option<Vec<Lightshow_V4_EventData>>,
+}
+
+impl Validate for Lightshow_V4 {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.validate(path, "basicEvents", &self.events);
+        validator.validate(path, "basicEventsData", &self.event_datas);
+
+        if let Some(events) = &self.events {
+            validator.check_indexes(path, "basicEvents", "i", events, |event| event.data_index, self.event_datas.as_ref().map_or(0, Vec::len));
+        }
+    }
+}
+
+impl Lightshow_V4 {
+    fn build(self) -> Vec<LightEvent> {
+        let mut light_events = Vec::new();
+
+        if let Some(raw_events) = self.events && let Some(raw_event_datas) = self.event_datas {
+            for raw_event in raw_events {
+                if let Some(raw_event_data) = raw_event_datas.get(raw_event.data_index as usize) &&
+                   let Some(light_event) = LightEvent::new(raw_event.bpm_pos, raw_event_data.event_type, raw_event_data.value, raw_event_data.float_value_opt.unwrap_or(1.0)) {
+                    light_events.push(light_event);
+                }
+            }
+        }
+
+        light_events
+    }
+
+    fn from_light_events(light_events: &[LightEvent]) -> Result<Self> {
+        let mut events = Vec::new();
+        let mut event_datas = DataPool::new();
+
+        for light_event in light_events {
+            let (event_type, value) = light_event.get_raw();
+
+            events.push(Lightshow_V4_Event {
+                bpm_pos: light_event.bpm_pos,
+                data_index: event_datas.add(Lightshow_V4_EventData {
+                    event_type,
+                    value,
+                    float_value_opt: Some(light_event.brightness),
+                })?,
+            });
+        }
+
+        Ok(Self {
+            events: Some(events),
+            event_datas: Some(event_datas.into_datas()),
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Lightshow_V4_Event {
+    #[serde(rename = "b")]
+    bpm_pos: f32,
+    #[serde(rename = "i")]
+    data_index: u32,
+}
+
+impl Validate for Lightshow_V4_Event {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        validator.check_bpm_pos(path, "b", self.bpm_pos);
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct Lightshow_V4_EventData {
+    #[serde(rename = "t")]
+    event_type: i32,
+    #[serde(rename = "i")]
+    value: i32,
+    #[serde(rename = "f", skip_serializing_if = "Option::is_none")]
+    float_value_opt: Option<f32>,
+}
+
+impl Validate for Lightshow_V4_EventData {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        if let Some(float_value) = self.float_value_opt {
+            validator.check_finite(path, "f", float_value);
+        }
+    }
+}
+
+// FloatColor
+
+// Color::from_srgb_float() truncates, so the smallest float is written, which gives the same byte again.
+
+fn to_srgb_float(color: Color) -> [f32; 3] {
+    color.to_srgb_byte().map(|value| {
+        let float = value as f32 / u8::MAX as f32;
+
+        if ((float * 255.0) as u8) < value {
+            float.next_up()
+        } else {
+            float
+        }
+    })
+}
+
+#[derive(Deserialize, Serialize)]
+struct FloatColor {
+    r: f32,
+    g: f32,
+    b: f32,
+}
+
+impl FloatColor {
+    fn from_color(color: &Color) -> Self {
+        let [r, g, b] = to_srgb_float(*color);
+
+        Self {
+            r,
+            g,
+            b,
+        }
+    }
+}
+
+// Values above 1 are used for boosted colors, so they are accepted with a warning.
+
+impl Validate for FloatColor {
+    fn validate(&self, validator: &mut Validator, path: &str) {
+        for (field, value) in [("r", self.r), ("g", self.g), ("b", self.b)] {
+            if validator.check_finite(path, field, value) && !(0.0..=1.0).contains(&value) {
+                validator.report(Severity::Warning, path, field, format!("Outside of 0..1: {}", value));
+            }
+        }
+    }
+}
+
+// Color
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result_Result<Self, D::Error> {
+        deserializer.deserialize_str(ColorVisitor)
+    }
+}
+
+struct ColorVisitor;
+
+impl<'de> Visitor<'de> for ColorVisitor {
+    type Value = Color;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt_Result {
+        formatter.write_str("valid color")
+    }
+
+    fn visit_str<E: de_Error>(self, v: &str) -> result_Result<Self::Value, E> {
+        let raw_color = u32::from_str_radix(v, 16).map_err(|_| E::custom("invalid color"))?; // TODO: validate: leading +, number of digits, etc.
+        Ok(Color::from_srgb_byte((raw_color >> 24) as u8, (raw_color >> 16) as u8, (raw_color >> 8) as u8))
+    } 
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result_Result<S::Ok, S::Error> {
+        let [r, g, b] = self.to_srgb_byte();
+        serializer.serialize_str(&format!("{:02x}{:02x}{:02x}ff", r, g, b))
+    }
+}
+
+// NoteCutDir
+
+impl<'de> Deserialize<'de> for NoteCutDir {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result_Result<Self, D::Error> {
+        deserializer.deserialize_u64(NoteCutDirVisitor)
+    }
+}
+
+struct NoteCutDirVisitor;
+
+impl<'de> Visitor<'de> for NoteCutDirVisitor {
+    type Value = NoteCutDir;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt_Result {
+        formatter.write_str("valid cut direction")
+    }
+
+    fn visit_u64<E: de_Error>(self, v: u64) -> result_Result<Self::Value, E> {
+        match v {
+            0 => Ok(NoteCutDir::Up),
+            1 => Ok(NoteCutDir::Down),
+            2 => Ok(NoteCutDir::Left),
+            3 => Ok(NoteCutDir::Right),
+            4 => Ok(NoteCutDir::UpLeft),
+            5 => Ok(NoteCutDir::UpRight),
+            6 => Ok(NoteCutDir::DownLeft),
+            7 => Ok(NoteCutDir::DownRight),
+            8 => Ok(NoteCutDir::Any),
+            _ => Err(E::custom("invalid cut direction")),
+        }
+    }    
+}
+
+impl Serialize for NoteCutDir {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result_Result<S::Ok, S::Error> {
+        serializer.serialize_u64(match self {
+            NoteCutDir::Up => 0,
+            NoteCutDir::Down => 1,
+            NoteCutDir::Left => 2,
+            NoteCutDir::Right => 3,
+            NoteCutDir::UpLeft => 4,
+            NoteCutDir::UpRight => 5,
+            NoteCutDir::DownLeft => 6,
+            NoteCutDir::DownRight => 7,
+            NoteCutDir::Any => 8,
+        })
+    }
+}
+
+// RotationTiming
+
+impl<'de> Deserialize<'de> for RotationTiming {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result_Result<Self, D::Error> {
+        deserializer.deserialize_u64(RotationTimingVisitor)
+    }
+}
+
+struct RotationTimingVisitor;
+
+impl<'de> Visitor<'de> for RotationTimingVisitor {
+    type Value = RotationTiming;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt_Result {
+        formatter.write_str("valid rotation timing")
+    }
+
+    fn visit_u64<E: de_Error>(self, v: u64) -> result_Result<Self::Value, E> {
+        match v {
+            0 => Ok(RotationTiming::Early),
+            1 => Ok(RotationTiming::Late),
+            _ => Err(E::custom("invalid rotation timing")),
+        }
+    }
+}
+
+impl Serialize for RotationTiming {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result_Result<S::Ok, S::Error> {
+        serializer.serialize_u64(match self {
+            RotationTiming::Early => 0,
+            RotationTiming::Late => 1,
+        })
+    }
+}
+
+// ArcMidAnchor
+
+impl<'de> Deserialize<'de> for ArcMidAnchor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result_Result<Self, D::Error> {
+        deserializer.deserialize_u64(ArcMidAnchorVisitor)
+    }
+}
+
+struct ArcMidAnchorVisitor;
+
+impl<'de> Visitor<'de> for ArcMidAnchorVisitor {
+    type Value = ArcMidAnchor;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt_Result {
+        formatter.write_str("valid mid-anchor mode")
+    }
+
+    fn visit_u64<E: de_Error>(self, v: u64) -> result_Result<Self::Value, E> {
+        match v {
+            0 => Ok(ArcMidAnchor::Straight),
+            1 => Ok(ArcMidAnchor::Clockwise),
+            2 => Ok(ArcMidAnchor::CounterClockwise),
+            _ => Err(E::custom("invalid mid-anchor mode")),
+        }
+    }
+}
+
+impl Serialize for ArcMidAnchor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result_Result<S::Ok, S::Error> {
+        serializer.serialize_u64(match self {
+            ArcMidAnchor::Straight => 0,
+            ArcMidAnchor::Clockwise => 1,
+            ArcMidAnchor::CounterClockwise => 2,
+        })
+    }
+}
+
+// Raw content of a file of the song.
+
+fn read_file(asset_mgr: &AssetManagerRc, filename: &str) -> Result<Vec<u8>> {
+    let asset_file = asset_mgr.open(&format!("/{}", filename))?;
+    let mut buf = Vec::new();
+    asset_file.read()?.read_to_end(&mut buf).map_err(|_| AssetError::Decode)?;
+
+    Ok(buf)
+}
+
+// The version is usually the first key of the top-level object, then it is read without parsing
+// the rest of the file (the file is parsed only once, into the raw structs of the version).
+// Otherwise the top-level object is scanned, its values are skipped.
+
+fn get_version(buf: &str) -> Result<String> {
+    if let Some(version) = sniff_version(buf) {
+        return Ok(version);
+    }
+
+    let probe: VersionProbe = serde_json::from_str(buf)?;
+    probe.version_v2_opt.or(probe.version_opt).ok_or_else(|| Error::Build("Version not found".to_string()))
+}
+
+#[derive(Deserialize)]
+struct VersionProbe {
+    #[serde(rename = "_version")]
+    version_v2_opt: Option<String>,
+    #[serde(rename = "version")]
+    version_opt: Option<String>,
+}
+
+fn sniff_version(buf: &str) -> Option<String> {
+    let buf = buf.trim_start().strip_prefix('{')?;
+    let (key, buf) = read_json_str(buf)?;
+
+    if key != "_version" && key != "version" {
+        return None;
+    }
+
+    let buf = buf.trim_start().strip_prefix(':')?;
+    let (version, _) = read_json_str(buf)?;
+
+    Some(version)
+}
+
+// Reads a string value, returns the rest of buf after it.
+
+fn read_json_str(buf: &str) -> Option<(String, &str)> {
+    let mut it = serde_json::Deserializer::from_str(buf).into_iter::<String>();
+    let value = it.next()?.ok()?;
+
+    Some((value, &buf[it.byte_offset()..]))
+}
+
+// The version is added to the top-level object (see get_version()).
+
+fn to_json<T: Serialize>(raw: &T, version_key: &str, version: &str) -> Result<String> {
+    let mut top = serde_json::Map::new();
+    top.insert(version_key.to_string(), Value::String(version.to_string()));
+
+    if let Value::Object(fields) = serde_json::to_value(raw)? {
+        top.extend(fields);
+    }
+
+    Ok(serde_json::to_string_pretty(&Value::Object(top))?)
+}
+
+// Collects the unique data of the V4 objects.
+
+struct DataPool<T> {
+    datas: Vec<T>,
+    indexes: HashMap<String, u32>, // Key is the JSON of the data
+}
+
+impl<T: Serialize> DataPool<T> {
+    fn new() -> Self {
+        Self {
+            datas: Vec::new(),
+            indexes: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, data: T) -> Result<u32> {
+        let key = serde_json::to_string(&data)?;
+
+        if let Some(index) = self.indexes.get(&key) {
+            return Ok(*index);
+        }
+
+        let index = self.datas.len() as u32;
+        self.datas.push(data);
+        self.indexes.insert(key, index);
+
+        Ok(index)
+    }
+
+    fn into_datas(self) -> Vec<T> {
+        self.datas
+    }
+}
+
+fn get_note_type(raw_note_type: u8) -> Option<NoteType> {
+    match raw_note_type {
+        0 => Some(NoteType::Left),
+        1 => Some(NoteType::Right),
+        _ => None,
+    }
+}
+
+fn get_raw_note_type(note_type: NoteType) -> u8 {
+    match note_type {
+        NoteType::Left => 0,
+        NoteType::Right => 1,
+    }
+}
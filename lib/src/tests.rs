@@ -1,9 +1,15 @@
 use std::fs::{self, File};
-use std::io::Read;
+use std::io::{Cursor, Read};
 use std::sync::Arc;
 
+use cgmath::{Quaternion, Vector3};
+
 use crate::asset::{AssetError, AssetFileBox, AssetFileTrait, AssetManagerRc, AssetManagerTrait, AssetResult};
-use crate::songinfo::SongInfo;
+use crate::bsor::{self, Replay, ReplayCutInfo, ReplayFrame, ReplayHeight, ReplayInfo, ReplayNote, ReplayNoteEvent, ReplayNoteKind, ReplayPause, ReplayPose, ReplayWall};
+use crate::scene::PracticeParam;
+use crate::score::{self, ACCURACY_DIST, Multiplier, SCORE_ACCURACY, SCORE_CHAIN_LINK, SCORE_NOTE, SCORE_PRE_SWING};
+use crate::songdef::SongDifficulty;
+use crate::songinfo::{BPMInfo, Beatmap, BeatmapAnalysis, Error, FormatVersion, Note, NoteCutDir, NoteType, Severity, SongFile, SongInfo, SwingStatus, Timing};
 
 const PREFIX: &str = "testmap";
 
@@ -55,6 +61,50 @@ impl AssetFileTrait for AssetFile {
     }
 }
 
+// Assets of a song given by their content, used for malformed songs.
+
+struct MemAssetManager {
+    files: Vec<(String, String)>,
+}
+
+impl AssetManagerTrait for MemAssetManager {
+    fn open(&self, name: &str) -> AssetResult<AssetFileBox> {
+        let (_, content) = self.files.iter().find(|(filename, _)| *filename == name).ok_or(AssetError::NotFound)?;
+        Ok(Box::new(MemAssetFile(content.clone())))
+    }
+}
+
+struct MemAssetFile(String);
+
+// Info.dat (V2) of a song with a single Easy beatmap in Easy.dat, custom_data is the custom data of the beatmap.
+
+fn mem_info(bpm: &str, characteristic: &str, custom_data: &str) -> String {
+    format!(r#"{{"_version": "2.0.0", "_songAuthorName": "", "_songName": "", "_songSubName": "", "_songFilename": "song.ogg", "_beatsPerMinute": {},
+        "_difficultyBeatmapSets": [{{"_beatmapCharacteristicName": "{}", "_difficultyBeatmaps": [{{"_difficulty": "Easy", "_beatmapFilename": "Easy.dat", "_noteJumpMovementSpeed": 10, "_noteJumpStartBeatOffset": 0,
+        "_customData": {}}}]}}]}}"#, bpm, characteristic, custom_data)
+}
+
+// Song of Info.dat and the beatmaps (filename, content).
+
+fn mem_song(info: &str, beatmaps: &[(&str, &str)]) -> MemAssetManager {
+    let mut files = vec![("/Info.dat".to_string(), info.to_string())];
+    files.extend(beatmaps.iter().map(|(filename, content)| (format!("/{}", filename), content.to_string())));
+
+    MemAssetManager {
+        files,
+    }
+}
+
+impl AssetFileTrait for MemAssetFile {
+    fn read(&self) -> AssetResult<Box<dyn Read + Send + Sync>> {
+        Ok(Box::new(Cursor::new(self.0.clone().into_bytes())))
+    }
+
+    fn read_str(&self) -> AssetResult<String> {
+        Ok(self.0.clone())
+    }
+}
+
 #[test]
 fn test_map() {
     for entry in fs::read_dir(PREFIX).expect("Unable to read directory").map(|entry| entry.expect("Unable to read entry")) {
@@ -73,3 +123,704 @@ fn test_map() {
         }
     }
 }
+
+fn load_timing(dir: &str, difficulty: SongDifficulty) -> Timing {
+    let asset_mgr: AssetManagerRc = Arc::new(AssetManager::new(dir));
+
+    let song_info = SongInfo::load(asset_mgr).expect("Unable to load info");
+    let bpm_info = song_info.get_bpm_info().expect("Unable to load bpm info");
+
+    let beatmap_info = song_info.get_beatmap_infos().iter().find(|beatmap_info| beatmap_info.get_difficulty() == difficulty).expect("Unable to find beatmap");
+    let beatmap = beatmap_info.load().expect("Unable to load beatmap");
+
+    Timing::new(&bpm_info, beatmap.get_bpm_changes())
+}
+
+fn assert_ts(timing: &Timing, bpm_pos: f32, ts: f32) {
+    let actual_ts = timing.get_ts(bpm_pos).expect("Unable to get ts");
+    assert!((actual_ts - ts).abs() < 0.001, "bpm_pos: {}, expected ts: {}, actual ts: {}", bpm_pos, ts, actual_ts);
+}
+
+#[test]
+fn test_timing_fixed() {
+    // V2 beatmap without BPM changes.
+
+    let timing = load_timing("b7aa (Venom of Venus - Cyrix)", SongDifficulty::Expert);
+
+    assert_ts(&timing, 0.0, 0.0);
+    assert_ts(&timing, 164.0, 60.0);
+    assert_ts(&timing, -41.0, -15.0);
+
+    // V3 beatmap without BPM events.
+
+    let timing = load_timing("3904d (Kool-Aid - Bytrius)", SongDifficulty::Expert);
+
+    assert_ts(&timing, 150.0, 60.0);
+}
+
+#[test]
+fn test_timing_bpm_changes() {
+    // V2 beatmap with BPM changes in custom data: 205 -> 210 @ 535.234 -> 205 @ 633.938 -> 210 @ 700.938.
+
+    let timing = load_timing("17907 (Circle With Me - ExistenceExpire)", SongDifficulty::ExpertPlus);
+
+    let ts1 = 535.234 * 60.0 / 205.0;
+    let ts2 = ts1 + (633.938 - 535.234) * 60.0 / 210.0;
+    let ts3 = ts2 + (700.938 - 633.938) * 60.0 / 205.0;
+
+    assert_ts(&timing, 100.0, 100.0 * 60.0 / 205.0);
+    assert_ts(&timing, 535.234, ts1);
+    assert_ts(&timing, 600.0, ts1 + (600.0 - 535.234) * 60.0 / 210.0);
+    assert_ts(&timing, 633.938, ts2);
+    assert_ts(&timing, 700.938, ts3);
+    assert_ts(&timing, 750.0, ts3 + (750.0 - 700.938) * 60.0 / 210.0);
+}
+
+#[test]
+fn test_timing_mapped() {
+    // V4 BPM map with a single region: beats 0..1111.04 are samples 0..16333375 at 44100 Hz.
+
+    let timing = load_timing("49ccd (Divinity - Scorefam)", SongDifficulty::ExpertPlus);
+
+    let ts_end = 16333375.0 / 44100.0;
+
+    assert_ts(&timing, 0.0, 0.0);
+    assert_ts(&timing, 1111.04 / 2.0, ts_end / 2.0);
+    assert!(timing.get_ts(-1.0).is_none());
+    assert!(timing.get_ts(1200.0).is_none());
+}
+
+#[test]
+fn test_jump() {
+    // Venom of Venus at 164 BPM: Easy (NJS 12, offset 0.5) and Expert (NJS 20, offset -0.25).
+
+    let asset_mgr: AssetManagerRc = Arc::new(AssetManager::new("b7aa (Venom of Venus - Cyrix)"));
+    let song_info = SongInfo::load(asset_mgr).expect("Unable to load info");
+    let spb = 60.0 / 164.0;
+
+    let find_beatmap_info = |difficulty| song_info.get_beatmap_infos().iter().find(|beatmap_info| beatmap_info.get_difficulty() == difficulty).expect("Unable to find beatmap");
+
+    // Half jump distance is 12 * 4 * spb = 17.56 m, it is short enough.
+
+    let jump_info = find_beatmap_info(SongDifficulty::Easy).calc_jump_info(spb, None);
+    assert!((jump_info.get_half_jump_duration() - 4.5 * spb).abs() < 0.001);
+    assert!((jump_info.get_jump_dist() - 2.0 * 12.0 * 4.5 * spb).abs() < 0.001);
+
+    // Half jump distance is 20 * 4 * spb = 29.27 m, the half jump duration is halved.
+
+    let beatmap_info = find_beatmap_info(SongDifficulty::Expert);
+
+    let jump_info = beatmap_info.calc_jump_info(spb, None);
+    assert!((jump_info.get_half_jump_duration() - 1.75 * spb).abs() < 0.001);
+    assert!((jump_info.get_jump_dist() - 2.0 * 20.0 * 1.75 * spb).abs() < 0.001);
+
+    // Reaction time is overridden.
+
+    let jump_info = beatmap_info.calc_jump_info(spb, Some(0.5));
+    assert!((jump_info.get_half_jump_duration() - 0.5).abs() < 0.001);
+    assert!((jump_info.get_jump_dist() - 20.0).abs() < 0.001);
+}
+
+#[test]
+fn test_validation() {
+    let beatmap = |time: &str| format!(r#"{{"_version": "2.2.0", "_notes": [{{"_time": 1, "_lineIndex": 0, "_lineLayer": 0, "_type": 0, "_cutDirection": 1}}, {{"_time": {}, "_lineIndex": 1, "_lineLayer": 0, "_type": 1, "_cutDirection": 1}}]}}"#, time);
+    let load = |bpm: &str, time: &str| SongInfo::load(Arc::new(mem_song(&mem_info(bpm, "Standard", "{}"), &[("Easy.dat", &beatmap(time))])));
+
+    // Negative BPM is rejected.
+
+    let Err(Error::Validation(diagnostics)) = load("-120", "2") else {
+        panic!("Negative BPM accepted");
+    };
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].get_severity(), Severity::Error);
+    assert_eq!(diagnostics[0].get_path(), "$._beatsPerMinute");
+
+    // Infinite beat position (overflow of f32) is rejected, negative beat position is accepted with a warning.
+
+    let song_info = load("120", "1e39").expect("Unable to load info");
+    let Err(Error::Validation(diagnostics)) = song_info.get_beatmap_infos()[0].load() else {
+        panic!("Infinite beat position accepted");
+    };
+
+    assert_eq!(diagnostics[0].get_path(), "$._notes[1]._time");
+
+    let song_info = load("120", "-2").expect("Unable to load info");
+    let beatmap = song_info.get_beatmap_infos()[0].load().expect("Unable to load beatmap");
+
+    assert_eq!(beatmap.get_diagnostics().len(), 1);
+    assert_eq!(beatmap.get_diagnostics()[0].get_severity(), Severity::Warning);
+}
+
+#[test]
+fn test_bombs() {
+    let info = mem_info("120", "Standard", "{}");
+    let load = |beatmap: &str| SongInfo::load(Arc::new(mem_song(&info, &[("Easy.dat", beatmap)]))).expect("Unable to load info").get_beatmap_infos()[0].load().expect("Unable to load beatmap");
+
+    // V2 bombs are notes of type 3, V3 and V4 bombs are separate objects.
+
+    let beatmaps = [
+        load(r#"{"_version": "2.2.0", "_notes": [{"_time": 1, "_lineIndex": 0, "_lineLayer": 0, "_type": 0, "_cutDirection": 1}, {"_time": 2, "_lineIndex": 3, "_lineLayer": 2, "_type": 3, "_cutDirection": 0}]}"#),
+        load(r#"{"version": "3.3.0", "colorNotes": [{"b": 1, "x": 0, "y": 0, "c": 0, "d": 1}], "bombNotes": [{"b": 2, "x": 3, "y": 2}]}"#),
+        load(r#"{"version": "4.1.0", "colorNotes": [{"b": 1, "i": 0}], "colorNotesData": [{"x": 0, "y": 0, "c": 0, "d": 1}], "bombNotes": [{"b": 2, "i": 0}], "bombNotesData": [{"x": 3, "y": 2}]}"#),
+    ];
+
+    for beatmap in &beatmaps {
+        assert_eq!(beatmap.get_notes().len(), 1);
+        assert_eq!(beatmap.get_bombs().len(), 1);
+
+        let bomb = &beatmap.get_bombs()[0];
+        assert_eq!((bomb.get_bpm_pos(), bomb.get_x(), bomb.get_y()), (2.0, 3, 2));
+    }
+
+    // V4 bombs with invalid data index are ignored with a warning.
+
+    let beatmap = load(r#"{"version": "4.1.0", "colorNotes": [], "colorNotesData": [], "bombNotes": [{"b": 2, "i": 0}, {"b": 3, "i": 1}], "bombNotesData": [{"x": 3, "y": 2}]}"#);
+
+    assert_eq!(beatmap.get_bombs().len(), 1);
+    assert_eq!(beatmap.get_diagnostics().len(), 1);
+    assert_eq!(beatmap.get_diagnostics()[0].get_severity(), Severity::Warning);
+    assert_eq!(beatmap.get_diagnostics()[0].get_path(), "$.bombNotes[1].i");
+}
+
+fn load_files(files: &[SongFile]) -> SongInfo {
+    let asset_mgr = Arc::new(MemAssetManager {
+        files: Vec::from_iter(files.iter().map(|file| (format!("/{}", file.get_filename()), file.get_content().to_string()))),
+    });
+
+    SongInfo::load(asset_mgr).expect("Unable to load written info")
+}
+
+#[test]
+fn test_write() {
+    for entry in fs::read_dir(PREFIX).expect("Unable to read directory").map(|entry| entry.expect("Unable to read entry")) {
+        let filename = entry.file_name();
+        let dir = filename.to_str().unwrap();
+
+        let asset_mgr: AssetManagerRc = Arc::new(AssetManager::new(dir));
+        let song_info = SongInfo::load(asset_mgr).expect("Unable to load info");
+        let bpm_info = song_info.get_bpm_info().expect("Unable to load bpm info");
+
+        for version in [FormatVersion::V2, FormatVersion::V3, FormatVersion::V4] {
+            println!("write {} in {:?}", dir, version);
+
+            // Arcs and chains can't be written in V2.
+
+            let files = match song_info.write(version) {
+                Ok(files) => files,
+                Err(Error::Build(e)) if version == FormatVersion::V2 => {
+                    println!("skipped: {}", e);
+                    continue;
+                },
+                Err(e) => panic!("Unable to write: {}", e),
+            };
+
+            // Writing the loaded files again gives the same files.
+
+            let written_song_info = load_files(&files);
+            let written_files = written_song_info.write(version).expect("Unable to write again");
+
+            assert_eq!(files.len(), written_files.len());
+
+            for (file, written_file) in files.iter().zip(&written_files) {
+                assert_eq!(file.get_filename(), written_file.get_filename());
+                assert!(file.get_content() == written_file.get_content(), "{} differs", file.get_filename());
+            }
+
+            // The objects are kept at the same time.
+
+            let written_bpm_info = written_song_info.get_bpm_info().expect("Unable to load written bpm info");
+
+            for (beatmap_info, written_beatmap_info) in song_info.get_beatmap_infos().iter().zip(written_song_info.get_beatmap_infos()) {
+                let beatmap = beatmap_info.load().expect("Unable to load beatmap");
+                let written_beatmap = written_beatmap_info.load().expect("Unable to load written beatmap");
+
+                let timing = Timing::new(&bpm_info, beatmap.get_bpm_changes());
+                let written_timing = Timing::new(&written_bpm_info, written_beatmap.get_bpm_changes());
+
+                assert_eq!(beatmap.get_notes().len(), written_beatmap.get_notes().len());
+                assert_eq!(beatmap.get_chains().len(), written_beatmap.get_chains().len());
+                assert_eq!(beatmap.get_light_events().len(), written_beatmap.get_light_events().len());
+
+                for (note, written_note) in beatmap.get_notes().iter().zip(written_beatmap.get_notes()) {
+                    assert_eq!((note.get_x(), note.get_y()), (written_note.get_x(), written_note.get_y()));
+
+                    if let Some(ts) = timing.get_ts(note.get_bpm_pos()) {
+                        let written_ts = written_timing.get_ts(written_note.get_bpm_pos()).expect("Unable to get ts");
+                        assert!((ts - written_ts).abs() < 0.01, "expected ts: {}, actual ts: {}", ts, written_ts);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_write_unknown_characteristic() {
+    let beatmap = r#"{"_version": "2.2.0", "_notes": []}"#;
+    let song_info = SongInfo::load(Arc::new(mem_song(&mem_info("120", "Ghost", "{}"), &[("Easy.dat", beatmap)]))).expect("Unable to load info");
+
+    // The characteristic would be written as "Unknown", so it is refused.
+
+    assert!(matches!(song_info.write(FormatVersion::V2), Err(Error::Build(_))));
+}
+
+#[test]
+fn test_write_dedup() {
+    // V2 notes at the same place with the same cut direction are sharing their data in V4.
+
+    let asset_mgr: AssetManagerRc = Arc::new(AssetManager::new("b7aa (Venom of Venus - Cyrix)"));
+    let song_info = SongInfo::load(asset_mgr).expect("Unable to load info");
+    let files = song_info.write(FormatVersion::V4).expect("Unable to write");
+
+    let file = files.iter().find(|file| file.get_filename() == "ExpertStandard.dat").expect("Unable to find beatmap");
+    let value: serde_json::Value = serde_json::from_str(file.get_content()).expect("Unable to parse beatmap");
+
+    let note_count = value["colorNotes"].as_array().expect("Notes expected").len();
+    let note_data_count = value["colorNotesData"].as_array().expect("Note datas expected").len();
+    assert!(note_data_count < note_count / 4, "notes: {}, note datas: {}", note_count, note_data_count);
+
+    // Lighting events are moved to a lightshow.
+
+    assert!(files.iter().any(|file| file.get_filename() == "ExpertStandard.lightshow.dat"));
+}
+
+#[test]
+fn test_note_custom_data() {
+    let beatmap = r#"{"_version": "2.2.0", "_notes": [{"_time": 1, "_lineIndex": 0, "_lineLayer": 0, "_type": 0, "_cutDirection": 1},
+        {"_time": 2, "_lineIndex": 1, "_lineLayer": 0, "_type": 1, "_cutDirection": 1, "_customData": {"_color": [1, 0, 0, 1], "_position": [-0.5, 1.5], "_disableSpawnEffect": true, "_track": "unknown"}}]}"#;
+    let song_info = SongInfo::load(Arc::new(mem_song(&mem_info("120", "Standard", "{}"), &[("Easy.dat", beatmap)]))).expect("Unable to load info");
+
+    // Unknown fields are ignored, the custom data is kept when written in other versions.
+
+    for version in [FormatVersion::V2, FormatVersion::V3, FormatVersion::V4] {
+        let song_info = load_files(&song_info.write(version).expect("Unable to write"));
+        let beatmap = song_info.get_beatmap_infos()[0].load().expect("Unable to load beatmap");
+        let notes = beatmap.get_notes();
+
+        assert!(notes[0].get_custom_data().is_none());
+
+        let custom_data = notes[1].get_custom_data().expect("Custom data expected");
+        assert_eq!(custom_data.get_color().map(|color| color.to_srgb_byte()), Some([255, 0, 0]));
+        assert_eq!(custom_data.get_pos(), Some((1.5, 1.5)));
+        assert!(custom_data.is_spawn_effect_disabled());
+    }
+}
+
+#[test]
+fn test_songcore_info() {
+    let custom_data = r#"{"_difficultyLabel": "Wobbly", "_requirements": ["Chroma", "Noodle Extensions"], "_suggestions": ["Cinema"], "_warnings": ["Flashing lights"]}"#;
+    let beatmap = r#"{"_version": "2.2.0", "_notes": []}"#;
+    let song_info = SongInfo::load(Arc::new(mem_song(&mem_info("120", "Standard", custom_data), &[("Easy.dat", beatmap)]))).expect("Unable to load info");
+
+    // Chroma is supported, Noodle Extensions only with the custom data applied. The info is kept when written in other versions.
+
+    for version in [FormatVersion::V2, FormatVersion::V4] {
+        let song_info = load_files(&song_info.write(version).expect("Unable to write"));
+        let beatmap_info = &song_info.get_beatmap_infos()[0];
+        let songcore_info = beatmap_info.get_songcore_info();
+
+        assert_eq!(songcore_info.get_label(), Some("Wobbly"));
+        assert_eq!(songcore_info.get_suggestions(), ["Cinema"]);
+        assert_eq!(songcore_info.get_warnings(), ["Flashing lights"]);
+        assert!(songcore_info.get_information().is_empty());
+        assert_eq!(beatmap_info.get_unsupported_requirements(false), ["Noodle Extensions"]);
+        assert!(beatmap_info.get_unsupported_requirements(true).is_empty());
+    }
+}
+
+#[test]
+fn test_metadata() {
+    let asset_mgr: AssetManagerRc = Arc::new(AssetManager::new("17907 (Circle With Me - ExistenceExpire)"));
+    let song_info = SongInfo::load(asset_mgr).expect("Unable to load info");
+    let metadata = song_info.get_metadata();
+
+    assert_eq!(metadata.get_level_author(), "ExistenceExpire");
+    assert_eq!(metadata.get_cover_filename(), Some("cover.jpg"));
+    assert_eq!(metadata.get_preview_start(), 127.0);
+    assert_eq!(metadata.get_environment_names(), ["DefaultEnvironment"]);
+
+    // The mappers of the song are moved to the beatmaps in V4.
+
+    let written_song_info = load_files(&song_info.write(FormatVersion::V4).expect("Unable to write"));
+    assert_eq!(written_song_info.get_metadata().get_level_author(), "ExistenceExpire");
+    assert_eq!(written_song_info.get_beatmap_infos()[0].get_mappers(), ["ExistenceExpire"]);
+    assert_eq!(written_song_info.get_environment_name(&written_song_info.get_beatmap_infos()[0]), Some("DefaultEnvironment"));
+
+    // Song time offset is shifting the timestamps.
+
+    let timing = load_timing("17907 (Circle With Me - ExistenceExpire)", SongDifficulty::Expert).with_offset(0.5);
+    assert_ts(&timing, 0.0, 0.5);
+}
+
+#[test]
+fn test_analysis() {
+    // 120 BPM: the beats are 0.5 s apart.
+
+    let notes = vec![
+        Note::new(0.0, 1, 0, NoteType::Left, NoteCutDir::Down).unwrap(),
+        Note::new(0.05, 1, 1, NoteType::Left, NoteCutDir::Down).unwrap(), // Stack
+        Note::new(0.0, 2, 0, NoteType::Right, NoteCutDir::Down).unwrap(),
+        Note::new(1.0, 2, 1, NoteType::Right, NoteCutDir::Up).unwrap(),
+        Note::new(1.5, 2, 1, NoteType::Right, NoteCutDir::Up).unwrap(), // Parity error
+        Note::new(4.0, 2, 2, NoteType::Right, NoteCutDir::Up).unwrap(), // Reset
+    ];
+
+    let beatmap = Beatmap::from_notes(notes);
+    let timing = Timing::new(&BPMInfo::Fixed(120.0), beatmap.get_bpm_changes());
+    let analysis = BeatmapAnalysis::new(&beatmap, &timing);
+
+    assert_eq!(analysis.get_note_count(), 6);
+    assert_eq!(analysis.get_nps(), 3.0);
+    assert_eq!(analysis.get_peak_nps(), 2.5);
+    assert_eq!(analysis.get_peak_ts(), 0.0);
+
+    assert_eq!(analysis.get_swings(NoteType::Left).len(), 1);
+
+    let statuses: Vec<_> = analysis.get_swings(NoteType::Right).iter().map(|swing| swing.get_status()).collect();
+    assert!(statuses == [SwingStatus::Ok, SwingStatus::Ok, SwingStatus::ParityError, SwingStatus::Reset]);
+    assert_eq!(analysis.get_parity_error_count(), 1);
+    assert_eq!(analysis.get_reset_count(), 1);
+}
+
+#[test]
+fn test_hash() {
+    // V2: Info.dat is followed by the beatmap files in the order of Info.dat.
+
+    let asset_mgr: AssetManagerRc = Arc::new(AssetManager::new("17907 (Circle With Me - ExistenceExpire)"));
+    let song_info = SongInfo::load(asset_mgr).expect("Unable to load info");
+    assert_eq!(song_info.calc_hash().expect("Unable to calculate hash"), "27f664ee61c5eacd925259f77667ded7a639bee4");
+
+    // V4: Info.dat is followed by AudioData.dat, the beatmap files are followed by their lightshow files.
+
+    let asset_mgr: AssetManagerRc = Arc::new(AssetManager::new("49ccd (Divinity - Scorefam)"));
+    let song_info = SongInfo::load(asset_mgr).expect("Unable to load info");
+    assert_eq!(song_info.calc_hash().expect("Unable to calculate hash"), "9aa91404b86e51eb2b35e5d84757e4f21ec7257c");
+}
+
+#[test]
+fn test_practice_param() {
+    // The settings of practice mode are chosen by the player, invalid ones are refused.
+
+    assert!(PracticeParam::new(0.0, None, 1.0).is_ok());
+    assert!(PracticeParam::new(30.0, Some((10.0, 20.0)), 0.5).is_ok());
+
+    assert!(PracticeParam::new(-1.0, None, 1.0).is_err());
+    assert!(PracticeParam::new(f32::NAN, None, 1.0).is_err());
+    assert!(PracticeParam::new(0.0, None, 0.0).is_err());
+    assert!(PracticeParam::new(0.0, Some((20.0, 20.0)), 1.0).is_err());
+    assert!(PracticeParam::new(0.0, Some((-5.0, 20.0)), 1.0).is_err());
+}
+
+#[test]
+fn test_multiplier() {
+    // The multiplier is doubled after 2, 4 and 8 hits in a row, the last hit already gets the doubled one.
+
+    let mut multiplier = Multiplier::new();
+    assert_eq!(multiplier.get(), 1);
+
+    let values: Vec<_> = (0..16).map(|_| {
+        multiplier.hit();
+        multiplier.get()
+    }).collect();
+    assert_eq!(values, [1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 4, 4, 4, 8, 8, 8]);
+
+    // A miss halves it (down to 1) and resets the progress.
+
+    multiplier.miss();
+    assert_eq!(multiplier.get(), 4);
+
+    for _ in 0..7 {
+        multiplier.hit();
+    }
+    assert_eq!(multiplier.get(), 4);
+
+    multiplier.hit();
+    assert_eq!(multiplier.get(), 8);
+
+    for value in [4, 2, 1, 1] {
+        multiplier.miss();
+        assert_eq!(multiplier.get(), value);
+    }
+}
+
+#[test]
+fn test_max_score() {
+    // Known values of the game: 115 for a single note and 920 * notes - 7245 from 14 notes on.
+
+    let calc_max_score = |count: usize| score::calc_max_score(Vec::from_iter((0..count).map(|i| (i as f32, SCORE_NOTE))));
+
+    assert_eq!(calc_max_score(0), 0);
+    assert_eq!(calc_max_score(1), 115);
+    assert_eq!(calc_max_score(5), 115 + 4 * 2 * 115);
+    assert_eq!(calc_max_score(13), 115 * (1 + 4 * 2 + 8 * 4));
+    assert_eq!(calc_max_score(14), 920 * 14 - 7245);
+    assert_eq!(calc_max_score(100), 920 * 100 - 7245);
+
+    // The notes are multiplied in the order of their time.
+
+    assert_eq!(score::calc_max_score(vec![(1.0, SCORE_CHAIN_LINK), (0.0, SCORE_NOTE)]), SCORE_NOTE + 2 * SCORE_CHAIN_LINK);
+}
+
+#[test]
+fn test_swing_score() {
+    // The ratio is clamped to 0..1, the score is rounded.
+
+    assert_eq!(score::calc_swing_score(-0.5, SCORE_PRE_SWING), 0);
+    assert_eq!(score::calc_swing_score(0.0, SCORE_PRE_SWING), 0);
+    assert_eq!(score::calc_swing_score(1.0, SCORE_PRE_SWING), SCORE_PRE_SWING);
+    assert_eq!(score::calc_swing_score(1.5, SCORE_PRE_SWING), SCORE_PRE_SWING);
+    assert_eq!(score::calc_swing_score(0.49, SCORE_ACCURACY), 7);
+    assert_eq!(score::calc_swing_score(0.5, SCORE_ACCURACY), 8);
+}
+
+#[test]
+fn test_accuracy_score() {
+    // Like in BS, the accuracy is rated over 0.3 m from the center of the note (the swing is not rated here).
+
+    let calc_accuracy_score = |cut_distance_to_center: f32| score::calc_note_score(&ReplayNote {
+        note_id: bsor::calc_note_id(ReplayNoteKind::Normal, 1, 0, 0, 1),
+        event_time: 1.0,
+        spawn_time: 1.0,
+        event_type: ReplayNoteEvent::Good,
+        cut_info_opt: Some(ReplayCutInfo {
+            speed_ok: true,
+            direction_ok: true,
+            saber_type_ok: true,
+            was_cut_too_soon: false,
+            saber_speed: 4.0,
+            saber_dir: Vector3::new(0.0, -1.0, 0.0),
+            saber_type: 0,
+            time_deviation: 0.0,
+            cut_dir_deviation: 0.0,
+            cut_point: Vector3::new(0.0, 0.0, 0.0),
+            cut_normal: Vector3::new(1.0, 0.0, 0.0),
+            cut_distance_to_center,
+            cut_angle: 90.0,
+            before_cut_rating: 0.0,
+            after_cut_rating: 0.0,
+        }),
+    });
+
+    assert_eq!(calc_accuracy_score(0.0), SCORE_ACCURACY);
+    assert_eq!(calc_accuracy_score(0.15), 8);
+    assert_eq!(calc_accuracy_score(0.2), 5);
+    assert_eq!(calc_accuracy_score(ACCURACY_DIST), 0);
+    assert_eq!(calc_accuracy_score(0.4), 0);
+}
+
+#[test]
+fn test_bsor_write() {
+    // Header, the info section and the empty sections in their order, see https://github.com/BeatLeader/BS-Open-Replay .
+
+    let replay = Replay {
+        info: ReplayInfo {
+            version: "0.1.0".to_string(),
+            timestamp: "1700000000".to_string(),
+            hash: "27f664ee61c5eacd925259f77667ded7a639bee4".to_string(),
+            song_name: "Café".to_string(),
+            difficulty: "ExpertPlus".to_string(),
+            score: 123456,
+            mode: "Standard".to_string(),
+            modifiers: "NF".to_string(),
+            jump_distance: 24.0,
+            left_handed: true,
+            height: 1.75,
+            ..ReplayInfo::default()
+        },
+        frames: Vec::new(),
+        notes: Vec::new(),
+        walls: Vec::new(),
+        heights: Vec::new(),
+        pauses: Vec::new(),
+    };
+
+    let mut data = Vec::new();
+    replay.write(&mut data).expect("Unable to write replay");
+
+    // Strings are prefixed with their length in bytes (UTF-8).
+
+    let string = |s: &str| [(s.len() as i32).to_le_bytes().as_slice(), s.as_bytes()].concat();
+
+    let mut expected = vec![0x69, 0x3d, 0x2d, 0x44, 1, 0];
+
+    for s in ["0.1.0", "", "1700000000", "", "", "", "", "", "", "27f664ee61c5eacd925259f77667ded7a639bee4", "Café", "", "ExpertPlus"] {
+        expected.extend(string(s));
+    }
+
+    expected.extend(123456_i32.to_le_bytes());
+
+    for s in ["Standard", "", "NF"] {
+        expected.extend(string(s));
+    }
+
+    expected.extend(24.0_f32.to_le_bytes());
+    expected.push(1);
+    expected.extend(1.75_f32.to_le_bytes());
+
+    for _ in 0..3 {
+        expected.extend(0.0_f32.to_le_bytes());
+    }
+
+    for section in 1..=5 {
+        expected.push(section);
+        expected.extend(0_i32.to_le_bytes());
+    }
+
+    assert_eq!(data, expected);
+    assert_eq!(string("Café")[..4], [5, 0, 0, 0]);
+
+    // The replays of a beatmap are found by their filename prefix.
+
+    assert_eq!(replay.info.calc_filename(), "27f664ee61c5eacd925259f77667ded7a639bee4-Standard-ExpertPlus-1700000000.bsor");
+}
+
+#[test]
+fn test_bsor_note_kind() {
+    // Note ids of BeatLeader replays: (scoring type + 2) * 10000 + line index * 1000 + line layer * 100 + color * 10 + cut direction,
+    // where bombs have no color (-1) and no cut direction (9).
+
+    assert!(matches!(bsor::calc_note_kind(31001), ReplayNoteKind::Normal));
+    assert!(matches!(bsor::calc_note_kind(19999), ReplayNoteKind::Bomb));
+    assert!(matches!(bsor::calc_note_kind(22099), ReplayNoteKind::Bomb));
+    assert!(matches!(bsor::calc_note_kind(40113), ReplayNoteKind::ArcHead));
+    assert!(matches!(bsor::calc_note_kind(53212), ReplayNoteKind::ArcTail));
+    assert!(matches!(bsor::calc_note_kind(60210), ReplayNoteKind::ChainHead));
+    assert!(matches!(bsor::calc_note_kind(72108), ReplayNoteKind::ChainLink));
+    assert!(matches!(bsor::calc_note_kind(81008), ReplayNoteKind::Normal)); // Arc head and tail
+
+    // The recorded ids are the same.
+
+    assert_eq!(bsor::calc_note_id(ReplayNoteKind::Normal, 1, 0, 0, 1), 31001);
+    assert_eq!(bsor::calc_note_id(ReplayNoteKind::Bomb, 0, 0, 0, 0), 19999);
+    assert_eq!(bsor::calc_note_id(ReplayNoteKind::ArcHead, 0, 1, 1, 3), 40113);
+    assert_eq!(bsor::calc_note_id(ReplayNoteKind::ChainLink, 2, 1, 0, 0), 72108);
+}
+
+#[test]
+fn test_bsor_round_trip() {
+    // A written replay is read back with its frames, note events, walls, heights and pauses.
+
+    let pose = |x: f32| ReplayPose {
+        pos: Vector3::new(x, 1.5, -0.25),
+        rot: Quaternion::new(0.5, 0.5, -0.5, 0.5),
+    };
+
+    let cut_info = ReplayCutInfo {
+        speed_ok: true,
+        direction_ok: true,
+        saber_type_ok: false,
+        was_cut_too_soon: false,
+        saber_speed: 4.5,
+        saber_dir: Vector3::new(0.0, -1.0, 0.0),
+        saber_type: 1,
+        time_deviation: -0.01,
+        cut_dir_deviation: 12.5,
+        cut_point: Vector3::new(0.1, 0.9, 0.0),
+        cut_normal: Vector3::new(1.0, 0.0, 0.0),
+        cut_distance_to_center: 0.05,
+        cut_angle: 110.0,
+        before_cut_rating: 1.0,
+        after_cut_rating: 0.75,
+    };
+
+    let replay = Replay {
+        info: ReplayInfo {
+            version: "0.1.0".to_string(),
+            hash: "27f664ee61c5eacd925259f77667ded7a639bee4".to_string(),
+            modifiers: "NF".to_string(),
+            jump_distance: 24.0,
+            fail_time: 12.5,
+            ..ReplayInfo::default()
+        },
+        frames: vec![
+            ReplayFrame { time: 0.0, fps: 90, head: pose(0.0), left: pose(-0.25), right: pose(0.25) },
+            ReplayFrame { time: 0.011, fps: 90, head: pose(0.5), left: pose(-0.75), right: pose(0.75) },
+        ],
+        notes: vec![
+            ReplayNote {
+                note_id: bsor::calc_note_id(ReplayNoteKind::Normal, 1, 0, 1, 1),
+                event_time: 2.0,
+                spawn_time: 2.0,
+                event_type: ReplayNoteEvent::Good,
+                cut_info_opt: Some(cut_info),
+            },
+            ReplayNote {
+                note_id: bsor::calc_note_id(ReplayNoteKind::Normal, 2, 1, 0, 8),
+                event_time: 3.25,
+                spawn_time: 3.0,
+                event_type: ReplayNoteEvent::Miss,
+                cut_info_opt: None,
+            },
+        ],
+        walls: vec![ReplayWall { wall_id: bsor::calc_wall_id(0, 2, 1), energy: 0.4, time: 5.0, spawn_time: 4.5 }],
+        heights: vec![ReplayHeight { height: 1.75, time: 0.5 }],
+        pauses: vec![ReplayPause { duration: 3, time: 7.5 }],
+    };
+
+    let mut data = Vec::new();
+    replay.write(&mut data).expect("Unable to write replay");
+
+    let read = Replay::read(&mut Cursor::new(data)).expect("Unable to read replay");
+
+    assert_eq!(read.info.hash, replay.info.hash);
+    assert_eq!(read.info.modifiers, "NF");
+    assert_eq!(read.info.jump_distance, 24.0);
+    assert_eq!(read.info.fail_time, 12.5);
+
+    assert_eq!(read.frames.len(), 2);
+
+    for (frame, expected) in read.frames.iter().zip(&replay.frames) {
+        assert_eq!(frame.time, expected.time);
+        assert_eq!(frame.fps, expected.fps);
+
+        for (pose, expected_pose) in [(&frame.head, &expected.head), (&frame.left, &expected.left), (&frame.right, &expected.right)] {
+            assert_eq!(pose.pos, expected_pose.pos);
+            assert_eq!(pose.rot, expected_pose.rot);
+        }
+    }
+
+    assert_eq!(read.notes.len(), 2);
+
+    for (note, expected) in read.notes.iter().zip(&replay.notes) {
+        assert_eq!(note.note_id, expected.note_id);
+        assert_eq!(note.event_time, expected.event_time);
+        assert_eq!(note.spawn_time, expected.spawn_time);
+        assert!(note.event_type == expected.event_type);
+        assert_eq!(note.cut_info_opt.is_some(), expected.cut_info_opt.is_some());
+    }
+
+    let read_cut_info = read.notes[0].cut_info_opt.expect("Cut info is missing");
+
+    assert!(read_cut_info.speed_ok && read_cut_info.direction_ok && !read_cut_info.saber_type_ok && !read_cut_info.was_cut_too_soon);
+    assert_eq!(read_cut_info.saber_speed, cut_info.saber_speed);
+    assert_eq!(read_cut_info.saber_dir, cut_info.saber_dir);
+    assert_eq!(read_cut_info.saber_type, cut_info.saber_type);
+    assert_eq!(read_cut_info.time_deviation, cut_info.time_deviation);
+    assert_eq!(read_cut_info.cut_dir_deviation, cut_info.cut_dir_deviation);
+    assert_eq!(read_cut_info.cut_point, cut_info.cut_point);
+    assert_eq!(read_cut_info.cut_normal, cut_info.cut_normal);
+    assert_eq!(read_cut_info.cut_distance_to_center, cut_info.cut_distance_to_center);
+    assert_eq!(read_cut_info.cut_angle, cut_info.cut_angle);
+    assert_eq!(read_cut_info.before_cut_rating, cut_info.before_cut_rating);
+    assert_eq!(read_cut_info.after_cut_rating, cut_info.after_cut_rating);
+
+    // The recorded cut is scored the same, after reading it back.
+
+    assert_eq!(score::calc_note_score(&read.notes[0]), score::calc_note_score(&replay.notes[0]));
+    assert!(matches!(bsor::calc_note_kind(read.notes[1].note_id), ReplayNoteKind::Normal));
+
+    assert_eq!(read.walls.len(), 1);
+    assert_eq!(read.walls[0].wall_id, replay.walls[0].wall_id);
+    assert_eq!(read.walls[0].energy, 0.4);
+    assert_eq!(read.walls[0].time, 5.0);
+    assert_eq!(read.walls[0].spawn_time, 4.5);
+
+    assert_eq!(read.heights.len(), 1);
+    assert_eq!(read.heights[0].height, 1.75);
+    assert_eq!(read.heights[0].time, 0.5);
+
+    assert_eq!(read.pauses.len(), 1);
+    assert_eq!(read.pauses[0].duration, 3);
+    assert_eq!(read.pauses[0].time, 7.5);
+}
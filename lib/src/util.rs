@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Condvar, Mutex};
 
+use serde::Serialize;
+
 pub struct IndexMap<T> {
     vec: Vec<T>,
     map: HashMap<T, usize>,
@@ -36,6 +39,11 @@ impl<T: Eq + Hash + Clone> IndexMap<T> {
     }
 }
 
+// Name of a unit variant, as it is serialized (e.g. the characteristics and difficulties of the beatmaps).
+pub fn to_serde_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value).ok().and_then(|value| value.as_str().map(str::to_string)).unwrap_or_default()
+}
+
 pub struct MuCo<T> {
     pub mutex: Mutex<T>,
     pub cond: Condvar,
@@ -111,3 +119,64 @@ impl Stats {
         self.inner_mutex.lock().unwrap().inst_buf = inst_buf;
     }
 }
+
+pub type SettingsRc = Arc<Settings>;
+
+// User-level settings, which are kept between scenes.
+
+pub struct Settings {
+    // Settings are changed from the UI thread, that's the reason for Mutex.
+
+    inner_mutex: Mutex<SettingsInner>,
+    replay_dir_opt: Option<PathBuf>, // The recorded replays are written here, it is given by the target
+}
+
+#[derive(Copy, Clone)]
+pub struct SettingsInner {
+    pub reaction_time_opt: Option<f32>, // Overrides the reaction time of the beatmaps [s]
+    pub custom_data: bool, // Applies the per-note custom data of the beatmaps (e.g. Chroma colors)
+    pub no_fail: bool, // The song is not stopped, if the energy is depleted (the score is marked)
+}
+
+impl Settings {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let inner = SettingsInner {
+            reaction_time_opt: None,
+            custom_data: false,
+            no_fail: false,
+        };
+
+        Self {
+            inner_mutex: Mutex::new(inner),
+            replay_dir_opt: None,
+        }
+    }
+
+    pub fn with_replay_dir(self, replay_dir: PathBuf) -> Self {
+        Self {
+            replay_dir_opt: Some(replay_dir),
+            ..self
+        }
+    }
+
+    pub fn get_inner(&self) -> SettingsInner {
+        *self.inner_mutex.lock().unwrap()
+    }
+
+    pub fn set_reaction_time_opt(&self, reaction_time_opt: Option<f32>) {
+        self.inner_mutex.lock().unwrap().reaction_time_opt = reaction_time_opt;
+    }
+
+    pub fn set_custom_data(&self, custom_data: bool) {
+        self.inner_mutex.lock().unwrap().custom_data = custom_data;
+    }
+
+    pub fn set_no_fail(&self, no_fail: bool) {
+        self.inner_mutex.lock().unwrap().no_fail = no_fail;
+    }
+
+    pub fn get_replay_dir_opt(&self) -> Option<&Path> {
+        self.replay_dir_opt.as_deref()
+    }
+}
@@ -13,9 +13,9 @@ use winit::window::{Window, WindowId};
 use rsaber_lib::{APP_NAME, Main};
 use rsaber_lib::asset::EmbedAssetManager;
 use rsaber_lib::cgmath::{Deg, InnerSpace, Matrix3, Quaternion, Rotation3, Vector3};
-use rsaber_lib::output::{WindowBegin, WindowOutput};
+use rsaber_lib::output::{Frame, WindowBegin, WindowOutput};
 use rsaber_lib::scene::{SceneInput, ScenePose, ScenePoseScroll};
-use rsaber_lib::util::Stats;
+use rsaber_lib::util::{Settings, Stats};
 use rsaber_lib::wgpu::{InstanceDescriptor, SurfaceTarget};
 
 const COMMENT: &str = "You can use keys w-a-s-d to move, z-x to change elevation, r to reset view and arrow keys to rotate camera. Interaction with UI controls can be done with mouse.";
@@ -26,6 +26,7 @@ const ROT_SPEED: f32 = 50.0; // [deg/s]
 const MOVE_SPEED: f32 = 5.0; // [m/s]
 const SCROLL_LINE_SPEED: f32 = 30.0; // [pixels/line]
 const SCROLL_PIXEL_FACTOR: f32 = 5.0;
+const REPLAY_DIR: &str = "replays";
 
 struct App {
     asset_mgr: Option<EmbedAssetManager>,
@@ -69,7 +70,8 @@ impl ApplicationHandler for App {
             let window = Arc::new(event_loop.create_window(window_attrs).expect("Unable to create window"));
             let output = WindowOutput::new(InstanceDescriptor::new_with_display_handle(Box::new(event_loop.owned_display_handle())), SurfaceTarget::from(Arc::clone(&window))).block_on();
             let stats = Stats::new(COMMENT);
-            let main = Main::new(self.asset_mgr.take().unwrap(), output.get_info(), stats);
+            let settings = Settings::new().with_replay_dir(REPLAY_DIR.into());
+            let main = Main::new(self.asset_mgr.take().unwrap(), output.get_info(), stats, settings);
 
             let audio_engine = main.get_audio_engine();
             audio_engine.start();
@@ -215,6 +217,8 @@ impl ApplicationHandler for App {
                         let mut scene_input = SceneInput {
                             pose_l_opt: None,
                             pose_r_opt: None,
+                            cam_pos: frame.get_cam_pos(),
+                            cam_rot: frame.get_cam_rot(),
                         };
 
                         let pose;
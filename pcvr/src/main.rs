@@ -2,13 +2,16 @@ use rsaber_lib::Main;
 use rsaber_lib::asset::EmbedAssetManager;
 use rsaber_lib::openxr;
 use rsaber_lib::output::XROutput;
-use rsaber_lib::util::Stats;
+use rsaber_lib::util::{Settings, Stats};
+
+const REPLAY_DIR: &str = "replays";
 
 fn main() {
     let asset_mgr = EmbedAssetManager::new();
     let output = XROutput::new(openxr::Entry::linked()); // Use compiled-in OpenXR loader.
     let stats = Stats::new("");
-    let main = Main::new(asset_mgr, output.get_info(), stats);
+    let settings = Settings::new().with_replay_dir(REPLAY_DIR.into());
+    let main = Main::new(asset_mgr, output.get_info(), stats, settings);
 
     // Do XR loop.
 